@@ -0,0 +1,159 @@
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+
+use atelier_core::{asset_uuid, AssetUuid};
+use serde::{Deserialize, Serialize};
+use structopt::StructOpt;
+
+use atelier_loader::rpc_loader::RpcLoader;
+
+use crate::daemon::AssetDaemonOpt;
+use crate::pack_loader::PackLoader;
+use crate::resources::AssetSource;
+
+/// Settings shared by the demo and the asset daemon it runs. These can be read from a RON file
+/// (any field can be left out to use its default) and overridden on the command line:
+///
+/// ```ron
+/// (
+///     db_dir: ".assets_db",
+///     address: "127.0.0.1:9999",
+///     asset_dirs: ["assets"],
+///     startup_prefab: "3991506e-ed7e-4bcb-8cfd-3366b31a6439",
+/// )
+/// ```
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
+pub struct AppConfig {
+    /// Path to the asset metadata database directory
+    pub db_dir: PathBuf,
+
+    /// Socket address for the daemon to listen for connections
+    pub address: SocketAddr,
+
+    /// Directories for the daemon to watch for assets
+    pub asset_dirs: Vec<PathBuf>,
+
+    /// The prefab the editor opens when it starts
+    pub startup_prefab: AssetUuid,
+
+    /// Load assets from this pack file (see build_pack) instead of running the asset daemon
+    pub pack: Option<PathBuf>,
+}
+
+impl Default for AppConfig {
+    fn default() -> Self {
+        AppConfig {
+            db_dir: PathBuf::from(".assets_db"),
+            address: "127.0.0.1:9999".parse().unwrap(),
+            asset_dirs: vec![PathBuf::from("assets")],
+            startup_prefab: asset_uuid!("3991506e-ed7e-4bcb-8cfd-3366b31a6439"),
+            pack: None,
+        }
+    }
+}
+
+impl AppConfig {
+    /// Reads the config from a RON file
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let config = std::fs::read_to_string(path)
+            .map_err(|e| format!("failed to read {}: {}", path.display(), e))?;
+        ron::de::from_str(&config).map_err(|e| format!("failed to read {}: {}", path.display(), e))
+    }
+
+    /// Reads the config file given on the command line, if any, and applies the other command
+    /// line flags on top of it
+    pub fn from_args() -> Result<Self, String> {
+        AppConfigOpt::from_args().into_config()
+    }
+
+    /// The daemon settings from this config, for daemon::run_with and daemon::spawn_with
+    pub fn daemon_opt(&self) -> AssetDaemonOpt {
+        AssetDaemonOpt {
+            db_dir: self.db_dir.clone(),
+            address: self.address,
+            asset_dirs: self.asset_dirs.clone(),
+        }
+    }
+
+    /// Creates an asset source that reads from the pack file if one is set, or else connects to
+    /// the daemon at the configured address
+    pub fn create_asset_source(&self) -> Result<AssetSource, String> {
+        match &self.pack {
+            Some(pack) => Ok(AssetSource::Pack(PackLoader::open(pack)?)),
+            None => RpcLoader::new(self.address.to_string())
+                .map(AssetSource::Rpc)
+                .map_err(|e| format!("failed to create a loader for {}: {}", self.address, e)),
+        }
+    }
+}
+
+/// Command line flags for AppConfig. Flags that are given override the config file.
+///
+/// # Examples
+///
+/// ```bash
+/// demo --config demo.ron --address "127.0.0.1:9998"
+/// ```
+#[derive(StructOpt)]
+pub struct AppConfigOpt {
+    /// A RON file to read the config from
+    #[structopt(long, parse(from_os_str))]
+    pub config: Option<PathBuf>,
+
+    /// Path to the asset metadata database directory
+    #[structopt(name = "db", long, parse(from_os_str))]
+    pub db_dir: Option<PathBuf>,
+
+    /// Socket address for the daemon to listen for connections, e.g. "127.0.0.1:9999"
+    #[structopt(short, long)]
+    pub address: Option<SocketAddr>,
+
+    /// A directory to watch for assets. Can be given more than once
+    #[structopt(long = "asset-dir", parse(from_os_str))]
+    pub asset_dirs: Vec<PathBuf>,
+
+    /// The UUID of the prefab to open at startup
+    #[structopt(long, parse(try_from_str = parse_asset_uuid))]
+    pub startup_prefab: Option<AssetUuid>,
+
+    /// Load assets from a pack file (see build_pack) instead of running the asset daemon
+    #[structopt(long, parse(from_os_str))]
+    pub pack: Option<PathBuf>,
+}
+
+impl AppConfigOpt {
+    pub fn into_config(self) -> Result<AppConfig, String> {
+        let mut config = match &self.config {
+            Some(path) => AppConfig::load(path)?,
+            None => AppConfig::default(),
+        };
+
+        if let Some(db_dir) = self.db_dir {
+            config.db_dir = db_dir;
+        }
+
+        if let Some(address) = self.address {
+            config.address = address;
+        }
+
+        if !self.asset_dirs.is_empty() {
+            config.asset_dirs = self.asset_dirs;
+        }
+
+        if let Some(startup_prefab) = self.startup_prefab {
+            config.startup_prefab = startup_prefab;
+        }
+
+        if self.pack.is_some() {
+            config.pack = self.pack;
+        }
+
+        Ok(config)
+    }
+}
+
+/// Parses a string as an asset UUID.
+pub fn parse_asset_uuid(s: &str) -> Result<AssetUuid, uuid::Error> {
+    uuid::Uuid::parse_str(s).map(|uuid| AssetUuid(*uuid.as_bytes()))
+}
@@ -1,14 +1,117 @@
 use atelier_loader::{
-    crossbeam_channel::Sender,
+    crossbeam_channel::{Receiver, Sender},
     handle::{AssetHandle, RefOp, TypedAssetStorage},
     AssetLoadOp, AssetStorage, AssetTypeId, LoadHandle, LoaderInfoProvider, TypeUuid,
 };
 use mopa::{mopafy, Any};
-use std::{sync::Mutex, collections::HashMap, error::Error, sync::Arc};
+use std::{sync::Mutex, collections::HashMap, collections::VecDeque, error::Error, sync::Arc};
+use std::marker::PhantomData;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+// The number of committed versions of each asset that are remembered by the version history
+const VERSION_HISTORY_LENGTH: usize = 16;
+
+// The number of threads that load assets registered with add_async_storage
+const ASSET_WORKER_THREAD_COUNT: usize = 2;
+
+/// Work done after an asset is deserialized and before it is committed, such as decoding or
+/// cooking. For asset types registered with add_async_storage, this runs on a worker thread
+pub type AssetPostProcess<A> = fn(&mut A) -> Result<(), String>;
+
+/// A change to an asset of type A in storage. Use GenericAssetStorage::subscribe to receive these
+pub struct AssetEvent<A> {
+    pub load_handle: LoadHandle,
+    pub kind: AssetEventKind,
+    _asset_type: PhantomData<fn() -> A>,
+}
+
+impl<A> AssetEvent<A> {
+    fn new(
+        load_handle: LoadHandle,
+        kind: AssetEventKind,
+    ) -> Self {
+        AssetEvent {
+            load_handle,
+            kind,
+            _asset_type: PhantomData,
+        }
+    }
+}
+
+// Derived Clone and Debug would require A to implement them
+impl<A> Clone for AssetEvent<A> {
+    fn clone(&self) -> Self {
+        AssetEvent::new(self.load_handle, self.kind.clone())
+    }
+}
+
+impl<A> std::fmt::Debug for AssetEvent<A> {
+    fn fmt(
+        &self,
+        f: &mut std::fmt::Formatter<'_>,
+    ) -> std::fmt::Result {
+        f.debug_struct("AssetEvent")
+            .field("load_handle", &self.load_handle)
+            .field("kind", &self.kind)
+            .finish()
+    }
+}
+
+/// What happened to the asset in an AssetEvent
+#[derive(Debug, Clone)]
+pub enum AssetEventKind {
+    /// The first version of the asset was committed
+    Loaded { version: u32 },
+
+    /// A new version of the asset replaced the previous one, usually because its source file
+    /// changed. The old version stays readable (see get_asset_at_version) until the next frame
+    /// begins, and after that for as long as this event or a clone of old_version_ref is held
+    Reloaded {
+        old_version: u32,
+        new_version: u32,
+        old_version_ref: ReplacedVersionRef,
+    },
+
+    /// The asset is no longer referenced and was removed from storage
+    Freed,
+
+    /// Loading a version of the asset failed. If a previous version was loaded, it stays loaded
+    Failed { error: String },
+}
+
+/// Keeps a replaced version of an asset readable while it, or any clone of it, is held. Each
+/// replaced version is reference counted separately, so holding one doesn't keep other versions of
+/// the asset loaded
+#[derive(Debug, Clone)]
+pub struct ReplacedVersionRef(Arc<()>);
+
+impl ReplacedVersionRef {
+    fn new() -> Self {
+        ReplacedVersionRef(Arc::new(()))
+    }
+
+    // Returns true if anything other than the storage holds a reference
+    fn is_held(&self) -> bool {
+        Arc::strong_count(&self.0) > 1
+    }
+}
+
+// Sends an event to one subscriber as an AssetEvent of the subscribed type. Returns false if the
+// subscriber's receiver was dropped
+type AssetEventSubscriber = Box<dyn Fn(LoadHandle, &AssetEventKind) -> bool + Send>;
 
 pub struct GenericAssetStorage {
     storage: Mutex<HashMap<AssetTypeId, Box<dyn TypedStorage>>>,
     refop_sender: Arc<Sender<RefOp>>,
+
+    // Everything that subscribed to events, by asset type
+    subscribers: Mutex<HashMap<AssetTypeId, Vec<AssetEventSubscriber>>>,
+
+    // Incremented by begin_frame(). Used to decide when replaced versions of assets can be freed
+    frame: AtomicU64,
+
+    // Created when the first asynchronously loaded asset type is added
+    worker_pool: Mutex<Option<Arc<AssetWorkerPool>>>,
 }
 
 impl GenericAssetStorage {
@@ -16,9 +119,161 @@ impl GenericAssetStorage {
         Self {
             storage: Mutex::new(HashMap::new()),
             refop_sender,
+            subscribers: Mutex::new(HashMap::new()),
+            frame: AtomicU64::new(0),
+            worker_pool: Mutex::new(None),
         }
     }
 
+    /// Adds storage for an asset type that is deserialized and post-processed on a worker thread
+    /// instead of the thread that calls AssetResource::update(). The load completes when the work
+    /// finishes, so large assets don't stall the frame that receives them.
+    ///
+    /// Handles can only be resolved on the thread that receives the asset data, so asset types
+    /// that contain handles must use add_storage instead
+    pub fn add_async_storage<T: TypeUuid + for<'a> serde::Deserialize<'a> + 'static + Send>(
+        &self,
+        post_process: Option<AssetPostProcess<T>>,
+    ) {
+        let worker_pool = self
+            .worker_pool
+            .lock()
+            .unwrap()
+            .get_or_insert_with(|| Arc::new(AssetWorkerPool::new(ASSET_WORKER_THREAD_COUNT)))
+            .clone();
+
+        let (completed_tx, completed_rx) = atelier_loader::crossbeam_channel::unbounded();
+        let mut storage = Storage::<T>::new(self.refop_sender.clone());
+        storage.async_load = Some(AsyncLoad {
+            worker_pool,
+            post_process,
+            completed_tx,
+            completed_rx,
+        });
+
+        let mut storages = self.storage.lock().unwrap();
+        storages.insert(AssetTypeId(T::UUID), Box::new(storage));
+    }
+
+    /// Call once per frame before the loader commits new asset versions. When a new version of an
+    /// asset is committed, the version it replaces stays readable at least until the next call to
+    /// begin_frame. After that, it's freed once every Reloaded event for it (and every
+    /// ReplacedVersionRef cloned from one) has been dropped, so a subscriber that keeps the event
+    /// until it has compared the old version against the new one can read both
+    pub fn begin_frame(&self) {
+        let frame = self.frame.fetch_add(1, Ordering::SeqCst) + 1;
+        for (asset_type, storage) in self.storage.lock().unwrap().iter_mut() {
+            storage.free_replaced_versions(frame);
+
+            // Loads that failed on worker threads are reported here
+            for (load_handle, error) in storage.take_failed_loads() {
+                self.publish(asset_type, load_handle, AssetEventKind::Failed { error });
+            }
+        }
+    }
+
+    /// Returns a receiver for events about assets of the given type. Events are queued until they
+    /// are received, so poll it regularly (i.e. once a frame). Dropping the receiver unsubscribes
+    pub fn subscribe<A: TypeUuid + 'static>(&self) -> Receiver<AssetEvent<A>> {
+        let (tx, rx) = atelier_loader::crossbeam_channel::unbounded();
+        let subscriber: AssetEventSubscriber = Box::new(move |load_handle, kind| {
+            tx.send(AssetEvent::new(load_handle, kind.clone())).is_ok()
+        });
+
+        self.subscribers
+            .lock()
+            .unwrap()
+            .entry(AssetTypeId(A::UUID))
+            .or_default()
+            .push(subscriber);
+        rx
+    }
+
+    /// Loads an asset for loaders that don't use AssetLoadOp (like PackLoader). The asset is
+    /// deserialized on the calling thread, even if its type was added with add_async_storage, and
+    /// is committed immediately
+    pub fn load_asset(
+        &self,
+        loader_info: &dyn LoaderInfoProvider,
+        asset_type: &AssetTypeId,
+        data: &[u8],
+        load_handle: LoadHandle,
+        version: u32,
+    ) -> Result<(), Box<dyn Error>> {
+        let mut storages = self.storage.lock().unwrap();
+        let storage = storages
+            .get_mut(asset_type)
+            .ok_or_else(|| UnknownAssetTypeError::new(asset_type, load_handle))?;
+
+        let frame = self.frame.load(Ordering::SeqCst);
+        match storage.load_and_commit(loader_info, data, load_handle, version, frame) {
+            Ok(replaced) => {
+                self.publish(asset_type, load_handle, committed_event(version, replaced));
+                Ok(())
+            }
+            Err(e) => {
+                let error = e.to_string();
+                self.publish(asset_type, load_handle, AssetEventKind::Failed { error });
+                Err(e)
+            }
+        }
+    }
+
+    fn publish(
+        &self,
+        asset_type: &AssetTypeId,
+        load_handle: LoadHandle,
+        kind: AssetEventKind,
+    ) {
+        if let Some(subscribers) = self.subscribers.lock().unwrap().get_mut(asset_type) {
+            // Sending fails if the receiver was dropped, in which case the subscriber is removed
+            subscribers.retain(|subscriber| subscriber(load_handle, &kind));
+        }
+    }
+
+    /// Returns the given version of an asset if it is still loaded. This is the current version, or
+    /// a version it replaced that hasn't been freed yet (see begin_frame)
+    pub fn get_asset_at_version<A: TypeUuid + 'static + Send, T: AssetHandle>(
+        &self,
+        handle: &T,
+        version: u32,
+    ) -> Option<&A> {
+        self.typed_storage::<A>().get_at_version(handle, version)
+    }
+
+    /// Returns the version of an asset that the current version replaced, if it hasn't been freed
+    /// yet (see begin_frame). Systems can compare this against the current version to see what
+    /// changed in a reload
+    pub fn get_replaced_asset_with_version<A: TypeUuid + 'static + Send, T: AssetHandle>(
+        &self,
+        handle: &T,
+    ) -> Option<(&A, u32)> {
+        self.typed_storage::<A>().get_replaced_asset_with_version(handle)
+    }
+
+    /// Returns the versions of an asset that have been committed, oldest first. Only the most
+    /// recent versions are remembered, and only the last one and any replaced versions that haven't
+    /// been freed yet (see begin_frame) are still loaded
+    pub fn get_version_history<A: TypeUuid + 'static + Send, T: AssetHandle>(
+        &self,
+        handle: &T,
+    ) -> Vec<u32> {
+        self.typed_storage::<A>().get_version_history(handle)
+    }
+
+    // Returns the storage for an asset type. The reference outlives the lock on the storages, which
+    // can probably be unsound, but I don't have the energy to fix it right now
+    fn typed_storage<A: TypeUuid + 'static + Send>(&self) -> &Storage<A> {
+        let storages = self.storage.lock().unwrap();
+        let storage: *const Storage<A> = storages
+            .get(&AssetTypeId(A::UUID))
+            .expect("unknown asset type")
+            .as_ref()
+            .downcast_ref::<Storage<A>>()
+            .expect("failed to downcast");
+        unsafe { &*storage }
+    }
+
     pub fn add_storage<T: TypeUuid + for<'a> serde::Deserialize<'a> + 'static + Send>(&self) {
         let mut storages = self.storage.lock().unwrap();
         storages.insert(
@@ -32,19 +287,207 @@ struct AssetState<A> {
     version: u32,
     asset: A,
 }
+
+type AssetJob = Box<dyn FnOnce() + Send>;
+
+// Threads that run asset loading jobs. The threads exit when the pool is dropped
+struct AssetWorkerPool {
+    job_tx: Sender<AssetJob>,
+}
+
+impl AssetWorkerPool {
+    fn new(thread_count: usize) -> Self {
+        let (job_tx, job_rx) = atelier_loader::crossbeam_channel::unbounded::<AssetJob>();
+        for i in 0..thread_count {
+            let job_rx = job_rx.clone();
+            std::thread::Builder::new()
+                .name(format!("asset worker {}", i))
+                .spawn(move || {
+                    for job in job_rx {
+                        job();
+                    }
+                })
+                .expect("failed to spawn asset worker thread");
+        }
+
+        AssetWorkerPool { job_tx }
+    }
+
+    fn spawn(
+        &self,
+        job: AssetJob,
+    ) {
+        self.job_tx.send(job).expect("asset worker threads exited");
+    }
+}
+
+// An asset that finished loading (or failed to) on a worker thread
+struct CompletedLoad<A> {
+    load_id: u64,
+    load_handle: LoadHandle,
+    version: u32,
+    result: Result<A, String>,
+}
+
+// Set for storages added with add_async_storage
+struct AsyncLoad<A> {
+    worker_pool: Arc<AssetWorkerPool>,
+    post_process: Option<AssetPostProcess<A>>,
+    completed_tx: Sender<CompletedLoad<A>>,
+    completed_rx: Receiver<CompletedLoad<A>>,
+}
+
+#[derive(Debug)]
+struct AssetLoadError(String);
+
+impl std::fmt::Display for AssetLoadError {
+    fn fmt(
+        &self,
+        f: &mut std::fmt::Formatter<'_>,
+    ) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl Error for AssetLoadError {}
+
+/// The daemon served an asset whose type was never registered with add_storage
+#[derive(Debug)]
+pub struct UnknownAssetTypeError {
+    pub asset_type: AssetTypeId,
+    pub load_handle: LoadHandle,
+}
+
+impl UnknownAssetTypeError {
+    fn new(
+        asset_type: &AssetTypeId,
+        load_handle: LoadHandle,
+    ) -> Self {
+        UnknownAssetTypeError {
+            asset_type: *asset_type,
+            load_handle,
+        }
+    }
+}
+
+impl std::fmt::Display for UnknownAssetTypeError {
+    fn fmt(
+        &self,
+        f: &mut std::fmt::Formatter<'_>,
+    ) -> std::fmt::Result {
+        write!(
+            f,
+            "Cannot load {:?}: no storage was added for asset type {}",
+            self.load_handle,
+            uuid::Uuid::from_bytes(self.asset_type.0)
+        )
+    }
+}
+
+impl Error for UnknownAssetTypeError {}
+
+// A version of an asset that was replaced by a newer one, the frame that happened in, and the
+// reference count that Reloaded events hold on it
+struct ReplacedAssetState<A> {
+    state: AssetState<A>,
+    frame: u64,
+    version_ref: ReplacedVersionRef,
+}
+
 pub struct Storage<A: TypeUuid> {
     refop_sender: Arc<Sender<RefOp>>,
     assets: HashMap<LoadHandle, AssetState<A>>,
-    uncommitted: HashMap<LoadHandle, AssetState<A>>,
+
+    // Versions that were replaced and haven't been freed yet, oldest first. A handle can have
+    // several if it's reloaded more than once before the older versions are freed
+    replaced: HashMap<LoadHandle, VecDeque<ReplacedAssetState<A>>>,
+
+    version_history: HashMap<LoadHandle, VecDeque<u32>>,
+
+    // Versions that finished loading and are waiting for the loader to commit them. A handle can
+    // have more than one if a newer version finishes loading before the older one is committed
+    uncommitted: HashMap<(LoadHandle, u32), A>,
+    async_load: Option<AsyncLoad<A>>,
+
+    // Loads running on worker threads, by load ID. Freeing a handle removes its loads, so their
+    // results are dropped when they complete. A freed handle can be requested again while its old
+    // load is still running, so loads are tracked by ID rather than by handle
+    loads_in_progress: HashMap<u64, LoadHandle>,
+    next_load_id: u64,
+
+    // Loads that failed on a worker thread and haven't been reported yet
+    failed_loads: Vec<(LoadHandle, String)>,
 }
 impl<A: TypeUuid> Storage<A> {
     fn new(sender: Arc<Sender<RefOp>>) -> Self {
         Self {
             refop_sender: sender,
             assets: HashMap::new(),
+            replaced: HashMap::new(),
+            version_history: HashMap::new(),
             uncommitted: HashMap::new(),
+            async_load: None,
+            loads_in_progress: HashMap::new(),
+            next_load_id: 0,
+            failed_loads: Vec::new(),
         }
     }
+    fn receive_completed_loads(&mut self) {
+        if let Some(async_load) = &self.async_load {
+            for completed in async_load.completed_rx.try_iter() {
+                // The handle was freed after this load started
+                if self.loads_in_progress.remove(&completed.load_id).is_none() {
+                    continue;
+                }
+
+                let load_handle = completed.load_handle;
+                match completed.result {
+                    Ok(asset) => {
+                        self.uncommitted
+                            .insert((load_handle, completed.version), asset);
+                    }
+                    Err(e) => self.failed_loads.push((load_handle, e)),
+                }
+            }
+        }
+    }
+    fn get_at_version<T: AssetHandle>(
+        &self,
+        handle: &T,
+        version: u32,
+    ) -> Option<&A> {
+        let load_handle = handle.load_handle();
+        self.assets
+            .get(&load_handle)
+            .into_iter()
+            .chain(
+                self.replaced
+                    .get(&load_handle)
+                    .into_iter()
+                    .flatten()
+                    .map(|replaced| &replaced.state),
+            )
+            .find(|a| a.version == version)
+            .map(|a| &a.asset)
+    }
+    fn get_replaced_asset_with_version<T: AssetHandle>(
+        &self,
+        handle: &T,
+    ) -> Option<(&A, u32)> {
+        self.replaced
+            .get(&handle.load_handle())
+            .and_then(|replaced| replaced.back())
+            .map(|replaced| (&replaced.state.asset, replaced.state.version))
+    }
+    fn get_version_history<T: AssetHandle>(
+        &self,
+        handle: &T,
+    ) -> Vec<u32> {
+        self.version_history
+            .get(&handle.load_handle())
+            .map(|versions| versions.iter().cloned().collect())
+            .unwrap_or_default()
+    }
     fn get<T: AssetHandle>(
         &self,
         handle: &T,
@@ -73,53 +516,19 @@ impl<A: TypeUuid + for<'a> serde::Deserialize<'a> + 'static + Send> TypedAssetSt
         &self,
         handle: &T,
     ) -> Option<&A> {
-        // This transmute can probably be unsound, but I don't have the energy to fix it right now
-        unsafe {
-            std::mem::transmute(
-                self.storage
-                    .lock()
-                    .unwrap()
-                    .get(&AssetTypeId(A::UUID))
-                    .expect("unknown asset type")
-                    .as_ref()
-                    .downcast_ref::<Storage<A>>()
-                    .expect("failed to downcast")
-                    .get(handle),
-            )
-        }
+        self.typed_storage::<A>().get(handle)
     }
     fn get_version<T: AssetHandle>(
         &self,
         handle: &T,
     ) -> Option<u32> {
-        self.storage
-            .lock()
-            .unwrap()
-            .get(&AssetTypeId(A::UUID))
-            .expect("unknown asset type")
-            .as_ref()
-            .downcast_ref::<Storage<A>>()
-            .expect("failed to downcast")
-            .get_version(handle)
+        self.typed_storage::<A>().get_version(handle)
     }
     fn get_asset_with_version<T: AssetHandle>(
         &self,
         handle: &T,
     ) -> Option<(&A, u32)> {
-        // This transmute can probably be unsound, but I don't have the energy to fix it right now
-        unsafe {
-            std::mem::transmute(
-                self.storage
-                    .lock()
-                    .unwrap()
-                    .get(&AssetTypeId(A::UUID))
-                    .expect("unknown asset type")
-                    .as_ref()
-                    .downcast_ref::<Storage<A>>()
-                    .expect("failed to downcast")
-                    .get_asset_with_version(handle),
-            )
-        }
+        self.typed_storage::<A>().get_asset_with_version(handle)
     }
 }
 pub trait TypedStorage: Any + Send {
@@ -131,15 +540,34 @@ pub trait TypedStorage: Any + Send {
         load_op: AssetLoadOp,
         version: u32,
     ) -> Result<(), Box<dyn Error>>;
+    /// Returns the version that was replaced and the reference that keeps it loaded, if there was
+    /// one
     fn commit_asset_version(
         &mut self,
         handle: LoadHandle,
         version: u32,
-    );
+        frame: u64,
+    ) -> Option<(u32, ReplacedVersionRef)>;
     fn free(
         &mut self,
         handle: LoadHandle,
     );
+    fn free_replaced_versions(
+        &mut self,
+        frame: u64,
+    );
+    fn take_failed_loads(&mut self) -> Vec<(LoadHandle, String)>;
+
+    /// Deserializes and commits an asset immediately. Returns the version that was replaced and
+    /// the reference that keeps it loaded, if there was one
+    fn load_and_commit(
+        &mut self,
+        loader_info: &dyn LoaderInfoProvider,
+        data: &[u8],
+        load_handle: LoadHandle,
+        version: u32,
+        frame: u64,
+    ) -> Result<Option<(u32, ReplacedVersionRef)>, Box<dyn Error>>;
 }
 mopafy!(TypedStorage);
 impl<A: for<'a> serde::Deserialize<'a> + 'static + TypeUuid + Send> TypedStorage for Storage<A> {
@@ -151,14 +579,61 @@ impl<A: for<'a> serde::Deserialize<'a> + 'static + TypeUuid + Send> TypedStorage
         load_op: AssetLoadOp,
         version: u32,
     ) -> Result<(), Box<dyn Error>> {
+        if let Some(async_load) = &self.async_load {
+            let load_id = self.next_load_id;
+            self.next_load_id += 1;
+            self.loads_in_progress.insert(load_id, load_handle);
+
+            let data = data.to_vec();
+            let post_process = async_load.post_process;
+            let completed_tx = async_load.completed_tx.clone();
+            async_load.worker_pool.spawn(Box::new(move || {
+                let result = bincode::deserialize::<A>(&data)
+                    .map_err(|e| e.to_string())
+                    .and_then(|mut asset| {
+                        if let Some(post_process) = post_process {
+                            post_process(&mut asset)?;
+                        }
+                        Ok(asset)
+                    });
+
+                match result {
+                    Ok(asset) => {
+                        log::info!("{} bytes loaded for {:?}", data.len(), load_handle);
+
+                        // The asset must be queued before the load completes, since completing it
+                        // allows the loader to commit it
+                        let _ = completed_tx.send(CompletedLoad {
+                            load_id,
+                            load_handle,
+                            version,
+                            result: Ok(asset),
+                        });
+                        load_op.complete();
+                    }
+                    Err(e) => {
+                        log::error!("Failed to load {:?}: {}", load_handle, e);
+                        let _ = completed_tx.send(CompletedLoad {
+                            load_id,
+                            load_handle,
+                            version,
+                            result: Err(e.clone()),
+                        });
+                        load_op.error(AssetLoadError(e));
+                    }
+                }
+            }));
+
+            return Ok(());
+        }
+
         // To enable automatic serde of Handle, we need to set up a SerdeContext with a RefOp sender
         let asset = atelier_loader::handle::SerdeContext::with_sync(
             loader_info,
             self.refop_sender.clone(),
             || bincode::deserialize::<A>(data),
         )?;
-        self.uncommitted
-            .insert(load_handle, AssetState { asset, version });
+        self.uncommitted.insert((load_handle, version), asset);
         log::info!("{} bytes loaded for {:?}", data.len(), load_handle);
         // The loading process could be async, in which case you can delay
         // calling `load_op.complete` as it should only be done when the asset is usable.
@@ -168,27 +643,124 @@ impl<A: for<'a> serde::Deserialize<'a> + 'static + TypeUuid + Send> TypedStorage
     fn commit_asset_version(
         &mut self,
         load_handle: LoadHandle,
-        _version: u32,
-    ) {
+        version: u32,
+        frame: u64,
+    ) -> Option<(u32, ReplacedVersionRef)> {
         // The commit step is done after an asset load has completed.
         // It exists to avoid frames where an asset that was loaded is unloaded, which
-        // could happen when hot reloading. To support this case, you must support having multiple
-        // versions of an asset loaded at the same time.
-        self.assets.insert(
-            load_handle,
-            self.uncommitted
-                .remove(&load_handle)
-                .expect("asset not present when committing"),
-        );
-        log::info!("Commit {:?}", load_handle);
+        // could happen when hot reloading. The version being replaced is kept until the next frame,
+        // and after that until every Reloaded event for it has been dropped (see begin_frame).
+        self.receive_completed_loads();
+
+        let asset = self
+            .uncommitted
+            .remove(&(load_handle, version))
+            .expect("asset not present when committing");
+        let replaced = self
+            .assets
+            .insert(load_handle, AssetState { asset, version });
+
+        // Older versions that finished loading after this one are never committed
+        self.uncommitted.retain(|(uncommitted_handle, uncommitted_version), _| {
+            *uncommitted_handle != load_handle || *uncommitted_version > version
+        });
+
+        let replaced = replaced.map(|state| {
+            let version_ref = ReplacedVersionRef::new();
+            let replaced_version = state.version;
+            self.replaced
+                .entry(load_handle)
+                .or_default()
+                .push_back(ReplacedAssetState {
+                    state,
+                    frame,
+                    version_ref: version_ref.clone(),
+                });
+            (replaced_version, version_ref)
+        });
+
+        let version_history = self.version_history.entry(load_handle).or_default();
+        if version_history.len() == VERSION_HISTORY_LENGTH {
+            version_history.pop_front();
+        }
+        version_history.push_back(version);
+
+        log::info!("Commit {:?} version {}", load_handle, version);
+        replaced
     }
     fn free(
         &mut self,
         load_handle: LoadHandle,
     ) {
+        // Drop loads that completed but weren't committed, and any still running on worker threads
+        self.receive_completed_loads();
+        self.uncommitted
+            .retain(|(uncommitted_handle, _), _| *uncommitted_handle != load_handle);
+        self.failed_loads
+            .retain(|(failed_handle, _)| *failed_handle != load_handle);
+        self.loads_in_progress
+            .retain(|_, loading_handle| *loading_handle != load_handle);
+
         self.assets.remove(&load_handle);
+        self.replaced.remove(&load_handle);
+        self.version_history.remove(&load_handle);
         log::info!("Free {:?}", load_handle);
     }
+    fn free_replaced_versions(
+        &mut self,
+        frame: u64,
+    ) {
+        self.replaced.retain(|load_handle, replaced_versions| {
+            replaced_versions.retain(|replaced| {
+                let keep = replaced.frame >= frame || replaced.version_ref.is_held();
+                if !keep {
+                    log::info!(
+                        "Free replaced version {} of {:?}",
+                        replaced.state.version,
+                        load_handle
+                    );
+                }
+                keep
+            });
+            !replaced_versions.is_empty()
+        });
+    }
+    fn take_failed_loads(&mut self) -> Vec<(LoadHandle, String)> {
+        self.receive_completed_loads();
+        std::mem::take(&mut self.failed_loads)
+    }
+    fn load_and_commit(
+        &mut self,
+        loader_info: &dyn LoaderInfoProvider,
+        data: &[u8],
+        load_handle: LoadHandle,
+        version: u32,
+        frame: u64,
+    ) -> Result<Option<(u32, ReplacedVersionRef)>, Box<dyn Error>> {
+        let asset = atelier_loader::handle::SerdeContext::with_sync(
+            loader_info,
+            self.refop_sender.clone(),
+            || bincode::deserialize::<A>(data),
+        )?;
+        self.uncommitted.insert((load_handle, version), asset);
+        log::info!("{} bytes loaded for {:?}", data.len(), load_handle);
+        Ok(self.commit_asset_version(load_handle, version, frame))
+    }
+}
+
+// The event for committing the given version of an asset
+fn committed_event(
+    version: u32,
+    replaced: Option<(u32, ReplacedVersionRef)>,
+) -> AssetEventKind {
+    match replaced {
+        Some((old_version, old_version_ref)) => AssetEventKind::Reloaded {
+            old_version,
+            new_version: version,
+            old_version_ref,
+        },
+        None => AssetEventKind::Loaded { version },
+    }
 }
 
 // Untyped implementation of AssetStorage that finds the asset_type's storage and forwards the call
@@ -202,12 +774,24 @@ impl AssetStorage for GenericAssetStorage {
         load_op: AssetLoadOp,
         version: u32,
     ) -> Result<(), Box<dyn Error>> {
-        self.storage
-            .lock()
-            .unwrap()
-            .get_mut(asset_type_id)
-            .expect("unknown asset type")
-            .update_asset(loader_info, data, load_handle, load_op, version)
+        let mut storages = self.storage.lock().unwrap();
+        match storages.get_mut(asset_type_id) {
+            Some(storage) => {
+                let result = storage.update_asset(loader_info, data, load_handle, load_op, version);
+                if let Err(e) = &result {
+                    let error = e.to_string();
+                    self.publish(asset_type_id, load_handle, AssetEventKind::Failed { error });
+                }
+                result
+            }
+            None => {
+                // Fail just this load rather than everything the loader is processing
+                let error = UnknownAssetTypeError::new(asset_type_id, load_handle);
+                log::error!("{}", error);
+                load_op.error(error);
+                Ok(())
+            }
+        }
     }
     fn commit_asset_version(
         &self,
@@ -215,23 +799,26 @@ impl AssetStorage for GenericAssetStorage {
         load_handle: LoadHandle,
         version: u32,
     ) {
-        self.storage
-            .lock()
-            .unwrap()
-            .get_mut(asset_type)
-            .expect("unknown asset type")
-            .commit_asset_version(load_handle, version)
+        match self.storage.lock().unwrap().get_mut(asset_type) {
+            Some(storage) => {
+                let frame = self.frame.load(Ordering::SeqCst);
+                let replaced = storage.commit_asset_version(load_handle, version, frame);
+                self.publish(asset_type, load_handle, committed_event(version, replaced));
+            }
+            None => log::error!("{}", UnknownAssetTypeError::new(asset_type, load_handle)),
+        }
     }
     fn free(
         &self,
         asset_type_id: &AssetTypeId,
         load_handle: LoadHandle,
     ) {
-        self.storage
-            .lock()
-            .unwrap()
-            .get_mut(asset_type_id)
-            .expect("unknown asset type")
-            .free(load_handle)
+        match self.storage.lock().unwrap().get_mut(asset_type_id) {
+            Some(storage) => {
+                storage.free(load_handle);
+                self.publish(asset_type_id, load_handle, AssetEventKind::Freed);
+            }
+            None => log::error!("{}", UnknownAssetTypeError::new(asset_type_id, load_handle)),
+        }
     }
 }
@@ -0,0 +1,249 @@
+use std::net::TcpStream;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use structopt::StructOpt;
+use type_uuid::TypeUuid;
+
+use atelier_core::AssetUuid;
+use atelier_loader::rpc_loader::RpcLoader;
+use atelier_legion_demo::app_config::parse_asset_uuid;
+use atelier_legion_demo::daemon::{self, AssetDaemonOpt, DaemonHandle};
+use atelier_legion_demo::daemon_client::list_assets;
+use atelier_legion_demo::pack_file::{find_source_files, import_source_file};
+use atelier_legion_demo::pipeline::{AtlasRegionAsset, CookedPrefabAsset, ImageAsset, PrefabAsset};
+use atelier_legion_demo::AssetSource;
+
+/// Imports, validates, lists and cooks assets without opening a window, so assets can be checked
+/// and cooked on machines with no GPU. Commands that need the asset daemon use the one listening
+/// on the given address if there is one (like the one the editor runs), and otherwise run one in
+/// this process until the command finishes.
+///
+/// # Examples
+///
+/// ```bash
+/// asset_cli import --db .assets_db assets
+/// asset_cli validate
+/// asset_cli list assets
+/// asset_cli cook 3991506e-ed7e-4bcb-8cfd-3366b31a6439 --output demo_level.cooked.ron
+/// ```
+#[derive(StructOpt)]
+enum AssetCliOpt {
+    /// Runs the asset daemon until it has imported every source file that changed since it last
+    /// ran, then exits. The results are stored in its metadata database, where the editor's daemon
+    /// picks them up
+    Import {
+        #[structopt(flatten)]
+        daemon: AssetDaemonOpt,
+    },
+
+    /// Runs the importers on every source file once and reports any that fail. Nothing is written,
+    /// the daemon (or build_pack) stores the results
+    Validate {
+        /// The asset directories to validate
+        #[structopt(parse(from_os_str), default_value = "assets")]
+        asset_dirs: Vec<PathBuf>,
+    },
+
+    /// Prints the ID, type and source file of every asset in the daemon's metadata database
+    List {
+        #[structopt(flatten)]
+        daemon: AssetDaemonOpt,
+    },
+
+    /// Cooks a prefab and everything it references, and writes the cooked prefab as RON. Only
+    /// those prefabs are loaded from the daemon
+    Cook {
+        /// The UUID of the prefab to cook
+        #[structopt(parse(try_from_str = parse_asset_uuid))]
+        prefab: AssetUuid,
+
+        /// Where to write the cooked prefab
+        #[structopt(short, long, parse(from_os_str), default_value = "cooked.ron")]
+        output: PathBuf,
+
+        #[structopt(flatten)]
+        daemon: AssetDaemonOpt,
+    },
+}
+
+// How long to wait for a daemon started by this process to import the changed source files
+const DAEMON_IMPORT_TIMEOUT: Duration = Duration::from_secs(600);
+
+fn main() {
+    env_logger::Builder::from_default_env()
+        .filter_level(log::LevelFilter::Info)
+        .init();
+
+    let result = match AssetCliOpt::from_args() {
+        AssetCliOpt::Import { daemon } => import(daemon),
+        AssetCliOpt::Validate { asset_dirs } => validate(&asset_dirs),
+        AssetCliOpt::List { daemon } => list(daemon),
+        AssetCliOpt::Cook {
+            prefab,
+            output,
+            daemon,
+        } => cook(prefab, &output, daemon),
+    };
+
+    if let Err(e) = result {
+        log::error!("{}", e);
+        std::process::exit(1);
+    }
+}
+
+fn import(opt: AssetDaemonOpt) -> Result<(), String> {
+    if is_daemon_running(&opt) {
+        return Err(format!(
+            "an asset daemon is already running on {}, and imports source files as they change",
+            opt.address
+        ));
+    }
+
+    let daemon = start_daemon(opt)?;
+    match daemon.failed_import_count() {
+        0 => {
+            log::info!("All changed source files were imported");
+            Ok(())
+        }
+        failed_count => Err(format!(
+            "{} source files failed to import, the errors are logged above",
+            failed_count
+        )),
+    }
+}
+
+fn validate(asset_dirs: &[PathBuf]) -> Result<(), String> {
+    atelier_legion_demo::pipeline::set_asset_dirs(asset_dirs.to_vec());
+
+    // Keep going after a failure so that every broken file is reported in one run
+    let mut asset_count = 0;
+    let mut failed_count = 0;
+    for asset_dir in asset_dirs {
+        for path in find_source_files(asset_dir)? {
+            match import_source_file(&path) {
+                Ok(Some(artifacts)) => {
+                    log::info!("{} imports {} assets", path.display(), artifacts.len());
+                    asset_count += artifacts.len();
+                }
+                Ok(None) => {
+                    log::debug!("Skipping {}, no importer is registered for it", path.display())
+                }
+                Err(e) => {
+                    log::error!("{}", e);
+                    failed_count += 1;
+                }
+            }
+        }
+    }
+
+    if failed_count > 0 {
+        return Err(format!("{} source files failed to import", failed_count));
+    }
+
+    log::info!("All source files import, producing {} assets", asset_count);
+    Ok(())
+}
+
+fn list(opt: AssetDaemonOpt) -> Result<(), String> {
+    let address = opt.address;
+    ensure_daemon(opt)?;
+
+    let mut entries = list_assets(address)?;
+    entries.sort_by(|a, b| a.source.cmp(&b.source).then(a.id.0.cmp(&b.id.0)));
+
+    for entry in entries {
+        println!(
+            "{} {} {}",
+            uuid::Uuid::from_bytes(entry.id.0),
+            entry
+                .asset_type
+                .map(|asset_type| asset_type_name(&asset_type))
+                .unwrap_or_else(|| "(not imported)".to_string()),
+            entry
+                .source
+                .map(|source| source.display().to_string())
+                .unwrap_or_else(|| "(unknown source)".to_string())
+        );
+    }
+
+    Ok(())
+}
+
+fn cook(
+    prefab: AssetUuid,
+    output: &Path,
+    opt: AssetDaemonOpt,
+) -> Result<(), String> {
+    let address = opt.address;
+    ensure_daemon(opt)?;
+
+    // cook_prefab requests the prefab, then the prefabs it references as they load, so nothing
+    // else is loaded
+    let loader = RpcLoader::new(address.to_string())
+        .map_err(|e| format!("failed to create a loader for {}: {}", address, e))?;
+    let mut asset_manager =
+        atelier_legion_demo::create_asset_manager_with_source(AssetSource::Rpc(loader));
+
+    let universe = legion::prelude::Universe::new();
+    let cooked_prefab = atelier_legion_demo::prefab_cooking::cook_prefab(
+        &universe,
+        &mut asset_manager,
+        &atelier_legion_demo::create_component_registry(),
+        &atelier_legion_demo::create_component_registry_by_uuid(),
+        prefab,
+        None,
+    )
+    .map_err(|e| format!("failed to cook prefab {}: {}", uuid::Uuid::from_bytes(prefab.0), e))?;
+
+    let cooked_prefab_string =
+        ron::ser::to_string_pretty(&cooked_prefab, ron::ser::PrettyConfig::default())
+            .map_err(|e| format!("failed to serialize cooked prefab: {}", e))?;
+    std::fs::write(output, cooked_prefab_string)
+        .map_err(|e| format!("failed to write {}: {}", output.display(), e))?;
+
+    log::info!(
+        "Cooked prefab {} with {} entities to {}",
+        uuid::Uuid::from_bytes(prefab.0),
+        cooked_prefab.entities.len(),
+        output.display()
+    );
+
+    Ok(())
+}
+
+// Returns true if something is listening on the daemon's address
+fn is_daemon_running(opt: &AssetDaemonOpt) -> bool {
+    TcpStream::connect(opt.address).is_ok()
+}
+
+// Starts a daemon on a background thread and waits for its first import pass. It runs until the
+// process exits
+fn start_daemon(opt: AssetDaemonOpt) -> Result<DaemonHandle, String> {
+    let mut daemon = daemon::spawn_with(opt);
+    daemon
+        .wait_until_ready(DAEMON_IMPORT_TIMEOUT)
+        .map_err(|e| e.to_string())?;
+    Ok(daemon)
+}
+
+// Uses the daemon that is running on the address, or starts one if there isn't one
+fn ensure_daemon(opt: AssetDaemonOpt) -> Result<(), String> {
+    if is_daemon_running(&opt) {
+        log::info!("Using the asset daemon running on {}", opt.address);
+        return Ok(());
+    }
+
+    start_daemon(opt).map(|_| ())
+}
+
+// The types registered in create_asset_manager, by name. Others are printed as their type UUID
+fn asset_type_name(asset_type: &[u8; 16]) -> String {
+    match *asset_type {
+        PrefabAsset::UUID => "PrefabAsset".to_string(),
+        CookedPrefabAsset::UUID => "CookedPrefabAsset".to_string(),
+        ImageAsset::UUID => "ImageAsset".to_string(),
+        AtlasRegionAsset::UUID => "AtlasRegionAsset".to_string(),
+        _ => uuid::Uuid::from_bytes(*asset_type).to_string(),
+    }
+}
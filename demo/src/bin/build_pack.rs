@@ -0,0 +1,41 @@
+use std::path::PathBuf;
+
+use structopt::StructOpt;
+
+use atelier_legion_demo::pack_file::build_pack;
+
+/// Imports every asset in the given asset directories into a pack file, which the demo can load
+/// with --pack instead of running the asset daemon.
+///
+/// # Examples
+///
+/// ```bash
+/// build_pack --output assets.pack assets
+/// ```
+#[derive(StructOpt)]
+struct BuildPackOpt {
+    /// Where to write the pack file
+    #[structopt(short, long, parse(from_os_str), default_value = "assets.pack")]
+    output: PathBuf,
+
+    /// The asset directories to import
+    #[structopt(parse(from_os_str), default_value = "assets")]
+    asset_dirs: Vec<PathBuf>,
+}
+
+fn main() {
+    env_logger::Builder::from_default_env()
+        .filter_level(log::LevelFilter::Info)
+        .init();
+
+    let opt = BuildPackOpt::from_args();
+    match build_pack(&opt.asset_dirs, &opt.output) {
+        Ok(asset_count) => {
+            log::info!("Wrote {} assets to {}", asset_count, opt.output.display());
+        }
+        Err(e) => {
+            log::error!("{}", e);
+            std::process::exit(1);
+        }
+    }
+}
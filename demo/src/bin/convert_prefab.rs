@@ -0,0 +1,95 @@
+use std::path::PathBuf;
+
+use structopt::StructOpt;
+
+use atelier_legion_demo::pipeline::{read_prefab, write_prefab, PrefabFileFormat};
+
+/// Converts a prefab file between the RON, bincode and JSON encodings.
+///
+/// # Examples
+///
+/// ```bash
+/// convert_prefab assets/demo_level.prefab demo_level.bin.prefab --format bincode
+/// ```
+#[derive(StructOpt)]
+struct ConvertPrefabOpt {
+    /// The prefab file to convert. Its format is detected from its contents
+    #[structopt(parse(from_os_str))]
+    input: PathBuf,
+
+    /// Where to write the converted prefab
+    #[structopt(parse(from_os_str))]
+    output: PathBuf,
+
+    /// The format to write, ron, bincode or json. Defaults to bincode for RON input and RON
+    /// otherwise
+    #[structopt(short, long)]
+    format: Option<PrefabFileFormat>,
+}
+
+fn main() {
+    env_logger::Builder::from_default_env()
+        .filter_level(log::LevelFilter::Info)
+        .init();
+
+    let opt = ConvertPrefabOpt::from_args();
+    if let Err(e) = convert(&opt) {
+        log::error!("{}", e);
+        std::process::exit(1);
+    }
+}
+
+fn convert(opt: &ConvertPrefabOpt) -> Result<(), String> {
+    let prefab_serde_context = legion_prefab::PrefabSerdeContext {
+        registered_components: atelier_legion_demo::create_component_registry_by_uuid(),
+    };
+
+    let input = std::fs::read(&opt.input)
+        .map_err(|e| format!("failed to read {}: {}", opt.input.display(), e))?;
+    let input_format = PrefabFileFormat::detect(&input);
+    let output_format = opt.format.unwrap_or(match input_format {
+        PrefabFileFormat::Ron => PrefabFileFormat::Bincode,
+        PrefabFileFormat::Bincode | PrefabFileFormat::Json => PrefabFileFormat::Ron,
+    });
+
+    let prefab_asset = read_prefab(&input, input_format, &prefab_serde_context)
+        .map_err(|e| format!("failed to read {}: {}", opt.input.display(), e))?;
+    let output = write_prefab(
+        &prefab_asset.prefab,
+        &prefab_asset.instances,
+        output_format,
+        &prefab_serde_context,
+    )?;
+
+    // Make sure nothing was lost by reading the output back and comparing it with the input
+    let restored = read_prefab(&output, output_format, &prefab_serde_context)
+        .map_err(|e| format!("failed to read back converted prefab: {}", e))?;
+    let expected = write_prefab(
+        &prefab_asset.prefab,
+        &prefab_asset.instances,
+        PrefabFileFormat::Ron,
+        &prefab_serde_context,
+    )?;
+    let actual = write_prefab(
+        &restored.prefab,
+        &restored.instances,
+        PrefabFileFormat::Ron,
+        &prefab_serde_context,
+    )?;
+    if expected != actual {
+        return Err("converted prefab does not match the input".to_string());
+    }
+
+    std::fs::write(&opt.output, output)
+        .map_err(|e| format!("failed to write {}: {}", opt.output.display(), e))?;
+
+    log::info!(
+        "Converted {} ({:?}) to {} ({:?})",
+        opt.input.display(),
+        input_format,
+        opt.output.display(),
+        output_format
+    );
+
+    Ok(())
+}
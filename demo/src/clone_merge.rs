@@ -116,14 +116,14 @@ where
 /// will be cloned using ComponentRegistration passed in new()
 pub struct SpawnCloneImpl<'a> {
     handlers: HashMap<ComponentTypeId, Box<dyn SpawnCloneImplMapping>>,
-    components: HashMap<ComponentTypeId, ComponentRegistration>,
+    components: &'a HashMap<ComponentTypeId, ComponentRegistration>,
     resources: &'a Resources,
 }
 
 impl<'a> SpawnCloneImpl<'a> {
     /// Creates a new implementation
     pub fn new(
-        components: HashMap<ComponentTypeId, ComponentRegistration>,
+        components: &'a HashMap<ComponentTypeId, ComponentRegistration>,
         resources: &'a Resources,
     ) -> Self {
         Self {
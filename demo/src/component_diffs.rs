@@ -4,14 +4,15 @@ use legion_prefab::Prefab;
 use std::collections::HashMap;
 use legion::prelude::*;
 use legion_prefab::DiffSingleResult;
+use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub enum EntityDiffOp {
     Add,
     Remove,
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct EntityDiff {
     entity_uuid: EntityUuid,
     op: EntityDiffOp,
@@ -34,8 +35,50 @@ impl EntityDiff {
     }
 }
 
+/// The encoding of the data in a ComponentDiffOp. The data can only be read with the encoding it
+/// was written with, so this is kept with it when diffs are written to disk
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ComponentDataFormat {
+    /// bincode 1.x with its default options, as written by bincode::with_serializer
+    Bincode1,
+}
+
+impl Default for ComponentDataFormat {
+    // The format that new diffs are written with
+    fn default() -> Self {
+        ComponentDataFormat::Bincode1
+    }
+}
+
+impl ComponentDataFormat {
+    /// Passes a serializer that appends data in this format to `data` to the acceptor
+    pub fn with_serializer<A: bincode::SerializerAcceptor>(
+        self,
+        data: &mut Vec<u8>,
+        acceptor: A,
+    ) -> A::Output {
+        match self {
+            ComponentDataFormat::Bincode1 => bincode::with_serializer(data, acceptor),
+        }
+    }
+
+    // Passes a deserializer for data in this format to the acceptor
+    fn with_deserializer<'a, A: bincode::DeserializerAcceptor<'a>>(
+        self,
+        data: &'a [u8],
+        acceptor: A,
+    ) -> A::Output {
+        match self {
+            ComponentDataFormat::Bincode1 => {
+                let reader = bincode::SliceReader::new(data);
+                bincode::with_deserializer(reader, acceptor)
+            }
+        }
+    }
+}
+
 // This is somewhat of a mirror of DiffSingleResult
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub enum ComponentDiffOp {
     Change(Vec<u8>),
     Add(Vec<u8>),
@@ -56,11 +99,14 @@ impl ComponentDiffOp {
     }
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct ComponentDiff {
     entity_uuid: EntityUuid,
     component_type: ComponentTypeUuid,
     op: ComponentDiffOp,
+
+    // How the data in op is encoded
+    format: ComponentDataFormat,
 }
 
 impl ComponentDiff {
@@ -68,11 +114,22 @@ impl ComponentDiff {
         entity_uuid: EntityUuid,
         component_type: ComponentTypeUuid,
         op: ComponentDiffOp,
+    ) -> Self {
+        Self::new_with_format(entity_uuid, component_type, op, ComponentDataFormat::default())
+    }
+
+    /// Creates a diff whose op data was written in the given format
+    pub fn new_with_format(
+        entity_uuid: EntityUuid,
+        component_type: ComponentTypeUuid,
+        op: ComponentDiffOp,
+        format: ComponentDataFormat,
     ) -> Self {
         ComponentDiff {
             entity_uuid,
             component_type,
             op,
+            format,
         }
     }
 
@@ -81,9 +138,10 @@ impl ComponentDiff {
         component_type: ComponentTypeUuid,
         diff_single_result: DiffSingleResult,
         data: Vec<u8>,
+        format: ComponentDataFormat,
     ) -> Option<Self> {
         let op = ComponentDiffOp::from_diff_single_result(diff_single_result, data);
-        op.map(|op| Self::new(entity_uuid, component_type, op))
+        op.map(|op| Self::new_with_format(entity_uuid, component_type, op, format))
     }
 
     pub fn entity_uuid(&self) -> &EntityUuid {
@@ -97,9 +155,13 @@ impl ComponentDiff {
     pub fn op(&self) -> &ComponentDiffOp {
         &self.op
     }
+
+    pub fn format(&self) -> ComponentDataFormat {
+        self.format
+    }
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct WorldDiff {
     entity_diffs: Vec<EntityDiff>,
     component_diffs: Vec<ComponentDiff>,
@@ -298,8 +360,7 @@ pub fn apply_diff(
                             entity: *new_prefab_entity,
                         };
 
-                        let reader = bincode::SliceReader::new(data);
-                        bincode::with_deserializer(reader, acceptor);
+                        component_diff.format().with_deserializer(data, acceptor);
                     }
                     ComponentDiffOp::Add(data) => {
                         let acceptor = DeserializeSingleDeserializerAcceptor {
@@ -308,8 +369,7 @@ pub fn apply_diff(
                             entity: *new_prefab_entity,
                         };
 
-                        let reader = bincode::SliceReader::new(data);
-                        bincode::with_deserializer(reader, acceptor);
+                        component_diff.format().with_deserializer(data, acceptor);
                     }
                     ComponentDiffOp::Remove => {
                         component_registration
@@ -0,0 +1,512 @@
+//! Component data in prefab files is stored by component type UUID. To allow a component type's
+//! fields to change without breaking existing prefabs, a schema version and a list of migrations
+//! can be registered for it:
+//!
+//! ```ignore
+//! // Version 2 renamed `size` to `half_extents`
+//! fn migrate_box_v1_to_v2(mut value: ComponentValue) -> Result<ComponentValue, String> {
+//!     if let Some(size) = value.remove_field("size") {
+//!         value.set_field("half_extents", size);
+//!     }
+//!     Ok(value)
+//! }
+//!
+//! register_component_schema!(DrawSkiaBoxComponentDef, 2, [migrate_box_v1_to_v2]);
+//! ```
+//!
+//! When a prefab is saved, the current version of every registered component type is written to a
+//! header comment at the top of the file. When it's imported, component data and overrides written
+//! with an older version are upgraded before they're deserialized. Component types that have no
+//! registered schema, and prefab files without a header, are version 1.
+//!
+//! Overrides are migrated as a partial value that only has the fields they change, so migrations
+//! must leave fields that aren't there alone.
+
+use std::collections::HashMap;
+use prefab_format::ComponentTypeUuid;
+
+use crate::component_value::ComponentValue;
+
+/// Upgrades component data from one version to the next
+pub type ComponentMigration = fn(ComponentValue) -> Result<ComponentValue, String>;
+
+/// The current schema version of a component type, and the migrations that upgrade data written
+/// with older versions. Use register_component_schema! to create these
+pub struct ComponentSchemaRegistration {
+    component_type: ComponentTypeUuid,
+    type_name: &'static str,
+    version: u32,
+
+    // migrations[i] upgrades data from version i + 1 to version i + 2
+    migrations: &'static [ComponentMigration],
+}
+
+inventory::collect!(ComponentSchemaRegistration);
+
+impl ComponentSchemaRegistration {
+    pub fn new(
+        component_type: ComponentTypeUuid,
+        type_name: &'static str,
+        version: u32,
+        migrations: &'static [ComponentMigration],
+    ) -> Self {
+        ComponentSchemaRegistration {
+            component_type,
+            type_name,
+            version,
+            migrations,
+        }
+    }
+
+    pub fn component_type(&self) -> &ComponentTypeUuid {
+        &self.component_type
+    }
+
+    pub fn type_name(&self) -> &'static str {
+        self.type_name
+    }
+
+    /// The version that data is saved with
+    pub fn version(&self) -> u32 {
+        self.version
+    }
+
+    /// Upgrades data written with the given version to the current version
+    pub fn migrate(
+        &self,
+        data_version: u32,
+        mut value: ComponentValue,
+    ) -> Result<ComponentValue, String> {
+        if data_version == 0 || data_version > self.version {
+            return Err(format!(
+                "{} data has version {}, but only versions 1 to {} are supported",
+                self.type_name, data_version, self.version
+            ));
+        }
+
+        for version in data_version..self.version {
+            let migration = self.migrations.get(version as usize - 1).ok_or_else(|| {
+                format!(
+                    "{} has no migration from version {} to {}",
+                    self.type_name,
+                    version,
+                    version + 1
+                )
+            })?;
+
+            value = migration(value).map_err(|e| {
+                format!(
+                    "failed to migrate {} from version {} to {}: {}",
+                    self.type_name,
+                    version,
+                    version + 1,
+                    e
+                )
+            })?;
+        }
+
+        Ok(value)
+    }
+
+    /// Upgrades component data written as RON with the given version to the current version. The
+    /// upgraded data is written on a single line
+    pub fn migrate_ron(
+        &self,
+        data_version: u32,
+        data: &str,
+    ) -> Result<String, String> {
+        if data_version == self.version {
+            return Ok(data.to_string());
+        }
+
+        let value = ComponentValue::parse(data)?;
+        Ok(self.migrate(data_version, value)?.to_ron_string_single_line())
+    }
+
+    /// Upgrades an override's serde_diff diff, written as RON with the given version, to the
+    /// current version, on a single line. Only diffs that change struct fields can be migrated
+    pub fn migrate_diff(
+        &self,
+        data_version: u32,
+        diff: &str,
+    ) -> Result<String, String> {
+        if data_version == self.version {
+            return Ok(diff.to_string());
+        }
+
+        let commands = match ComponentValue::parse(diff)? {
+            ComponentValue::List(commands) => commands,
+            _ => return Err("expected a list of diff commands".to_string()),
+        };
+
+        let changed_fields = read_diff_fields(&mut commands.into_iter().peekable())?;
+        let changed_fields = self.migrate(data_version, changed_fields)?;
+
+        let mut commands = Vec::new();
+        write_diff_fields(changed_fields, &mut commands)?;
+        Ok(ComponentValue::List(commands).to_ron_string_single_line())
+    }
+}
+
+// Reads diff commands up to the end of the current struct as a struct holding only the fields
+// that changed. Changes to the fields of a nested struct are read as an unnamed struct
+fn read_diff_fields(
+    commands: &mut std::iter::Peekable<std::vec::IntoIter<ComponentValue>>,
+) -> Result<ComponentValue, String> {
+    let mut fields = Vec::new();
+    while let Some(command) = commands.next() {
+        if command == ComponentValue::Ident("Exit".to_string()) {
+            break;
+        }
+
+        let field_name = match diff_command_argument(&command, "Enter") {
+            Some(ComponentValue::Tuple { name, fields }) if name.as_deref() == Some("Field") => {
+                match fields.as_slice() {
+                    [ComponentValue::String(field_name)] => field_name.clone(),
+                    _ => return Err(format!("malformed diff command {}", command.to_ron_string())),
+                }
+            }
+            _ => {
+                return Err(format!(
+                    "diff command {} can't be migrated, only changes to struct fields can",
+                    command.to_ron_string()
+                ))
+            }
+        };
+
+        let value = match commands.peek().and_then(|next| diff_command_argument(next, "Value")) {
+            Some(value) => {
+                let value = value.clone();
+                commands.next();
+                value
+            }
+            None => read_diff_fields(commands)?,
+        };
+
+        fields.push((field_name, value));
+    }
+
+    Ok(ComponentValue::Struct { name: None, fields })
+}
+
+// Writes the diff commands that read_diff_fields would read the given struct from
+fn write_diff_fields(
+    changed_fields: ComponentValue,
+    commands: &mut Vec<ComponentValue>,
+) -> Result<(), String> {
+    let fields = match changed_fields {
+        ComponentValue::Struct { name: None, fields } => fields,
+        other => {
+            return Err(format!(
+                "migrating a diff produced {} instead of the changed fields",
+                other.to_ron_string()
+            ))
+        }
+    };
+
+    for (field_name, value) in fields {
+        let field = ComponentValue::Tuple {
+            name: Some("Field".to_string()),
+            fields: vec![ComponentValue::String(field_name)],
+        };
+        commands.push(ComponentValue::Tuple {
+            name: Some("Enter".to_string()),
+            fields: vec![field],
+        });
+
+        match value {
+            ComponentValue::Struct { name: None, .. } => {
+                write_diff_fields(value, commands)?;
+                commands.push(ComponentValue::Ident("Exit".to_string()));
+            }
+            value => commands.push(ComponentValue::Tuple {
+                name: Some("Value".to_string()),
+                fields: vec![value],
+            }),
+        }
+    }
+
+    Ok(())
+}
+
+// Returns the argument of a diff command like Enter(..) or Value(..) if it's the given command
+fn diff_command_argument<'a>(
+    command: &'a ComponentValue,
+    command_name: &str,
+) -> Option<&'a ComponentValue> {
+    match command {
+        ComponentValue::Tuple {
+            name: Some(name),
+            fields,
+        } if name == command_name && fields.len() == 1 => Some(&fields[0]),
+        _ => None,
+    }
+}
+
+/// Registers the current schema version of a component type and the migrations from each older
+/// version, oldest first. There must be one migration per version before the current one
+#[macro_export]
+macro_rules! register_component_schema {
+    ($component_type:ty, $version:expr, [$($migration:expr),* $(,)?]) => {
+        inventory::submit! {
+            $crate::component_schemas::ComponentSchemaRegistration::new(
+                <$component_type as type_uuid::TypeUuid>::UUID,
+                stringify!($component_type),
+                $version,
+                &[$($migration),*],
+            )
+        }
+    };
+}
+
+/// Returns all registered component schemas by component type
+pub fn component_schemas() -> HashMap<ComponentTypeUuid, &'static ComponentSchemaRegistration> {
+    inventory::iter::<ComponentSchemaRegistration>
+        .into_iter()
+        .map(|schema| (schema.component_type, schema))
+        .collect()
+}
+
+/// Returns the current schema version of every registered component type
+pub fn current_schema_versions() -> HashMap<ComponentTypeUuid, u32> {
+    component_schemas()
+        .values()
+        .map(|schema| (schema.component_type, schema.version))
+        .collect()
+}
+
+// Marks the header line that holds the component schema versions a prefab file was saved with
+const SCHEMA_VERSIONS_HEADER_PREFIX: &str = "// component schema versions: ";
+
+/// Returns the header to write at the top of a prefab file. It records the current version of
+/// every registered component type
+pub fn schema_versions_header() -> String {
+    let mut versions: Vec<_> = component_schemas()
+        .values()
+        .map(|schema| {
+            (
+                uuid::Uuid::from_bytes(schema.component_type).to_string(),
+                schema.version,
+            )
+        })
+        .collect();
+
+    // Sorted so that saving the same data twice produces the same file
+    versions.sort();
+
+    let versions: Vec<_> = versions
+        .iter()
+        .map(|(component_type, version)| format!("\"{}\": {}", component_type, version))
+        .collect();
+
+    format!("{}{{{}}}\n", SCHEMA_VERSIONS_HEADER_PREFIX, versions.join(", "))
+}
+
+/// Reads the component schema versions from the header of a prefab file. Component types that are
+/// not listed are version 1
+pub fn read_schema_versions_header(
+    prefab_file: &str,
+) -> Result<HashMap<ComponentTypeUuid, u32>, String> {
+    let header = prefab_file
+        .lines()
+        .take_while(|line| line.trim_start().starts_with("//"))
+        .find(|line| line.starts_with(SCHEMA_VERSIONS_HEADER_PREFIX));
+
+    let header = match header {
+        Some(header) => &header[SCHEMA_VERSIONS_HEADER_PREFIX.len()..],
+        None => return Ok(HashMap::new()),
+    };
+
+    let versions: HashMap<String, u32> = ron::de::from_str(header)
+        .map_err(|e| format!("malformed component schema versions header: {}", e))?;
+
+    let mut result = HashMap::with_capacity(versions.len());
+    for (component_type, version) in versions {
+        let component_type = uuid::Uuid::parse_str(&component_type).map_err(|e| {
+            format!(
+                "malformed component type {} in component schema versions header: {}",
+                component_type, e
+            )
+        })?;
+        result.insert(*component_type.as_bytes(), version);
+    }
+
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use type_uuid::TypeUuid;
+
+    // A component type that only exists to test migrations. Version 2 renamed `size` to
+    // `half_extents` and version 3 replaced `is_static: bool` with `body_type`
+    #[derive(TypeUuid)]
+    #[uuid = "3b0b7a52-5e64-4f0c-9d1a-7c2f4e8b6a10"]
+    struct TestBoxComponentDef;
+
+    fn migrate_test_box_v1_to_v2(mut value: ComponentValue) -> Result<ComponentValue, String> {
+        if let Some(size) = value.remove_field("size") {
+            value.set_field("half_extents", size);
+        }
+        Ok(value)
+    }
+
+    fn migrate_test_box_v2_to_v3(mut value: ComponentValue) -> Result<ComponentValue, String> {
+        if let Some(is_static) = value.remove_field("is_static") {
+            let body_type = match is_static {
+                ComponentValue::Bool(false) => "Dynamic",
+                ComponentValue::Bool(true) => "Static",
+                other => return Err(format!("expected a bool, found {}", other.to_ron_string())),
+            };
+            value.set_field("body_type", ComponentValue::Ident(body_type.to_string()));
+        }
+        Ok(value)
+    }
+
+    crate::register_component_schema!(
+        TestBoxComponentDef,
+        3,
+        [migrate_test_box_v1_to_v2, migrate_test_box_v2_to_v3]
+    );
+
+    fn test_schema() -> &'static ComponentSchemaRegistration {
+        component_schemas()[&TestBoxComponentDef::UUID]
+    }
+
+    fn parse(text: &str) -> ComponentValue {
+        ComponentValue::parse(text).unwrap()
+    }
+
+    #[test]
+    fn registers_schema() {
+        let schema = test_schema();
+        assert_eq!(schema.type_name(), "TestBoxComponentDef");
+        assert_eq!(schema.version(), 3);
+        assert_eq!(current_schema_versions()[&TestBoxComponentDef::UUID], 3);
+    }
+
+    #[test]
+    fn migrates_through_every_version() {
+        let v1 = parse("TestBoxComponentDef(size: Vec2(1.0, 2.0), is_static: true)");
+        assert_eq!(
+            test_schema().migrate(1, v1).unwrap(),
+            parse("TestBoxComponentDef(half_extents: Vec2(1.0, 2.0), body_type: Static)")
+        );
+
+        let v2 = parse("(half_extents: Vec2(1.0, 2.0), is_static: false)");
+        assert_eq!(
+            test_schema().migrate(2, v2).unwrap(),
+            parse("(half_extents: Vec2(1.0, 2.0), body_type: Dynamic)")
+        );
+
+        let v3 = parse("(half_extents: Vec2(1.0, 2.0), body_type: Dynamic)");
+        assert_eq!(test_schema().migrate(3, v3.clone()).unwrap(), v3);
+    }
+
+    #[test]
+    fn rejects_unsupported_versions() {
+        let value = parse("(size: 1.0)");
+        assert!(test_schema().migrate(0, value.clone()).is_err());
+        assert!(test_schema().migrate(4, value).is_err());
+
+        let schema = ComponentSchemaRegistration::new([0; 16], "Missing", 3, &[]);
+        let error = schema.migrate(2, parse("()")).unwrap_err();
+        assert!(
+            error.contains("no migration from version 2 to 3"),
+            "{}",
+            error
+        );
+    }
+
+    #[test]
+    fn reports_failed_migrations() {
+        let error = test_schema()
+            .migrate(2, parse("(is_static: 1)"))
+            .unwrap_err();
+        assert!(
+            error.starts_with("failed to migrate TestBoxComponentDef from version 2 to 3"),
+            "{}",
+            error
+        );
+    }
+
+    #[test]
+    fn migrates_ron() {
+        let migrated = test_schema()
+            .migrate_ron(
+                1,
+                "TestBoxComponentDef(size: Vec2(1.0, 2.0), is_static: true)",
+            )
+            .unwrap();
+        assert_eq!(
+            parse(&migrated),
+            parse("TestBoxComponentDef(half_extents: Vec2(1.0, 2.0), body_type: Static)")
+        );
+
+        // Data that's already current is left as written
+        let current = "( half_extents: Vec2(1.0, 2.0) )";
+        assert_eq!(test_schema().migrate_ron(3, current).unwrap(), current);
+    }
+
+    #[test]
+    fn migrates_diffs() {
+        let diff = r#"[Enter(Field("size")), Value(Vec2(3.0, 4.0))]"#;
+        let migrated = test_schema().migrate_diff(1, diff).unwrap();
+        assert_eq!(
+            parse(&migrated),
+            parse(r#"[Enter(Field("half_extents")), Value(Vec2(3.0, 4.0))]"#)
+        );
+
+        // Fields of nested structs are entered and exited
+        let diff = r#"[Enter(Field("paint")), Enter(Field("stroke_width")), Value(0.5), Exit,
+            Enter(Field("is_static")), Value(true)]"#;
+        let migrated = test_schema().migrate_diff(2, diff).unwrap();
+        assert_eq!(
+            parse(&migrated),
+            parse(
+                r#"[Enter(Field("paint")), Enter(Field("stroke_width")), Value(0.5), Exit,
+                Enter(Field("body_type")), Value(Static)]"#
+            )
+        );
+    }
+
+    #[test]
+    fn rejects_diffs_that_cant_be_migrated() {
+        let diff = r#"[Enter(CollectionIndex(0)), Value(1.0)]"#;
+        assert!(test_schema().migrate_diff(1, diff).is_err());
+        assert!(test_schema().migrate_diff(1, "(size: 1.0)").is_err());
+
+        // Diffs that are already current aren't read
+        assert_eq!(test_schema().migrate_diff(3, diff).unwrap(), diff);
+    }
+
+    #[test]
+    fn reads_schema_versions_header() {
+        let prefab_file = format!(
+            "{}PrefabFile(prefab: Prefab(objects: []))",
+            schema_versions_header()
+        );
+        let versions = read_schema_versions_header(&prefab_file).unwrap();
+        assert_eq!(versions, current_schema_versions());
+        assert_eq!(versions[&TestBoxComponentDef::UUID], 3);
+
+        // Other comments can come before the header, but it must be before the prefab
+        let prefab_file = concat!(
+            "// comment\n",
+            "// component schema versions: {\"3b0b7a52-5e64-4f0c-9d1a-7c2f4e8b6a10\": 2}\n",
+            "()",
+        );
+        let versions = read_schema_versions_header(prefab_file).unwrap();
+        assert_eq!(versions[&TestBoxComponentDef::UUID], 2);
+
+        let prefab_file = concat!(
+            "()\n",
+            "// component schema versions: {\"3b0b7a52-5e64-4f0c-9d1a-7c2f4e8b6a10\": 2}",
+        );
+        assert!(read_schema_versions_header(prefab_file).unwrap().is_empty());
+
+        assert!(read_schema_versions_header("// component schema versions: {\"x\": 1}\n").is_err());
+        assert!(read_schema_versions_header("// component schema versions: [\n").is_err());
+    }
+}
@@ -0,0 +1,935 @@
+//! Component data as it's written in a RON prefab file. Unlike ron::Value, this keeps the names of
+//! structs and enum variants, so data can be migrated (see component_schemas) and written back out
+//! without changing what it deserializes to.
+
+use std::fmt::Write;
+use std::ops::Range;
+
+/// A RON value. Numbers are kept as written so that they aren't changed by reading and writing
+/// them
+#[derive(Clone, Debug, PartialEq)]
+pub enum ComponentValue {
+    /// ()
+    Unit,
+    Bool(bool),
+    Char(char),
+    Number(String),
+    String(String),
+    Option(Option<Box<ComponentValue>>),
+
+    /// [a, b]
+    List(Vec<ComponentValue>),
+
+    /// {a: b}
+    Map(Vec<(ComponentValue, ComponentValue)>),
+
+    /// Name(a, b) or (a, b). Tuples, tuple structs and tuple variants
+    Tuple {
+        name: Option<String>,
+        fields: Vec<ComponentValue>,
+    },
+
+    /// Name(a: 1) or (a: 1). Structs and struct variants
+    Struct {
+        name: Option<String>,
+        fields: Vec<(String, ComponentValue)>,
+    },
+
+    /// A unit struct or unit variant
+    Ident(String),
+}
+
+impl ComponentValue {
+    /// Parses a RON value, ignoring comments
+    pub fn parse(text: &str) -> Result<ComponentValue, String> {
+        Ok(Self::parse_spanned(text)?.value)
+    }
+
+    /// Parses a RON value and records where in the text each value within it was read from, so
+    /// that parts of the text can be replaced without rewriting the rest
+    pub fn parse_spanned(text: &str) -> Result<SpannedDocument, String> {
+        let mut parser = Parser {
+            text,
+            position: 0,
+            spans: Vec::new(),
+        };
+        let value = parser.value()?;
+        parser.skip_whitespace()?;
+        if parser.position != text.len() {
+            return Err(parser.error("unexpected characters after the value"));
+        }
+
+        Ok(SpannedDocument {
+            value,
+            spans: parser.spans,
+        })
+    }
+
+    /// Writes the value as RON, with one item per line
+    pub fn to_ron_string(&self) -> String {
+        let mut output = String::new();
+        self.write_ron(&mut output, Some(0));
+        output
+    }
+
+    /// Writes the value as RON on a single line
+    pub fn to_ron_string_single_line(&self) -> String {
+        let mut output = String::new();
+        self.write_ron(&mut output, None);
+        output
+    }
+
+    // The number of values in this value, including itself. Values are numbered in the order
+    // they're written, which is the order of the spans in a SpannedDocument
+    fn value_count(&self) -> usize {
+        1 + match self {
+            ComponentValue::Option(Some(value)) => value.value_count(),
+            ComponentValue::List(items) => items.iter().map(ComponentValue::value_count).sum(),
+            ComponentValue::Map(entries) => entries
+                .iter()
+                .map(|(key, value)| key.value_count() + value.value_count())
+                .sum(),
+            ComponentValue::Tuple { fields, .. } => {
+                fields.iter().map(ComponentValue::value_count).sum()
+            }
+            ComponentValue::Struct { fields, .. } => {
+                fields.iter().map(|(_, value)| value.value_count()).sum()
+            }
+            _ => 0,
+        }
+    }
+
+    /// Returns the value of a struct's field, or None if this isn't a struct or has no such field
+    pub fn field(
+        &self,
+        name: &str,
+    ) -> Option<&ComponentValue> {
+        match self {
+            ComponentValue::Struct { fields, .. } => fields
+                .iter()
+                .find(|(field_name, _)| field_name == name)
+                .map(|(_, value)| value),
+            _ => None,
+        }
+    }
+
+    /// Returns the value of a struct's field for modifying it, or None if this isn't a struct or
+    /// has no such field
+    pub fn field_mut(
+        &mut self,
+        name: &str,
+    ) -> Option<&mut ComponentValue> {
+        match self {
+            ComponentValue::Struct { fields, .. } => fields
+                .iter_mut()
+                .find(|(field_name, _)| field_name == name)
+                .map(|(_, value)| value),
+            _ => None,
+        }
+    }
+
+    /// Removes a field from a struct and returns its value, or None if this isn't a struct or has
+    /// no such field
+    pub fn remove_field(
+        &mut self,
+        name: &str,
+    ) -> Option<ComponentValue> {
+        match self {
+            ComponentValue::Struct { fields, .. } => {
+                let index = fields
+                    .iter()
+                    .position(|(field_name, _)| field_name == name)?;
+                Some(fields.remove(index).1)
+            }
+            _ => None,
+        }
+    }
+
+    /// Sets the value of a struct's field, adding the field if it isn't there. Does nothing if this
+    /// isn't a struct
+    pub fn set_field(
+        &mut self,
+        name: &str,
+        value: ComponentValue,
+    ) {
+        if let ComponentValue::Struct { fields, .. } = self {
+            match fields.iter_mut().find(|(field_name, _)| field_name == name) {
+                Some((_, field_value)) => *field_value = value,
+                None => fields.push((name.to_string(), value)),
+            }
+        }
+    }
+
+    // indent is None when writing on a single line
+    fn write_ron(
+        &self,
+        output: &mut String,
+        indent: Option<usize>,
+    ) {
+        match self {
+            ComponentValue::Unit => output.push_str("()"),
+            ComponentValue::Bool(value) => output.push_str(if *value { "true" } else { "false" }),
+            ComponentValue::Char(value) => {
+                output.push('\'');
+                write_escaped(output, *value, '\'');
+                output.push('\'');
+            }
+            ComponentValue::Number(value) => output.push_str(value),
+            ComponentValue::String(value) => {
+                output.push('"');
+                for c in value.chars() {
+                    write_escaped(output, c, '"');
+                }
+                output.push('"');
+            }
+            ComponentValue::Option(None) => output.push_str("None"),
+            ComponentValue::Option(Some(value)) => {
+                output.push_str("Some(");
+                value.write_ron(output, indent);
+                output.push(')');
+            }
+            ComponentValue::List(items) => {
+                output.push('[');
+                write_block(output, indent, items, |output, item| {
+                    item.write_ron(output, indent.map(|indent| indent + 1))
+                });
+                output.push(']');
+            }
+            ComponentValue::Map(entries) => {
+                output.push('{');
+                write_block(output, indent, entries, |output, (key, value)| {
+                    key.write_ron(output, indent.map(|indent| indent + 1));
+                    output.push_str(": ");
+                    value.write_ron(output, indent.map(|indent| indent + 1));
+                });
+                output.push('}');
+            }
+            ComponentValue::Tuple { name, fields } => {
+                output.push_str(name.as_ref().map(String::as_str).unwrap_or(""));
+                output.push('(');
+                for (index, field) in fields.iter().enumerate() {
+                    if index > 0 {
+                        output.push_str(", ");
+                    }
+                    field.write_ron(output, indent);
+                }
+                output.push(')');
+            }
+            ComponentValue::Struct { name, fields } => {
+                output.push_str(name.as_ref().map(String::as_str).unwrap_or(""));
+                output.push('(');
+                write_block(output, indent, fields, |output, (field_name, value)| {
+                    output.push_str(field_name);
+                    output.push_str(": ");
+                    value.write_ron(output, indent.map(|indent| indent + 1));
+                });
+                output.push(')');
+            }
+            ComponentValue::Ident(name) => output.push_str(name),
+        }
+    }
+}
+
+// Writes one item per line, indented one level deeper than the enclosing brackets, or all of the
+// items on one line if indent is None
+fn write_block<T>(
+    output: &mut String,
+    indent: Option<usize>,
+    items: &[T],
+    mut write_item: impl FnMut(&mut String, &T),
+) {
+    if items.is_empty() {
+        return;
+    }
+
+    let indent = match indent {
+        Some(indent) => indent,
+        None => {
+            for (index, item) in items.iter().enumerate() {
+                if index > 0 {
+                    output.push_str(", ");
+                }
+                write_item(output, item);
+            }
+            return;
+        }
+    };
+
+    output.push('\n');
+    for item in items {
+        output.push_str(&"    ".repeat(indent + 1));
+        write_item(output, item);
+        output.push_str(",\n");
+    }
+    output.push_str(&"    ".repeat(indent));
+}
+
+fn write_escaped(
+    output: &mut String,
+    c: char,
+    quote: char,
+) {
+    match c {
+        '\\' => output.push_str("\\\\"),
+        '\n' => output.push_str("\\n"),
+        '\r' => output.push_str("\\r"),
+        '\t' => output.push_str("\\t"),
+        c if c == quote => {
+            output.push('\\');
+            output.push(c);
+        }
+        c if c.is_control() => {
+            let _ = write!(output, "\\u{:04x}", c as u32);
+        }
+        c => output.push(c),
+    }
+}
+
+/// A parsed value along with the byte range of the text that each value within it was read from.
+/// See ComponentValue::parse_spanned
+pub struct SpannedDocument {
+    value: ComponentValue,
+
+    // The span of every value, in the order they're written
+    spans: Vec<Range<usize>>,
+}
+
+impl SpannedDocument {
+    pub fn root(&self) -> SpannedValue<'_> {
+        SpannedValue {
+            value: &self.value,
+            spans: &self.spans,
+        }
+    }
+}
+
+/// A value within a SpannedDocument
+#[derive(Clone, Copy)]
+pub struct SpannedValue<'a> {
+    value: &'a ComponentValue,
+
+    // The span of this value followed by the spans of the values within it
+    spans: &'a [Range<usize>],
+}
+
+impl<'a> SpannedValue<'a> {
+    pub fn value(&self) -> &'a ComponentValue {
+        self.value
+    }
+
+    /// The byte range of the text that the value was read from
+    pub fn span(&self) -> Range<usize> {
+        self.spans[0].clone()
+    }
+
+    /// Returns a struct's field, or None if this isn't a struct or has no such field
+    pub fn field(
+        &self,
+        name: &str,
+    ) -> Option<SpannedValue<'a>> {
+        let fields = match self.value {
+            ComponentValue::Struct { fields, .. } => fields,
+            _ => return None,
+        };
+
+        let mut index = 1;
+        for (field_name, value) in fields {
+            if field_name == name {
+                return Some(self.child(value, index));
+            }
+            index += value.value_count();
+        }
+
+        None
+    }
+
+    /// Returns the items of a list or the fields of a tuple, or nothing if this is neither
+    pub fn items(&self) -> Vec<SpannedValue<'a>> {
+        let items = match self.value {
+            ComponentValue::List(items) => items,
+            ComponentValue::Tuple { fields, .. } => fields,
+            _ => return Vec::new(),
+        };
+
+        let mut index = 1;
+        let mut spanned_items = Vec::with_capacity(items.len());
+        for item in items {
+            spanned_items.push(self.child(item, index));
+            index += item.value_count();
+        }
+
+        spanned_items
+    }
+
+    fn child(
+        &self,
+        value: &'a ComponentValue,
+        index: usize,
+    ) -> SpannedValue<'a> {
+        SpannedValue {
+            value,
+            spans: &self.spans[index..index + value.value_count()],
+        }
+    }
+}
+
+struct Parser<'a> {
+    text: &'a str,
+    position: usize,
+
+    // The span of every value parsed so far, in the order they were started
+    spans: Vec<Range<usize>>,
+}
+
+impl<'a> Parser<'a> {
+    fn rest(&self) -> &'a str {
+        &self.text[self.position..]
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.rest().chars().next()
+    }
+
+    fn error(
+        &self,
+        message: &str,
+    ) -> String {
+        let consumed = &self.text[..self.position];
+        let line = consumed.matches('\n').count() + 1;
+        let line_start = consumed.rfind('\n').map(|index| index + 1).unwrap_or(0);
+        let column = consumed[line_start..].chars().count() + 1;
+        format!("line {}, column {}: {}", line, column, message)
+    }
+
+    fn skip_whitespace(&mut self) -> Result<(), String> {
+        loop {
+            let rest = self.rest();
+            let trimmed = rest.trim_start();
+            self.position += rest.len() - trimmed.len();
+
+            if trimmed.starts_with("//") {
+                self.position += trimmed.find('\n').unwrap_or_else(|| trimmed.len());
+            } else if trimmed.starts_with("/*") {
+                let end = trimmed
+                    .find("*/")
+                    .ok_or_else(|| self.error("unterminated comment"))?;
+                self.position += end + 2;
+            } else {
+                return Ok(());
+            }
+        }
+    }
+
+    // Skips whitespace, then consumes the character if it's next
+    fn consume(
+        &mut self,
+        c: char,
+    ) -> Result<bool, String> {
+        self.skip_whitespace()?;
+        if self.peek() == Some(c) {
+            self.position += c.len_utf8();
+            Ok(true)
+        } else {
+            Ok(false)
+        }
+    }
+
+    fn expect(
+        &mut self,
+        c: char,
+    ) -> Result<(), String> {
+        if self.consume(c)? {
+            Ok(())
+        } else {
+            Err(self.error(&format!("expected '{}'", c)))
+        }
+    }
+
+    fn identifier(&mut self) -> Option<&'a str> {
+        let rest = self.rest();
+        if !rest.starts_with(|c: char| c.is_alphabetic() || c == '_') {
+            return None;
+        }
+
+        let length = rest
+            .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+            .unwrap_or_else(|| rest.len());
+        self.position += length;
+        Some(&rest[..length])
+    }
+
+    fn value(&mut self) -> Result<ComponentValue, String> {
+        self.skip_whitespace()?;
+        let span_index = self.spans.len();
+        let start = self.position;
+        self.spans.push(start..start);
+
+        let value = self.unspanned_value()?;
+        self.spans[span_index] = start..self.position;
+        Ok(value)
+    }
+
+    fn unspanned_value(&mut self) -> Result<ComponentValue, String> {
+        let rest = self.rest();
+        match self.peek() {
+            Some('(') => self.parenthesized(None),
+            Some('[') => {
+                self.position += 1;
+                Ok(ComponentValue::List(self.sequence(']', Parser::value)?))
+            }
+            Some('{') => {
+                self.position += 1;
+                let entries = self.sequence('}', |parser| {
+                    let key = parser.value()?;
+                    parser.expect(':')?;
+                    Ok((key, parser.value()?))
+                })?;
+                Ok(ComponentValue::Map(entries))
+            }
+            Some('"') => Ok(ComponentValue::String(self.string()?)),
+            Some('r') if rest[1..].starts_with(|c| c == '"' || c == '#') => {
+                Ok(ComponentValue::String(self.raw_string()?))
+            }
+            Some('\'') => {
+                self.position += 1;
+                let c = self.escaped_char('\'')?;
+                if !self.rest().starts_with('\'') {
+                    return Err(self.error("expected the end of the character"));
+                }
+                self.position += 1;
+                Ok(ComponentValue::Char(c))
+            }
+            Some(c) if c.is_ascii_digit() || c == '-' || c == '+' || c == '.' => {
+                let length = rest
+                    .find(|c: char| !(c.is_ascii_alphanumeric() || "_.+-".contains(c)))
+                    .unwrap_or_else(|| rest.len());
+                self.position += length;
+                Ok(ComponentValue::Number(rest[..length].to_string()))
+            }
+            Some(_) => {
+                let identifier = self
+                    .identifier()
+                    .ok_or_else(|| self.error("expected a value"))?;
+                match identifier {
+                    "true" => Ok(ComponentValue::Bool(true)),
+                    "false" => Ok(ComponentValue::Bool(false)),
+                    "inf" | "NaN" => Ok(ComponentValue::Number(identifier.to_string())),
+                    "None" => Ok(ComponentValue::Option(None)),
+                    "Some" => {
+                        self.expect('(')?;
+                        let value = self.value()?;
+                        self.consume(',')?;
+                        self.expect(')')?;
+                        Ok(ComponentValue::Option(Some(Box::new(value))))
+                    }
+                    _ => {
+                        self.skip_whitespace()?;
+                        if self.peek() == Some('(') {
+                            self.parenthesized(Some(identifier.to_string()))
+                        } else {
+                            Ok(ComponentValue::Ident(identifier.to_string()))
+                        }
+                    }
+                }
+            }
+            None => Err(self.error("unexpected end of data")),
+        }
+    }
+
+    // Parses a tuple or struct, starting at the opening parenthesis
+    fn parenthesized(
+        &mut self,
+        name: Option<String>,
+    ) -> Result<ComponentValue, String> {
+        self.expect('(')?;
+        if self.consume(')')? {
+            return Ok(match name {
+                Some(name) => ComponentValue::Tuple {
+                    name: Some(name),
+                    fields: Vec::new(),
+                },
+                None => ComponentValue::Unit,
+            });
+        }
+
+        // It's a struct if the first item is a field name followed by a colon
+        let start = self.position;
+        let is_struct = self.identifier().is_some() && self.consume(':')?;
+        self.position = start;
+
+        if is_struct {
+            let fields = self.sequence(')', |parser| {
+                parser.skip_whitespace()?;
+                let field_name = parser
+                    .identifier()
+                    .ok_or_else(|| parser.error("expected a field name"))?
+                    .to_string();
+                parser.expect(':')?;
+                Ok((field_name, parser.value()?))
+            })?;
+            Ok(ComponentValue::Struct { name, fields })
+        } else {
+            let fields = self.sequence(')', Parser::value)?;
+            Ok(ComponentValue::Tuple { name, fields })
+        }
+    }
+
+    // Parses comma separated items up to and including the closing character. The opening
+    // character must already be consumed
+    fn sequence<T>(
+        &mut self,
+        end: char,
+        mut item: impl FnMut(&mut Self) -> Result<T, String>,
+    ) -> Result<Vec<T>, String> {
+        let mut items = Vec::new();
+        loop {
+            if self.consume(end)? {
+                return Ok(items);
+            }
+
+            items.push(item(self)?);
+            if !self.consume(',')? {
+                self.expect(end)?;
+                return Ok(items);
+            }
+        }
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        self.position += 1;
+        let mut value = String::new();
+        loop {
+            if self.rest().starts_with('"') {
+                self.position += 1;
+                return Ok(value);
+            }
+
+            value.push(self.escaped_char('"')?);
+        }
+    }
+
+    // Parses r"..." or r#"..."# with any number of #
+    fn raw_string(&mut self) -> Result<String, String> {
+        self.position += 1;
+        let hashes = self.rest().len() - self.rest().trim_start_matches('#').len();
+        self.position += hashes;
+        if !self.rest().starts_with('"') {
+            return Err(self.error("expected '\"'"));
+        }
+        self.position += 1;
+
+        let terminator = format!("\"{}", "#".repeat(hashes));
+        let length = self
+            .rest()
+            .find(&terminator)
+            .ok_or_else(|| self.error("unterminated raw string"))?;
+        let value = self.rest()[..length].to_string();
+        self.position += length + terminator.len();
+        Ok(value)
+    }
+
+    // Parses one possibly escaped character of a string or character literal
+    fn escaped_char(
+        &mut self,
+        quote: char,
+    ) -> Result<char, String> {
+        let c = self
+            .peek()
+            .ok_or_else(|| self.error(&format!("expected '{}'", quote)))?;
+        self.position += c.len_utf8();
+        if c != '\\' {
+            return Ok(c);
+        }
+
+        let escape = self
+            .peek()
+            .ok_or_else(|| self.error("expected an escape sequence"))?;
+        self.position += escape.len_utf8();
+        match escape {
+            'n' => Ok('\n'),
+            'r' => Ok('\r'),
+            't' => Ok('\t'),
+            '0' => Ok('\0'),
+            'b' => Ok('\u{8}'),
+            'f' => Ok('\u{c}'),
+            'u' => {
+                // Either \u{1F600} or \u00E9
+                let digits = if self.rest().starts_with('{') {
+                    let end = self
+                        .rest()
+                        .find('}')
+                        .ok_or_else(|| self.error("unterminated unicode escape"))?;
+                    let digits = &self.rest()[1..end];
+                    self.position += end + 1;
+                    digits
+                } else {
+                    let digits = self.rest().get(..4).unwrap_or("");
+                    self.position += digits.len();
+                    digits
+                };
+
+                u32::from_str_radix(digits, 16)
+                    .ok()
+                    .and_then(std::char::from_u32)
+                    .ok_or_else(|| self.error("invalid unicode escape"))
+            }
+            c => Ok(c),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(text: &str) -> ComponentValue {
+        ComponentValue::parse(text).unwrap()
+    }
+
+    fn string(value: &str) -> ComponentValue {
+        ComponentValue::String(value.to_string())
+    }
+
+    fn number(value: &str) -> ComponentValue {
+        ComponentValue::Number(value.to_string())
+    }
+
+    #[test]
+    fn parses_primitives() {
+        assert_eq!(parse("()"), ComponentValue::Unit);
+        assert_eq!(parse("true"), ComponentValue::Bool(true));
+        assert_eq!(parse("false"), ComponentValue::Bool(false));
+        assert_eq!(parse("'a'"), ComponentValue::Char('a'));
+        assert_eq!(parse("'\\''"), ComponentValue::Char('\''));
+        assert_eq!(parse("None"), ComponentValue::Option(None));
+        assert_eq!(
+            parse("Some(1)"),
+            ComponentValue::Option(Some(Box::new(number("1"))))
+        );
+        assert_eq!(
+            parse("Dynamic"),
+            ComponentValue::Ident("Dynamic".to_string())
+        );
+    }
+
+    #[test]
+    fn keeps_numbers_as_written() {
+        let numbers = [
+            "0",
+            "-1",
+            "+2",
+            "1.5",
+            "-0.12796938",
+            "1e10",
+            "2.5E-3",
+            "0x1F",
+            "inf",
+            "NaN",
+        ];
+        for text in &numbers {
+            assert_eq!(parse(text), number(text));
+            assert_eq!(parse(text).to_ron_string(), *text);
+        }
+    }
+
+    #[test]
+    fn parses_strings() {
+        assert_eq!(parse(r#""plain""#), string("plain"));
+        assert_eq!(parse(r#""a\"b\\c\nd\te""#), string("a\"b\\c\nd\te"));
+        assert_eq!(parse(r#""\u{1F600}é""#), string("\u{1F600}\u{e9}"));
+        assert_eq!(parse(r#"r"raw \n""#), string("raw \\n"));
+        assert_eq!(parse(r###"r##"a "# b"##"###), string("a \"# b"));
+    }
+
+    #[test]
+    fn parses_collections() {
+        assert_eq!(
+            parse("[1, 2,]"),
+            ComponentValue::List(vec![number("1"), number("2")])
+        );
+        assert_eq!(parse("[]"), ComponentValue::List(Vec::new()));
+        assert_eq!(
+            parse(r#"{"a": 1, "b": 2}"#),
+            ComponentValue::Map(vec![(string("a"), number("1")), (string("b"), number("2"))])
+        );
+    }
+
+    #[test]
+    fn parses_tuples_and_structs() {
+        assert_eq!(
+            parse("Vec2(1.0, 2.0)"),
+            ComponentValue::Tuple {
+                name: Some("Vec2".to_string()),
+                fields: vec![number("1.0"), number("2.0")],
+            }
+        );
+        assert_eq!(
+            parse("(1, \"a\")"),
+            ComponentValue::Tuple {
+                name: None,
+                fields: vec![number("1"), string("a")],
+            }
+        );
+        assert_eq!(
+            parse("Empty()"),
+            ComponentValue::Tuple {
+                name: Some("Empty".to_string()),
+                fields: Vec::new(),
+            }
+        );
+        assert_eq!(
+            parse("Paint(color: Vec4(1.0, 1.0, 1.0, 1.0), stroke_width: 0.02)"),
+            ComponentValue::Struct {
+                name: Some("Paint".to_string()),
+                fields: vec![
+                    (
+                        "color".to_string(),
+                        ComponentValue::Tuple {
+                            name: Some("Vec4".to_string()),
+                            fields: vec![number("1.0"); 4],
+                        }
+                    ),
+                    ("stroke_width".to_string(), number("0.02")),
+                ],
+            }
+        );
+        assert_eq!(
+            parse("(radius: 0.5,)"),
+            ComponentValue::Struct {
+                name: None,
+                fields: vec![("radius".to_string(), number("0.5"))],
+            }
+        );
+    }
+
+    #[test]
+    fn ignores_comments() {
+        let text = "// header\n( /* inline */ a: 1, // trailing\n b: [ /* empty */ ] )";
+        assert_eq!(
+            parse(text),
+            ComponentValue::Struct {
+                name: None,
+                fields: vec![
+                    ("a".to_string(), number("1")),
+                    ("b".to_string(), ComponentValue::List(Vec::new())),
+                ],
+            }
+        );
+    }
+
+    #[test]
+    fn reports_error_positions() {
+        let error = ComponentValue::parse("(\n    a: 1,\n    b: ?,\n)").unwrap_err();
+        assert!(error.starts_with("line 3, column 8:"), "{}", error);
+
+        let error = ComponentValue::parse("[1, 2").unwrap_err();
+        assert!(error.starts_with("line 1, column 6:"), "{}", error);
+
+        let error = ComponentValue::parse("(a: 1) x").unwrap_err();
+        assert!(
+            error.contains("unexpected characters after the value"),
+            "{}",
+            error
+        );
+
+        assert!(ComponentValue::parse("\"unterminated").is_err());
+        assert!(ComponentValue::parse("r#\"unterminated\"").is_err());
+        assert!(ComponentValue::parse("/* unterminated").is_err());
+        assert!(ComponentValue::parse("").is_err());
+    }
+
+    #[test]
+    fn round_trips() {
+        let text = r#"Entity(
+            id: "e938c98b-df7e-41d7-a2e4-9f028702b022",
+            data: Position2DComponent(position: Vec2(-0.12796938, 2.868164)),
+            tags: ["a\"b", r"c\d"],
+            lookup: {'x': Some(()), 'y': None},
+            kind: Static,
+        )"#;
+        let value = parse(text);
+        assert_eq!(parse(&value.to_ron_string()), value);
+        assert_eq!(parse(&value.to_ron_string_single_line()), value);
+        assert!(!value.to_ron_string_single_line().contains('\n'));
+    }
+
+    #[test]
+    fn writes_pretty_ron() {
+        let value = parse("Paint(color: Vec4(1.0, 0.0, 0.0, 1.0), sizes: [1, 2])");
+        let expected = concat!(
+            "Paint(\n",
+            "    color: Vec4(1.0, 0.0, 0.0, 1.0),\n",
+            "    sizes: [\n",
+            "        1,\n",
+            "        2,\n",
+            "    ],\n",
+            ")",
+        );
+        assert_eq!(value.to_ron_string(), expected);
+        assert_eq!(
+            value.to_ron_string_single_line(),
+            "Paint(color: Vec4(1.0, 0.0, 0.0, 1.0), sizes: [1, 2])"
+        );
+    }
+
+    #[test]
+    fn edits_struct_fields() {
+        let mut value = parse("(size: 1, paint: 2)");
+        assert_eq!(value.remove_field("size"), Some(number("1")));
+        assert_eq!(value.remove_field("size"), None);
+        value.set_field("half_extents", number("3"));
+        value.set_field("paint", number("4"));
+        assert_eq!(value.field("half_extents"), Some(&number("3")));
+        assert_eq!(
+            value.to_ron_string_single_line(),
+            "(paint: 4, half_extents: 3)"
+        );
+
+        let mut list = parse("[1]");
+        list.set_field("a", number("1"));
+        assert_eq!(list, parse("[1]"));
+        assert_eq!(list.field_mut("a"), None);
+    }
+
+    #[test]
+    fn records_spans() {
+        let text = concat!(
+            "PrefabFile(\n",
+            "    prefab: (id: \"a\", objects: [Entity(( id: 1 ))]),\n",
+            "    instances: [],\n",
+            ")",
+        );
+        let document = ComponentValue::parse_spanned(text).unwrap();
+        let root = document.root();
+        assert_eq!(root.span(), 0..text.len());
+
+        let span_text = |value: SpannedValue| &text[value.span()];
+        let prefab = root.field("prefab").unwrap();
+        assert_eq!(
+            span_text(prefab),
+            "(id: \"a\", objects: [Entity(( id: 1 ))])"
+        );
+        assert_eq!(span_text(prefab.field("id").unwrap()), "\"a\"");
+
+        let objects = prefab.field("objects").unwrap().items();
+        assert_eq!(objects.len(), 1);
+        assert_eq!(span_text(objects[0]), "Entity(( id: 1 ))");
+        let entity = objects[0].items()[0];
+        assert_eq!(span_text(entity), "( id: 1 )");
+        assert_eq!(span_text(entity.field("id").unwrap()), "1");
+
+        assert_eq!(span_text(root.field("instances").unwrap()), "[]");
+        assert!(root.field("missing").is_none());
+        assert!(prefab.field("id").unwrap().items().is_empty());
+    }
+
+    #[test]
+    fn records_spans_after_maps_and_options() {
+        let text = "(a: {1: Some([2])}, b: Some((c: 3)), d: 4)";
+        let document = ComponentValue::parse_spanned(text).unwrap();
+        let root = document.root();
+        assert_eq!(&text[root.field("a").unwrap().span()], "{1: Some([2])}");
+        assert_eq!(&text[root.field("b").unwrap().span()], "Some((c: 3))");
+        assert_eq!(&text[root.field("d").unwrap().span()], "4");
+    }
+}
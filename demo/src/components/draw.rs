@@ -17,7 +17,15 @@ use crate::math::Vec2;
 use crate::math::Vec4;
 use imgui_inspect_derive::Inspect;
 use legion::prelude::*;
-use crate::resources::OpenedPrefabState;
+use crate::resources::{AssetResource, OpenedPrefabState};
+use crate::clone_merge::SpawnFrom;
+use crate::pipeline::{AtlasRegionAsset, ColorSpace, ImageAsset};
+use atelier_core::AssetUuid;
+use atelier_loader::handle::{AssetHandle, Handle};
+use atelier_loader::{LoadHandle, Loader};
+use legion::index::ComponentIndex;
+use legion::storage::ComponentStorage;
+use std::ops::Range;
 
 // A utility struct to describe color for a skia shape
 #[derive(Clone, Copy, Debug, Serialize, Deserialize, SerdeDiff, PartialEq, Inspect, Default)]
@@ -196,3 +204,221 @@ impl crate::selection::EditorSelectable for DrawSkiaCircleComponent {
         }
     }
 }
+
+//
+// Draw an image (or a region of a texture atlas) at the component's current location, stretched to
+// fill half_extents. Will be affected by scale and rotation, if those components exist
+//
+#[derive(
+    TypeUuid,
+    Serialize,
+    Deserialize,
+    SerdeImportable,
+    SerdeDiff,
+    Debug,
+    PartialEq,
+    Clone,
+    Inspect,
+    Default,
+)]
+#[uuid = "8b4d2ec5-0d0f-4c4e-9f6c-2f3b1d5a7e61"]
+pub struct DrawSkiaSpriteComponentDef {
+    #[serde_diff(opaque)]
+    #[inspect(skip)]
+    pub image: Option<Handle<ImageAsset>>,
+
+    // If set, this region of its atlas is drawn instead of image
+    #[serde_diff(opaque)]
+    #[inspect(skip)]
+    pub region: Option<Handle<AtlasRegionAsset>>,
+
+    #[serde_diff(opaque)]
+    pub half_extents: Vec2,
+}
+
+legion_prefab::register_component_type!(DrawSkiaSpriteComponentDef);
+
+// The skia image that was created from an ImageAsset, and the asset and version it was created from
+struct SpriteImage {
+    image: skia_safe::Image,
+    load_handle: LoadHandle,
+    version: u32,
+}
+
+pub struct DrawSkiaSpriteComponent {
+    pub image: Option<Handle<ImageAsset>>,
+    pub region: Option<Handle<AtlasRegionAsset>>,
+    pub half_extents: Vec2,
+
+    // Keeps the atlas image that region is in loaded
+    atlas_image: std::sync::Mutex<Option<(AssetUuid, Handle<ImageAsset>)>>,
+    sprite_image: std::sync::Mutex<Option<SpriteImage>>,
+}
+
+unsafe impl Send for DrawSkiaSpriteComponent {}
+unsafe impl Sync for DrawSkiaSpriteComponent {}
+
+impl DrawSkiaSpriteComponent {
+    /// Returns the skia image to draw and, for atlas regions, the part of it to draw. Returns None
+    /// if the image hasn't loaded yet. If a new version of the image asset has loaded (i.e. the
+    /// source file changed), the skia image is recreated
+    pub fn skia_image(
+        &self,
+        asset_resource: &AssetResource,
+    ) -> Option<(skia_safe::Image, Option<skia_safe::Rect>)> {
+        let mut atlas_image = self.atlas_image.lock().unwrap();
+        let (handle, src_rect) = match &self.region {
+            Some(region) => {
+                let region = region.asset(asset_resource.storage())?;
+
+                // Start loading the atlas image the first time, or if the region moved to another
+                // atlas
+                let is_new_atlas = atlas_image
+                    .as_ref()
+                    .map(|(atlas_uuid, _)| *atlas_uuid != region.image)
+                    .unwrap_or(true);
+
+                if is_new_atlas {
+                    let load_handle = asset_resource.loader().add_ref(region.image);
+                    let handle = Handle::new(asset_resource.tx().clone(), load_handle);
+                    *atlas_image = Some((region.image, handle));
+                }
+
+                let src_rect = skia_safe::Rect::from_xywh(
+                    region.x as f32,
+                    region.y as f32,
+                    region.width as f32,
+                    region.height as f32,
+                );
+
+                (&atlas_image.as_ref().unwrap().1, Some(src_rect))
+            }
+            None => (self.image.as_ref()?, None),
+        };
+
+        let mut sprite_image = self.sprite_image.lock().unwrap();
+        if let Some(version) = handle.asset_version::<ImageAsset, _>(asset_resource.storage()) {
+            let is_stale = sprite_image
+                .as_ref()
+                .map(|sprite_image| {
+                    sprite_image.load_handle != handle.load_handle()
+                        || sprite_image.version != version
+                })
+                .unwrap_or(true);
+
+            if is_stale {
+                let image_asset = handle.asset(asset_resource.storage()).unwrap();
+                match create_skia_image(image_asset) {
+                    Some(image) => {
+                        *sprite_image = Some(SpriteImage {
+                            image,
+                            load_handle: handle.load_handle(),
+                            version,
+                        })
+                    }
+                    None => log::error!("Failed to create skia image for version {}", version),
+                }
+            }
+        }
+
+        sprite_image
+            .as_ref()
+            .map(|sprite_image| (sprite_image.image.clone(), src_rect))
+    }
+}
+
+fn create_skia_image(image_asset: &ImageAsset) -> Option<skia_safe::Image> {
+    let color_info = image_asset.color_info();
+    let alpha_type = if color_info.premultiplied_alpha {
+        skia_safe::AlphaType::Premul
+    } else {
+        skia_safe::AlphaType::Unpremul
+    };
+
+    let color_space = match color_info.color_space {
+        ColorSpace::Srgb => skia_safe::ColorSpace::new_srgb(),
+        ColorSpace::Linear => skia_safe::ColorSpace::new_srgb_linear(),
+    };
+
+    let image_info = skia_safe::ImageInfo::new(
+        (image_asset.width() as i32, image_asset.height() as i32),
+        skia_safe::ColorType::RGBA8888,
+        alpha_type,
+        Some(color_space),
+    );
+
+    let pixels = skia_safe::Data::new_copy(&image_asset.to_rgba8());
+    skia_safe::Image::from_raster_data(&image_info, pixels, image_asset.width() * 4)
+}
+
+impl SpawnFrom<DrawSkiaSpriteComponentDef> for DrawSkiaSpriteComponent {
+    fn spawn_from(
+        _src_world: &World,
+        _src_component_storage: &ComponentStorage,
+        _src_component_storage_indexes: Range<ComponentIndex>,
+        resources: &Resources,
+        _src_entities: &[Entity],
+        _dst_entities: &[Entity],
+        from: &[DrawSkiaSpriteComponentDef],
+        into: &mut [std::mem::MaybeUninit<Self>],
+    ) {
+        let asset_resource = resources.get::<AssetResource>().unwrap();
+
+        for (from, into) in from.iter().zip(into) {
+            let component = DrawSkiaSpriteComponent {
+                image: from.image.clone(),
+                region: from.region.clone(),
+                half_extents: from.half_extents,
+                atlas_image: Default::default(),
+                sprite_image: Default::default(),
+            };
+
+            // Create the skia image now if the image is already loaded. Otherwise it's created when
+            // it's first drawn
+            component.skia_image(&*asset_resource);
+
+            *into = std::mem::MaybeUninit::new(component);
+        }
+    }
+}
+
+impl crate::selection::EditorSelectable for DrawSkiaSpriteComponent {
+    fn create_editor_selection_world(
+        &self,
+        collision_world: &mut CollisionWorld<f32, Entity>,
+        resources: &Resources,
+        opened_prefab: &OpenedPrefabState,
+        world: &World,
+        entity: Entity,
+    ) {
+        if let Some(position) = world.get_component::<Position2DComponent>(entity) {
+            let mut half_extents = *self.half_extents;
+
+            if let Some(uniform_scale) = world.get_component::<UniformScale2DComponent>(entity) {
+                half_extents *= uniform_scale.uniform_scale;
+            }
+
+            if let Some(non_uniform_scale) =
+                world.get_component::<NonUniformScale2DComponent>(entity)
+            {
+                half_extents *= *non_uniform_scale.non_uniform_scale;
+            }
+
+            let mut rotation = 0.0;
+            if let Some(rotation_component) = world.get_component::<Rotation2DComponent>(entity) {
+                rotation = rotation_component.rotation;
+            }
+
+            let shape_handle =
+                ShapeHandle::new(Cuboid::new(crate::math::vec2_glam_to_glm(half_extents)));
+
+            collision_world.add(
+                ncollide2d::math::Isometry::new(position.position.into(), rotation),
+                shape_handle,
+                CollisionGroups::new(),
+                GeometricQueryType::Proximity(0.001),
+                entity,
+            );
+        }
+    }
+}
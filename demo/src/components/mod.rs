@@ -20,6 +20,8 @@ pub use draw::DrawSkiaCircleComponent;
 pub use draw::DrawSkiaCircleComponentDef;
 pub use draw::DrawSkiaBoxComponent;
 pub use draw::DrawSkiaBoxComponentDef;
+pub use draw::DrawSkiaSpriteComponent;
+pub use draw::DrawSkiaSpriteComponentDef;
 pub use draw::PaintDef;
 pub use draw::Paint;
 
@@ -1,11 +1,21 @@
 use std::{
+    collections::HashMap,
+    io::Read,
     net::{AddrParseError, SocketAddr},
-    path::PathBuf,
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicUsize, Ordering},
+    sync::mpsc::{self, RecvTimeoutError, TryRecvError},
+    sync::Arc,
+    time::{Duration, SystemTime},
 };
 
 use atelier_daemon::AssetDaemon;
+use atelier_importer::{BoxedImporter, BoxedImporterValue, SerdeObj, SourceMetadata};
+use type_uuid::TypeUuidDynamic;
 use structopt::StructOpt;
 
+use crate::pack_file::find_importer;
+
 /// Parameters to the asset daemon.
 ///
 /// # Examples
@@ -13,7 +23,7 @@ use structopt::StructOpt;
 /// ```bash
 /// asset_daemon --db .assets_db --address "127.0.0.1:9999" assets
 /// ```
-#[derive(StructOpt)]
+#[derive(StructOpt, Clone, Debug)]
 pub struct AssetDaemonOpt {
     /// Path to the asset metadata database directory.
     #[structopt(name = "db", long, parse(from_os_str), default_value = ".assets_db")]
@@ -53,23 +63,437 @@ fn init_modules() {
     // }
 }
 
+/// What a running daemon is serving
+#[derive(Debug, Clone)]
+pub struct DaemonInfo {
+    pub address: SocketAddr,
+    pub asset_dirs: Vec<PathBuf>,
+
+    /// The file extensions that have importers registered
+    pub importers: Vec<String>,
+}
+
+impl DaemonInfo {
+    fn new(opt: &AssetDaemonOpt) -> Self {
+        DaemonInfo {
+            address: opt.address,
+            asset_dirs: opt.asset_dirs.clone(),
+            importers: atelier_importer::get_source_importers()
+                .map(|(ext, _)| ext.to_string())
+                .collect(),
+        }
+    }
+}
+
+/// Returns the extension that the daemon looks up a source file's importer by. Only the last part
+/// of the file's extension counts, so "level.prefab.json" is imported by the importer registered
+/// for "json"
+pub fn importer_extension(path: &Path) -> Option<String> {
+    path.extension()
+        .map(|extension| extension.to_string_lossy().to_lowercase())
+}
+
+// Logs an error for each importer registration that the daemon's lookup can never pick
+fn check_importer_lookup(info: &DaemonInfo) {
+    for registered_extension in &info.importers {
+        if registered_extension.trim_start_matches('.').contains('.') {
+            log::error!(
+                "the importer registered for {} is never used, the daemon only matches the last \
+                 part of a file's extension",
+                registered_extension
+            );
+        }
+    }
+}
+
+/// Errors that can occur while waiting for the daemon to become ready
+#[derive(Debug)]
+pub enum DaemonWaitError {
+    /// The daemon did not finish its first import pass within the given time. It keeps importing,
+    /// so assets that aren't ready yet load once they are imported
+    Timeout(Duration),
+
+    /// The daemon thread exited (usually a panic, which is logged) before it became ready
+    Stopped,
+}
+
+impl std::error::Error for DaemonWaitError {}
+
+impl core::fmt::Display for DaemonWaitError {
+    fn fmt(
+        &self,
+        f: &mut core::fmt::Formatter,
+    ) -> core::fmt::Result {
+        match self {
+            DaemonWaitError::Timeout(timeout) => write!(
+                f,
+                "the asset daemon did not become ready within {:.1}s",
+                timeout.as_secs_f32()
+            ),
+            DaemonWaitError::Stopped => {
+                write!(f, "the asset daemon stopped before it became ready")
+            }
+        }
+    }
+}
+
+/// A daemon running on a background thread, returned by spawn()
+pub struct DaemonHandle {
+    info: DaemonInfo,
+    tracker: Arc<ImportTracker>,
+    ready_rx: mpsc::Receiver<()>,
+    ready: bool,
+}
+
+impl DaemonHandle {
+    pub fn info(&self) -> &DaemonInfo {
+        &self.info
+    }
+
+    /// The number of imports that have failed since the daemon started. The daemon logs the
+    /// error for each one
+    pub fn failed_import_count(&self) -> usize {
+        self.tracker.failed.load(Ordering::SeqCst)
+    }
+
+    /// Blocks until the daemon has finished its first pass over the asset directories, so every
+    /// source file that needed importing has been imported or has failed to. Returns immediately
+    /// if it's already ready
+    pub fn wait_until_ready(
+        &mut self,
+        timeout: Duration,
+    ) -> Result<&DaemonInfo, DaemonWaitError> {
+        if !self.ready {
+            match self.ready_rx.recv_timeout(timeout) {
+                Ok(()) => self.ready = true,
+                Err(RecvTimeoutError::Timeout) => return Err(DaemonWaitError::Timeout(timeout)),
+                Err(RecvTimeoutError::Disconnected) => return Err(DaemonWaitError::Stopped),
+            }
+        }
+
+        Ok(&self.info)
+    }
+}
+
+// How often to check whether the daemon has finished its first import pass
+const READY_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+// Counts the imports the daemon runs, so spawn_with can tell when its first pass is done
+#[derive(Default)]
+struct ImportTracker {
+    started: AtomicUsize,
+    finished: AtomicUsize,
+    failed: AtomicUsize,
+}
+
+// Wraps an importer to count its imports in an ImportTracker
+struct TrackedImporter {
+    importer: Box<dyn BoxedImporter>,
+    tracker: Arc<ImportTracker>,
+}
+
+impl TypeUuidDynamic for TrackedImporter {
+    fn uuid(&self) -> [u8; 16] {
+        self.importer.uuid()
+    }
+}
+
+impl BoxedImporter for TrackedImporter {
+    fn import_boxed(
+        &self,
+        source: &mut dyn Read,
+        options: Box<dyn SerdeObj>,
+        state: Box<dyn SerdeObj>,
+    ) -> atelier_importer::Result<BoxedImporterValue> {
+        self.tracker.started.fetch_add(1, Ordering::SeqCst);
+        let result = self.importer.import_boxed(source, options, state);
+
+        // Failed imports count as finished. The daemon logs the error and moves on, and the file
+        // isn't imported again until it changes
+        if result.is_err() {
+            self.tracker.failed.fetch_add(1, Ordering::SeqCst);
+        }
+        self.tracker.finished.fetch_add(1, Ordering::SeqCst);
+        result
+    }
+
+    fn default_options(&self) -> Box<dyn SerdeObj> {
+        self.importer.default_options()
+    }
+
+    fn default_state(&self) -> Box<dyn SerdeObj> {
+        self.importer.default_state()
+    }
+
+    fn version(&self) -> u32 {
+        self.importer.version()
+    }
+
+    fn deserialize_metadata(
+        &self,
+        bytes: &[u8],
+    ) -> atelier_importer::Result<SourceMetadata<Box<dyn SerdeObj>, Box<dyn SerdeObj>>> {
+        self.importer.deserialize_metadata(bytes)
+    }
+
+    fn deserialize_options(
+        &self,
+        bytes: &[u8],
+    ) -> atelier_importer::Result<Box<dyn SerdeObj>> {
+        self.importer.deserialize_options(bytes)
+    }
+
+    fn deserialize_state(
+        &self,
+        bytes: &[u8],
+    ) -> atelier_importer::Result<Box<dyn SerdeObj>> {
+        self.importer.deserialize_state(bytes)
+    }
+}
+
+// Returns the number of source files the daemon imports on its first pass. Without a metadata
+// database it imports every file that has an importer. Otherwise it only imports the files that
+// changed, or whose importer changed, since it last ran, and those are the files whose .meta file
+// (which it writes along with the database) is missing or out of date
+fn count_files_to_import(opt: &AssetDaemonOpt) -> usize {
+    if opt.db_dir.exists() {
+        match crate::pack_file::read_asset_metadata(&opt.asset_dirs) {
+            Ok((_, unprocessed)) => return unprocessed.len(),
+            Err(e) => log::warn!("Expecting the asset daemon to import every file: {}", e),
+        }
+    }
+
+    let mut count = 0;
+    for asset_dir in &opt.asset_dirs {
+        match crate::pack_file::find_source_files(asset_dir) {
+            Ok(paths) => count += paths.iter().filter(|path| find_importer(path).is_some()).count(),
+            Err(e) => log::warn!("{}", e),
+        }
+    }
+
+    count
+}
+
+/// Runs the daemon on a background thread, configured from the process args. Call
+/// wait_until_ready() on the returned handle before loading assets
+pub fn spawn() -> DaemonHandle {
+    spawn_with(AssetDaemonOpt::from_args())
+}
+
+/// Runs the daemon on a background thread with the given settings. Call wait_until_ready() on the
+/// returned handle before loading assets
+pub fn spawn_with(opt: AssetDaemonOpt) -> DaemonHandle {
+    init_modules();
+
+    let info = DaemonInfo::new(&opt);
+    log_daemon_info(&info);
+    check_importer_lookup(&info);
+
+    // Must be counted before the daemon starts writing .meta files
+    let files_to_import = count_files_to_import(&opt);
+    let tracker = Arc::new(ImportTracker::default());
+
+    // The daemon never returns, so this is only disconnected if its thread panics
+    let (stopped_tx, stopped_rx) = mpsc::channel::<()>();
+    let daemon_tracker = tracker.clone();
+    let ready_tracker = tracker.clone();
+    std::thread::Builder::new()
+        .name("asset-daemon".to_string())
+        .spawn(move || {
+            let _stopped_tx = stopped_tx;
+            run_daemon(opt, Some(daemon_tracker));
+        })
+        .expect("failed to start the asset daemon thread");
+
+    // atelier_daemon doesn't report when its first pass is done, but every import goes through
+    // the tracked importers. The pass is done when as many imports as there were files to import
+    // have finished and none are running. Dropping ready_tx without sending tells the waiter that
+    // the daemon stopped
+    let (ready_tx, ready_rx) = mpsc::channel();
+    let address = info.address;
+    std::thread::Builder::new()
+        .name("asset-daemon-ready".to_string())
+        .spawn(move || loop {
+            if let Err(TryRecvError::Disconnected) = stopped_rx.try_recv() {
+                log::error!("The asset daemon stopped before it became ready");
+                return;
+            }
+
+            let started = ready_tracker.started.load(Ordering::SeqCst);
+            let finished = ready_tracker.finished.load(Ordering::SeqCst);
+            if finished >= files_to_import && finished >= started {
+                log::info!(
+                    "The asset daemon is ready on {} after {} imports",
+                    address,
+                    finished
+                );
+                let _ = ready_tx.send(());
+                return;
+            }
+
+            log::trace!(
+                "Waiting for the asset daemon to import {} files ({} running)",
+                files_to_import.saturating_sub(finished),
+                started.saturating_sub(finished)
+            );
+            std::thread::sleep(READY_POLL_INTERVAL);
+        })
+        .expect("failed to start the asset daemon readiness thread");
+
+    DaemonHandle {
+        info,
+        tracker,
+        ready_rx,
+        ready: false,
+    }
+}
+
+/// Runs the daemon on this thread, configured from the process args. This never returns
 pub fn run() {
+    run_with(AssetDaemonOpt::from_args());
+}
+
+/// Runs the daemon on this thread with the given settings. This never returns
+pub fn run_with(opt: AssetDaemonOpt) {
     init_modules();
 
+    let info = DaemonInfo::new(&opt);
+    log_daemon_info(&info);
+    check_importer_lookup(&info);
+    run_daemon(opt, None);
+}
+
+fn log_daemon_info(info: &DaemonInfo) {
+    log::info!("registered importers for {}", info.importers.join(", "));
     log::info!(
-        "registered importers for {}",
-        atelier_importer::get_source_importers()
-            .map(|(ext, _)| ext)
+        "starting the asset daemon on {} for {}",
+        info.address,
+        info.asset_dirs
+            .iter()
+            .map(|dir| dir.display().to_string())
             .collect::<Vec<_>>()
             .join(", ")
     );
+}
+
+// Runs the daemon. If a tracker is given, every import is counted in it
+fn run_daemon(
+    opt: AssetDaemonOpt,
+    tracker: Option<Arc<ImportTracker>>,
+) {
+    // Let importers find the source files that other source files refer to
+    crate::pipeline::set_asset_dirs(opt.asset_dirs.clone());
 
-    let opt = AssetDaemonOpt::from_args();
+    let watched_asset_dirs = opt.asset_dirs.clone();
+    std::thread::Builder::new()
+        .name("asset-daemon-atlas-watcher".to_string())
+        .spawn(move || watch_atlas_images(watched_asset_dirs))
+        .expect("failed to start the atlas image watcher thread");
+
+    let importers = atelier_importer::get_source_importers().map(move |(extension, importer)| {
+        let importer: Box<dyn BoxedImporter> = match &tracker {
+            Some(tracker) => Box::new(TrackedImporter {
+                importer,
+                tracker: tracker.clone(),
+            }),
+            None => importer,
+        };
+        (extension, importer)
+    });
 
     AssetDaemon::default()
-        .with_importers(atelier_importer::get_source_importers())
+        .with_importers(importers)
         .with_db_path(opt.db_dir)
         .with_address(opt.address)
         .with_asset_dirs(opt.asset_dirs)
         .run();
 }
+
+// How often watch_atlas_images checks the images that atlases are packed from
+const ATLAS_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+// When an .atlas file and the images it's packed from were last modified. An image that can't be
+// found has no time, so adding it counts as a change
+#[derive(PartialEq)]
+struct AtlasModifiedTimes {
+    atlas: Option<SystemTime>,
+    images: Vec<(PathBuf, Option<SystemTime>)>,
+}
+
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+fn atlas_modified_times(atlas_path: &Path) -> Result<AtlasModifiedTimes, String> {
+    let images = crate::pipeline::read_atlas_image_paths(atlas_path)?
+        .into_iter()
+        .map(|image_path| {
+            let modified = crate::pipeline::find_source_file(&image_path)
+                .and_then(|path| modified_time(&path));
+            (image_path, modified)
+        })
+        .collect();
+
+    Ok(AtlasModifiedTimes {
+        atlas: modified_time(atlas_path),
+        images,
+    })
+}
+
+// The daemon only imports a source file again when that file changes, but an atlas is packed from
+// other images. This polls the images of every atlas and, when one of them changes, writes the
+// .atlas file back unchanged so that the daemon sees it change and packs the atlas again. Changes
+// made while the daemon isn't running aren't seen
+fn watch_atlas_images(asset_dirs: Vec<PathBuf>) {
+    let mut last_modified_times = HashMap::new();
+    loop {
+        for asset_dir in &asset_dirs {
+            let atlas_paths = match crate::pack_file::find_source_files(asset_dir) {
+                Ok(paths) => paths
+                    .into_iter()
+                    .filter(|path| importer_extension(path).as_deref() == Some("atlas")),
+                Err(e) => {
+                    log::warn!("{}", e);
+                    continue;
+                }
+            };
+
+            for atlas_path in atlas_paths {
+                let modified_times = match atlas_modified_times(&atlas_path) {
+                    Ok(modified_times) => modified_times,
+                    // The importer reports errors in the atlas when the daemon imports it
+                    Err(_) => {
+                        last_modified_times.remove(&atlas_path);
+                        continue;
+                    }
+                };
+
+                // If the .atlas file changed too, the daemon imports it anyway
+                let images_changed = match last_modified_times.get(&atlas_path) {
+                    Some(AtlasModifiedTimes { atlas, images }) => {
+                        *atlas == modified_times.atlas && *images != modified_times.images
+                    }
+                    None => false,
+                };
+
+                if images_changed {
+                    log::info!(
+                        "The images of {} changed, importing it again",
+                        atlas_path.display()
+                    );
+                    let result = std::fs::read(&atlas_path)
+                        .and_then(|contents| std::fs::write(&atlas_path, contents));
+                    if let Err(e) = result {
+                        log::error!("Failed to update {}: {}", atlas_path.display(), e);
+                    }
+                }
+
+                last_modified_times.insert(atlas_path, modified_times);
+            }
+        }
+
+        std::thread::sleep(ATLAS_POLL_INTERVAL);
+    }
+}
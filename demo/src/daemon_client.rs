@@ -0,0 +1,170 @@
+//! Queries a running asset daemon over the same RPC interface that RpcLoader uses to request
+//! assets. This reads what the daemon has recorded in its metadata database without importing
+//! anything.
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::future::Future;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+
+use atelier_core::AssetUuid;
+use atelier_schema::data::asset_metadata;
+use atelier_schema::service::asset_hub;
+use capnp::message::ReaderOptions;
+use capnp_rpc::{rpc_twoparty_capnp, twoparty, RpcSystem};
+use futures::{AsyncReadExt, FutureExt};
+
+/// An asset as recorded in the daemon's metadata database
+pub struct DaemonAssetEntry {
+    pub id: AssetUuid,
+
+    /// The asset's TypeUuid, or None if the daemon hasn't produced the asset yet
+    pub asset_type: Option<[u8; 16]>,
+
+    /// The source file the asset was imported from, or None if the daemon doesn't know it
+    pub source: Option<PathBuf>,
+}
+
+/// Returns every asset in the metadata database of the daemon listening on the given address
+pub fn list_assets(address: SocketAddr) -> Result<Vec<DaemonAssetEntry>, String> {
+    run_rpc(list_assets_async(address))
+        .map_err(|e| format!("failed to list the assets of the daemon on {}: {}", address, e))
+}
+
+/// Returns the source file that the daemon listening on the given address imported the asset
+/// from, or None if the daemon doesn't know the asset
+pub fn find_source_path(
+    address: SocketAddr,
+    id: AssetUuid,
+) -> Result<Option<PathBuf>, String> {
+    run_rpc(find_source_path_async(address, id)).map_err(|e| {
+        format!(
+            "failed to ask the daemon on {} for the source of {}: {}",
+            address, id, e
+        )
+    })
+}
+
+// capnp-rpc isn't Send, so requests have to run on a single threaded runtime
+fn run_rpc<T>(request: impl Future<Output = Result<T, Box<dyn Error>>>) -> Result<T, String> {
+    let mut runtime = tokio::runtime::Builder::new()
+        .basic_scheduler()
+        .enable_all()
+        .build()
+        .map_err(|e| format!("failed to start the RPC runtime: {}", e))?;
+
+    let local = tokio::task::LocalSet::new();
+    local
+        .block_on(&mut runtime, request)
+        .map_err(|e| e.to_string())
+}
+
+// Connects to the daemon and returns a snapshot of its metadata database. This has to be called
+// from within run_rpc, since the RPC system is spawned on its LocalSet
+async fn get_snapshot(address: SocketAddr) -> Result<asset_hub::snapshot::Client, Box<dyn Error>> {
+    let stream = tokio::net::TcpStream::connect(&address).await?;
+    stream.set_nodelay(true)?;
+    let (reader, writer) = futures_tokio_compat::Compat::new(stream).split();
+    let rpc_network = Box::new(twoparty::VatNetwork::new(
+        reader,
+        writer,
+        rpc_twoparty_capnp::Side::Client,
+        *ReaderOptions::new()
+            .nesting_limit(64)
+            .traversal_limit_in_words(64 * 1024 * 1024),
+    ));
+
+    let mut rpc_system = RpcSystem::new(rpc_network, None);
+    let hub: asset_hub::Client = rpc_system.bootstrap(rpc_twoparty_capnp::Side::Server);
+    tokio::task::spawn_local(rpc_system.map(|_| ()));
+
+    let snapshot = hub
+        .get_snapshot_request()
+        .send()
+        .promise
+        .await?
+        .get()?
+        .get_snapshot()?;
+
+    Ok(snapshot)
+}
+
+// The metadata doesn't include where each asset came from, so that's a separate request. Assets
+// the daemon doesn't know are left out of the returned map
+async fn get_source_paths(
+    snapshot: &asset_hub::snapshot::Client,
+    ids: &[AssetUuid],
+) -> Result<HashMap<AssetUuid, PathBuf>, Box<dyn Error>> {
+    let mut path_request = snapshot.get_path_for_assets_request();
+    {
+        let mut assets = path_request.get().init_assets(ids.len() as u32);
+        for (i, id) in ids.iter().enumerate() {
+            assets.reborrow().get(i as u32).set_id(&id.0);
+        }
+    }
+
+    let path_response = path_request.send().promise.await?;
+    let mut sources = HashMap::new();
+    for asset_path in path_response.get()?.get_paths()?.iter() {
+        let id = AssetUuid(to_uuid_bytes(asset_path.get_id()?.get_id()?)?);
+        let path = PathBuf::from(String::from_utf8_lossy(asset_path.get_path()?).into_owned());
+        sources.insert(id, path);
+    }
+
+    Ok(sources)
+}
+
+async fn list_assets_async(address: SocketAddr) -> Result<Vec<DaemonAssetEntry>, Box<dyn Error>> {
+    let snapshot = get_snapshot(address).await?;
+    let metadata_response = snapshot
+        .get_all_asset_metadata_request()
+        .send()
+        .promise
+        .await?;
+
+    let mut entries = Vec::new();
+    for asset in metadata_response.get()?.get_assets()?.iter() {
+        let id = AssetUuid(to_uuid_bytes(asset.get_id()?.get_id()?)?);
+        let asset_type = match asset.get_latest_artifact().which()? {
+            asset_metadata::latest_artifact::Artifact(artifact) => {
+                Some(to_uuid_bytes(artifact?.get_type_id()?)?)
+            }
+            asset_metadata::latest_artifact::None(_) => None,
+        };
+
+        entries.push(DaemonAssetEntry {
+            id,
+            asset_type,
+            source: None,
+        });
+    }
+
+    let ids: Vec<_> = entries.iter().map(|entry| entry.id).collect();
+    let mut sources = get_source_paths(&snapshot, &ids).await?;
+    for entry in &mut entries {
+        entry.source = sources.remove(&entry.id);
+    }
+
+    Ok(entries)
+}
+
+async fn find_source_path_async(
+    address: SocketAddr,
+    id: AssetUuid,
+) -> Result<Option<PathBuf>, Box<dyn Error>> {
+    let snapshot = get_snapshot(address).await?;
+    let mut sources = get_source_paths(&snapshot, &[id]).await?;
+    Ok(sources.remove(&id))
+}
+
+// The daemon sends UUIDs as 16 bytes of data
+fn to_uuid_bytes(data: &[u8]) -> Result<[u8; 16], Box<dyn Error>> {
+    if data.len() != 16 {
+        return Err(format!("expected a 16 byte UUID, got {} bytes", data.len()).into());
+    }
+
+    let mut bytes = [0; 16];
+    bytes.copy_from_slice(data);
+    Ok(bytes)
+}
@@ -7,10 +7,11 @@ extern crate nalgebra_glm as glm;
 use legion::prelude::*;
 
 use std::collections::HashMap;
+use std::net::SocketAddr;
 use legion::storage::ComponentTypeId;
 use legion_prefab::ComponentRegistration;
 use prefab_format::ComponentTypeUuid;
-use atelier_core::asset_uuid;
+use atelier_core::AssetUuid;
 
 pub mod temp_test;
 
@@ -25,6 +26,7 @@ use components::*;
 
 mod resources;
 use resources::*;
+pub use resources::{AssetResource, AssetSource};
 
 mod systems;
 use systems::*;
@@ -39,7 +41,7 @@ pub mod math;
 
 pub mod transactions;
 
-mod pipeline;
+pub mod pipeline;
 use pipeline::*;
 use std::sync::mpsc::RecvTimeoutError::Timeout;
 use std::borrow::BorrowMut;
@@ -47,12 +49,26 @@ use nphysics2d::object::RigidBodyDesc;
 
 pub mod daemon;
 
-mod prefab_cooking;
+pub mod daemon_client;
 
-mod component_diffs;
+pub mod pack_file;
+
+pub mod pack_loader;
+use pack_loader::PackLoader;
+
+pub mod prefab_cooking;
+
+pub mod component_diffs;
+
+pub mod component_schemas;
+
+pub mod component_value;
 
 pub mod app;
 
+pub mod app_config;
+use app_config::AppConfig;
+
 mod imgui_support;
 
 pub const GROUND_HALF_EXTENTS_WIDTH: f32 = 3.0;
@@ -60,8 +76,19 @@ pub const GRAVITY: f32 = -9.81;
 
 /// Create the asset manager that has all the required types registered
 pub fn create_asset_manager() -> AssetResource {
-    let mut asset_manager = AssetResource::default();
+    create_asset_manager_with_source(AssetSource::Rpc(Default::default()))
+}
+
+/// Create the asset manager that has all the required types registered, reading assets from the
+/// given source
+pub fn create_asset_manager_with_source(source: AssetSource) -> AssetResource {
+    let mut asset_manager = AssetResource::new(source);
     asset_manager.add_storage::<PrefabAsset>();
+    asset_manager.add_storage::<CookedPrefabAsset>();
+    asset_manager.add_storage::<AtlasRegionAsset>();
+
+    // Images are large and contain no handles, so they can be loaded off the main thread
+    asset_manager.add_async_storage::<ImageAsset>(None);
     asset_manager
 }
 
@@ -90,11 +117,14 @@ pub fn create_copy_clone_impl() -> CopyCloneImpl {
     clone_merge_impl
 }
 
-pub fn create_spawn_clone_impl<'a>(resources: &'a Resources) -> SpawnCloneImpl<'a> {
-    let component_registry = create_component_registry();
+pub fn create_spawn_clone_impl<'a>(
+    component_registry: &'a HashMap<ComponentTypeId, ComponentRegistration>,
+    resources: &'a Resources,
+) -> SpawnCloneImpl<'a> {
     let mut clone_merge_impl = clone_merge::SpawnCloneImpl::new(component_registry, resources);
     clone_merge_impl.add_mapping_into::<DrawSkiaCircleComponentDef, DrawSkiaCircleComponent>();
     clone_merge_impl.add_mapping_into::<DrawSkiaBoxComponentDef, DrawSkiaBoxComponent>();
+    clone_merge_impl.add_mapping::<DrawSkiaSpriteComponentDef, DrawSkiaSpriteComponent>();
     clone_merge_impl.add_mapping::<RigidBodyBallComponentDef, RigidBodyComponent>();
     clone_merge_impl.add_mapping::<RigidBodyBoxComponentDef, RigidBodyComponent>();
     clone_merge_impl
@@ -104,6 +134,7 @@ pub fn create_editor_selection_registry() -> EditorSelectableRegistry {
     let mut registry = EditorSelectableRegistry::default();
     registry.register::<DrawSkiaBoxComponent>();
     registry.register::<DrawSkiaCircleComponent>();
+    registry.register::<DrawSkiaSpriteComponent>();
     registry.register_transformed::<RigidBodyBoxComponentDef, RigidBodyComponent>();
     registry.register_transformed::<RigidBodyBallComponentDef, RigidBodyComponent>();
     registry
@@ -113,6 +144,7 @@ pub fn create_editor_inspector_registry() -> EditorInspectRegistry {
     let mut registry = EditorInspectRegistry::default();
     registry.register::<DrawSkiaCircleComponentDef>();
     registry.register::<DrawSkiaBoxComponentDef>();
+    registry.register::<DrawSkiaSpriteComponentDef>();
     registry.register::<Position2DComponent>();
     registry.register::<UniformScale2DComponent>();
     registry.register::<NonUniformScale2DComponent>();
@@ -125,6 +157,10 @@ pub fn create_editor_inspector_registry() -> EditorInspectRegistry {
 pub struct DemoApp {
     update_schedules: HashMap<ScheduleCriteria, Schedule>,
     draw_schedules: HashMap<ScheduleCriteria, Schedule>,
+    asset_source: Option<AssetSource>,
+    daemon_address: SocketAddr,
+    startup_prefab: AssetUuid,
+    spawn_cooked_prefabs: bool,
 }
 
 impl DemoApp {
@@ -150,9 +186,57 @@ impl DemoApp {
         DemoApp {
             update_schedules,
             draw_schedules,
+            asset_source: None,
+            daemon_address: AppConfig::default().address,
+            startup_prefab: AppConfig::default().startup_prefab,
+            spawn_cooked_prefabs: false,
         }
     }
 
+    /// The prefab to open when the app starts
+    pub fn with_startup_prefab(
+        mut self,
+        startup_prefab: AssetUuid,
+    ) -> Self {
+        self.startup_prefab = startup_prefab;
+        self
+    }
+
+    /// Spawn the startup prefab without opening it in the editor. With a pack file, the prefab
+    /// that was cooked when the pack was built is spawned (see CookedPrefabResource)
+    pub fn with_cooked_prefabs(mut self) -> Self {
+        self.spawn_cooked_prefabs = true;
+        self
+    }
+
+    /// Load assets from the given pack file instead of the asset daemon
+    pub fn with_asset_pack(
+        self,
+        asset_pack: PackLoader,
+    ) -> Self {
+        self.with_asset_source(AssetSource::Pack(asset_pack))
+    }
+
+    /// Load assets from the given source instead of connecting to the daemon at its default
+    /// address
+    pub fn with_asset_source(
+        mut self,
+        asset_source: AssetSource,
+    ) -> Self {
+        self.asset_source = Some(asset_source);
+        self
+    }
+
+    /// The daemon that imports the asset source files. The editor asks it where to save the opened
+    /// prefab. It isn't used when assets come from a pack file
+    pub fn with_daemon_address(
+        mut self,
+        daemon_address: SocketAddr,
+    ) -> Self {
+        self.daemon_address = daemon_address;
+        self
+    }
+
     // Determine the current state of the game
     fn get_current_schedule_criteria(resources: &Resources) -> ScheduleCriteria {
         ScheduleCriteria::new(
@@ -174,7 +258,10 @@ impl app::AppHandler for DemoApp {
         world: &mut World,
         resources: &mut Resources,
     ) {
-        let asset_manager = create_asset_manager();
+        let asset_manager = match self.asset_source.take() {
+            Some(asset_source) => create_asset_manager_with_source(asset_source),
+            None => create_asset_manager(),
+        };
         let physics = PhysicsResource::new(glam::Vec2::unit_y() * GRAVITY);
 
         let window_size = resources.get::<InputResource>().unwrap().window_size();
@@ -185,10 +272,17 @@ impl app::AppHandler for DemoApp {
         );
         let viewport = ViewportResource::new(window_size, camera.position, camera.x_half_extents);
 
+        let daemon_address = if asset_manager.is_pack() {
+            None
+        } else {
+            Some(self.daemon_address)
+        };
+
         resources.insert(physics);
         resources.insert(FpsTextResource::new());
         resources.insert(asset_manager);
-        resources.insert(EditorStateResource::new());
+        resources.insert(CookedPrefabResource::default());
+        resources.insert(EditorStateResource::new(daemon_address));
         resources.insert(camera);
         resources.insert(viewport);
         resources.insert(DebugDrawResource::new());
@@ -200,11 +294,15 @@ impl app::AppHandler for DemoApp {
         resources.insert(selection_resource);
 
         // Start the application
-        EditorStateResource::open_prefab(
-            world,
-            resources,
-            asset_uuid!("3991506e-ed7e-4bcb-8cfd-3366b31a6439"),
-        );
+        if self.spawn_cooked_prefabs {
+            let asset_resource = resources.get::<AssetResource>().unwrap();
+            resources
+                .get_mut::<CookedPrefabResource>()
+                .unwrap()
+                .spawn_prefab(&asset_resource, self.startup_prefab);
+        } else {
+            EditorStateResource::open_prefab(resources, self.startup_prefab);
+        }
     }
 
     fn update(
@@ -1,11 +1,16 @@
 use skulpin::app::LogicalSize;
 
 use std::ffi::CString;
+use std::time::Duration;
 
 use atelier_legion_demo::DemoApp;
+use atelier_legion_demo::app_config::AppConfig;
 use atelier_legion_demo::daemon;
 //use atelier_legion_demo::game;
 
+// Long enough for the daemon to import all of the demo assets on a clean start
+const DAEMON_STARTUP_TIMEOUT: Duration = Duration::from_secs(60);
+
 fn main() {
     // Setup logging
     env_logger::Builder::from_default_env()
@@ -13,21 +18,55 @@ fn main() {
         .filter_module("tokio_reactor", log::LevelFilter::Info)
         .init();
 
-    // Spawn the daemon in a background thread. This could be a different process, but
-    // for simplicity we'll launch it here.
-    std::thread::spawn(move || {
-        daemon::run();
+    let config = AppConfig::from_args().unwrap_or_else(|e| {
+        log::error!("{}", e);
+        std::process::exit(1);
     });
 
-    {
-        let mut asset_manager = atelier_legion_demo::create_asset_manager();
-        asset_manager.add_storage::<atelier_legion_demo::temp_test::Position2DComponentDef>();
-        atelier_legion_demo::temp_test::temp_force_load_asset(&mut asset_manager);
-        atelier_legion_demo::temp_test::temp_force_prefab_cook(&mut asset_manager);
+    let create_asset_source = || {
+        config.create_asset_source().unwrap_or_else(|e| {
+            log::error!("{}", e);
+            std::process::exit(1);
+        })
+    };
+
+    // Without a pack file, assets come from the daemon
+    if config.pack.is_none() {
+        // Spawn the daemon in a background thread. This could be a different process, but
+        // for simplicity we'll launch it here. Wait for it so that a daemon that fails to start
+        // is reported instead of hanging on the first load
+        let mut daemon = daemon::spawn_with(config.daemon_opt());
+        match daemon.wait_until_ready(DAEMON_STARTUP_TIMEOUT) {
+            Ok(_) => {}
+            Err(e @ daemon::DaemonWaitError::Timeout(_)) => {
+                log::warn!("{}, assets will load as they are imported", e)
+            }
+            Err(e @ daemon::DaemonWaitError::Stopped) => {
+                log::error!("{}", e);
+                std::process::exit(1);
+            }
+        }
+
+        {
+            let mut asset_manager =
+                atelier_legion_demo::create_asset_manager_with_source(create_asset_source());
+            asset_manager.add_storage::<atelier_legion_demo::temp_test::Position2DComponentDef>();
+            atelier_legion_demo::temp_test::temp_force_load_asset(&mut asset_manager);
+            atelier_legion_demo::temp_test::temp_force_prefab_cook(&mut asset_manager);
+        }
     }
 
     // Build the app and run it
-    let example_app = DemoApp::new();
+    let mut example_app = DemoApp::new()
+        .with_asset_source(create_asset_source())
+        .with_daemon_address(config.address)
+        .with_startup_prefab(config.startup_prefab);
+
+    // Pack files are how the demo would ship, so spawn the prefab cooked by the asset pipeline
+    // instead of opening it in the editor
+    if config.pack.is_some() {
+        example_app = example_app.with_cooked_prefabs();
+    }
     let renderer_builder = skulpin::RendererBuilder::new()
         .app_name(CString::new("Skulpin Example App").unwrap())
         .use_vulkan_debug_layer(true);
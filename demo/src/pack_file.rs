@@ -0,0 +1,549 @@
+//! A pack file holds every asset imported from a set of asset directories, so that they can be
+//! loaded by PackLoader without a running asset daemon. Build one with build_pack (or the
+//! build_pack binary).
+//!
+//! Along with the imported assets, the pack holds a CookedPrefabAsset for every prefab, cooked from
+//! the imported prefabs when the pack is built.
+//!
+//! Layout: PACK_FILE_MAGIC, then the length of the index as a little-endian u64, then the index (a
+//! bincode-encoded Vec<PackEntry>), then the asset data. Each asset's data is encoded with bincode,
+//! the same way the daemon sends it to RpcLoader.
+
+use std::collections::HashMap;
+use std::convert::TryInto;
+use std::path::{Path, PathBuf};
+
+use atelier_core::{AssetRef, AssetUuid};
+use legion::prelude::*;
+use serde::{Deserialize, Serialize};
+use type_uuid::{TypeUuid, TypeUuidDynamic};
+
+use crate::pipeline::{CookedPrefabAsset, PrefabAsset};
+use crate::prefab_cooking::{cook_prefab_with_cache, PrefabCookCache};
+
+pub const PACK_FILE_MAGIC: &[u8] = b"ATELIER-LEGION-PACK\n";
+
+/// Describes one asset in a pack file
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PackEntry {
+    pub id: AssetUuid,
+
+    /// The asset's TypeUuid
+    pub asset_type: [u8; 16],
+
+    /// Assets that must be loaded before this one
+    pub load_deps: Vec<AssetUuid>,
+    pub search_tags: Vec<(String, Option<String>)>,
+
+    /// The source file the asset was imported from, relative to its asset directory
+    pub source: PathBuf,
+
+    // Location of the asset's data, relative to the end of the index
+    offset: u64,
+    length: u64,
+}
+
+/// A pack file that has been read into memory
+pub struct PackFile {
+    entries: HashMap<AssetUuid, PackEntry>,
+    data: Vec<u8>,
+}
+
+impl PackFile {
+    pub fn open(path: &Path) -> Result<Self, String> {
+        let bytes =
+            std::fs::read(path).map_err(|e| format!("failed to read {}: {}", path.display(), e))?;
+        Self::from_bytes(bytes).map_err(|e| format!("failed to read {}: {}", path.display(), e))
+    }
+
+    pub fn from_bytes(mut bytes: Vec<u8>) -> Result<Self, String> {
+        let header_length = PACK_FILE_MAGIC.len() + 8;
+        if bytes.len() < header_length || !bytes.starts_with(PACK_FILE_MAGIC) {
+            return Err("not a pack file".to_string());
+        }
+
+        let index_length = u64::from_le_bytes(
+            bytes[PACK_FILE_MAGIC.len()..header_length]
+                .try_into()
+                .unwrap(),
+        ) as usize;
+
+        // The lengths come from the file, so they're checked for overflow as well as against the
+        // size of the file
+        let index_end = header_length
+            .checked_add(index_length)
+            .filter(|index_end| *index_end <= bytes.len())
+            .ok_or_else(|| "the pack file is truncated".to_string())?;
+
+        let index: Vec<PackEntry> = bincode::deserialize(&bytes[header_length..index_end])
+            .map_err(|e| format!("the pack file index is corrupt: {}", e))?;
+
+        let data = bytes.split_off(index_end);
+        for entry in &index {
+            let entry_end = entry.offset.checked_add(entry.length);
+            if entry_end.map_or(true, |entry_end| entry_end > data.len() as u64) {
+                return Err(format!(
+                    "the data for asset {} is outside the pack file",
+                    uuid::Uuid::from_bytes(entry.id.0)
+                ));
+            }
+        }
+
+        Ok(PackFile {
+            entries: index.into_iter().map(|entry| (entry.id, entry)).collect(),
+            data,
+        })
+    }
+
+    pub fn entries(&self) -> impl Iterator<Item = &PackEntry> {
+        self.entries.values()
+    }
+
+    pub fn entry(
+        &self,
+        id: &AssetUuid,
+    ) -> Option<&PackEntry> {
+        self.entries.get(id)
+    }
+
+    /// Returns the bincode-encoded asset
+    pub fn data(
+        &self,
+        entry: &PackEntry,
+    ) -> &[u8] {
+        &self.data[entry.offset as usize..(entry.offset + entry.length) as usize]
+    }
+
+    /// Encodes the pack in the layout described at the top of this module
+    pub fn to_bytes(&self) -> Result<Vec<u8>, String> {
+        let mut index: Vec<_> = self.entries.values().collect();
+        index.sort_by_key(|entry| entry.offset);
+
+        let index_bytes =
+            bincode::serialize(&index).map_err(|e| format!("failed to write pack index: {}", e))?;
+
+        let mut bytes =
+            Vec::with_capacity(PACK_FILE_MAGIC.len() + 8 + index_bytes.len() + self.data.len());
+        bytes.extend_from_slice(PACK_FILE_MAGIC);
+        bytes.extend_from_slice(&(index_bytes.len() as u64).to_le_bytes());
+        bytes.extend_from_slice(&index_bytes);
+        bytes.extend_from_slice(&self.data);
+        Ok(bytes)
+    }
+}
+
+/// An asset produced by importing a source file, encoded the way the daemon would serve it
+pub struct ImportedArtifact {
+    pub id: AssetUuid,
+    pub asset_type: [u8; 16],
+    pub load_deps: Vec<AssetUuid>,
+    pub search_tags: Vec<(String, Option<String>)>,
+    pub data: Vec<u8>,
+}
+
+/// Imports a source file with the importer registered for its extension. The importer options and
+/// state are read from the file's .meta file, so asset IDs match the ones the daemon assigned.
+/// Returns Ok(None) if no importer handles the file
+pub fn import_source_file(path: &Path) -> Result<Option<Vec<ImportedArtifact>>, String> {
+    let importer = match find_importer(path) {
+        Some(importer) => importer,
+        None => return Ok(None),
+    };
+
+    let meta_path = meta_path(path);
+    let (options, state) = if meta_path.exists() {
+        let meta = std::fs::read(&meta_path)
+            .map_err(|e| format!("failed to read {}: {}", meta_path.display(), e))?;
+        let metadata = importer
+            .deserialize_metadata(&meta)
+            .map_err(|e| format!("failed to read {}: {}", meta_path.display(), e))?;
+        (metadata.importer_options, metadata.importer_state)
+    } else {
+        log::warn!(
+            "{} has no .meta file, so its assets get new IDs. Run the asset daemon to create one",
+            path.display()
+        );
+        (importer.default_options(), importer.default_state())
+    };
+
+    let mut source =
+        std::fs::File::open(path).map_err(|e| format!("failed to open {}: {}", path.display(), e))?;
+    let imported = importer
+        .import_boxed(&mut source, options, state)
+        .map_err(|e| format!("failed to import {}: {}", path.display(), e))?;
+
+    let mut artifacts = Vec::with_capacity(imported.value.assets.len());
+    for asset in imported.value.assets {
+        let data = bincode::serialize(&asset.asset_data).map_err(|e| {
+            format!(
+                "failed to serialize asset {} from {}: {}",
+                uuid::Uuid::from_bytes(asset.id.0),
+                path.display(),
+                e
+            )
+        })?;
+
+        let load_deps = asset
+            .load_deps
+            .iter()
+            .filter_map(|dep| match dep {
+                AssetRef::Uuid(id) => Some(*id),
+                AssetRef::Path(dep_path) => {
+                    log::warn!(
+                        "{} has a load dependency on {} by path, which is not packed",
+                        path.display(),
+                        dep_path.display()
+                    );
+                    None
+                }
+            })
+            .collect();
+
+        artifacts.push(ImportedArtifact {
+            id: asset.id,
+            asset_type: asset.asset_data.uuid(),
+            load_deps,
+            search_tags: asset.search_tags,
+            data,
+        });
+    }
+
+    Ok(Some(artifacts))
+}
+
+/// An asset as the daemon recorded it in the .meta file next to its source file
+pub struct AssetMetadataEntry {
+    pub id: AssetUuid,
+
+    /// The asset's TypeUuid, or None if the daemon hasn't produced the asset yet
+    pub asset_type: Option<[u8; 16]>,
+
+    /// The source file the asset was imported from, relative to its asset directory
+    pub source: PathBuf,
+}
+
+/// Reads the assets that the daemon has recorded for the source files in the given asset
+/// directories. Nothing is imported. Source files that have an importer but no up to date .meta
+/// file haven't been processed by the daemon yet, and are returned separately
+pub fn read_asset_metadata(
+    asset_dirs: &[PathBuf],
+) -> Result<(Vec<AssetMetadataEntry>, Vec<PathBuf>), String> {
+    let mut entries = Vec::new();
+    let mut unprocessed = Vec::new();
+    for asset_dir in asset_dirs {
+        for path in find_source_files(asset_dir)? {
+            let importer = match find_importer(&path) {
+                Some(importer) => importer,
+                None => continue,
+            };
+
+            let meta_path = meta_path(&path);
+            if !meta_path.exists() {
+                unprocessed.push(path);
+                continue;
+            }
+
+            let meta = std::fs::read(&meta_path)
+                .map_err(|e| format!("failed to read {}: {}", meta_path.display(), e))?;
+            let metadata = importer
+                .deserialize_metadata(&meta)
+                .map_err(|e| format!("failed to read {}: {}", meta_path.display(), e))?;
+
+            // The daemon rewrites the .meta file when it imports a changed file or a file whose
+            // importer was updated, so until then the recorded assets may be out of date
+            if metadata.importer_version != importer.version()
+                || is_older_than(&meta_path, &path)
+            {
+                unprocessed.push(path.clone());
+            }
+
+            let source = path.strip_prefix(asset_dir).unwrap_or(&path).to_path_buf();
+            for asset in metadata.assets {
+                entries.push(AssetMetadataEntry {
+                    id: asset.id,
+                    asset_type: asset.artifact.map(|artifact| artifact.type_id.0),
+                    source: source.clone(),
+                });
+            }
+        }
+    }
+
+    Ok((entries, unprocessed))
+}
+
+/// Returns the importer that the daemon would use for the given source file
+pub(crate) fn find_importer(path: &Path) -> Option<Box<dyn atelier_importer::BoxedImporter>> {
+    let extension = crate::daemon::importer_extension(path)?;
+    atelier_importer::get_source_importers()
+        .find(|(registered_extension, _)| {
+            registered_extension.trim_start_matches('.').to_lowercase() == extension
+        })
+        .map(|(_, importer)| importer)
+}
+
+// Returns true if the first file was last modified before the second one. Files whose times can't
+// be read aren't considered older
+fn is_older_than(
+    path: &Path,
+    other_path: &Path,
+) -> bool {
+    let modified = |path: &Path| std::fs::metadata(path).and_then(|metadata| metadata.modified());
+    match (modified(path), modified(other_path)) {
+        (Ok(modified), Ok(other_modified)) => modified < other_modified,
+        _ => false,
+    }
+}
+
+// The daemon stores each source file's importer settings and asset IDs next to it, in a file with
+// .meta appended to its name
+fn meta_path(path: &Path) -> PathBuf {
+    let mut meta_path = path.as_os_str().to_owned();
+    meta_path.push(".meta");
+    PathBuf::from(meta_path)
+}
+
+/// Returns every file under the given directory, except .meta files
+pub fn find_source_files(dir: &Path) -> Result<Vec<PathBuf>, String> {
+    let mut source_files = Vec::new();
+    let read_dir =
+        std::fs::read_dir(dir).map_err(|e| format!("failed to read {}: {}", dir.display(), e))?;
+
+    for dir_entry in read_dir {
+        let path = dir_entry
+            .map_err(|e| format!("failed to read {}: {}", dir.display(), e))?
+            .path();
+
+        if path.is_dir() {
+            source_files.extend(find_source_files(&path)?);
+        } else if path.extension().map(|x| x != "meta").unwrap_or(true) {
+            source_files.push(path);
+        }
+    }
+
+    source_files.sort();
+    Ok(source_files)
+}
+
+/// Imports every source file in the given asset directories into an in-memory pack, which can be
+/// served by PackLoader without writing it to disk
+pub fn import_assets(asset_dirs: &[PathBuf]) -> Result<PackFile, String> {
+    crate::pipeline::set_asset_dirs(asset_dirs.to_vec());
+
+    let mut entries = HashMap::new();
+    let mut data = Vec::new();
+    for asset_dir in asset_dirs {
+        for path in find_source_files(asset_dir)? {
+            let artifacts = match import_source_file(&path)? {
+                Some(artifacts) => artifacts,
+                None => {
+                    log::info!("Skipping {}, no importer is registered for it", path.display());
+                    continue;
+                }
+            };
+
+            // e.g. a .json file that isn't a prefab
+            if artifacts.is_empty() {
+                log::info!("Skipping {}, it contains no assets", path.display());
+                continue;
+            }
+
+            let source = path.strip_prefix(asset_dir).unwrap_or(&path).to_path_buf();
+            for artifact in artifacts {
+                let entry = PackEntry {
+                    id: artifact.id,
+                    asset_type: artifact.asset_type,
+                    load_deps: artifact.load_deps,
+                    search_tags: artifact.search_tags,
+                    source: source.clone(),
+                    offset: data.len() as u64,
+                    length: artifact.data.len() as u64,
+                };
+                data.extend_from_slice(&artifact.data);
+
+                if let Some(existing) = entries.insert(entry.id, entry) {
+                    return Err(format!(
+                        "asset {} is imported from both {} and {}",
+                        uuid::Uuid::from_bytes(existing.id.0),
+                        existing.source.display(),
+                        source.display()
+                    ));
+                }
+            }
+        }
+    }
+
+    cook_prefabs(&mut entries, &mut data)?;
+
+    Ok(PackFile { entries, data })
+}
+
+/// Cooks every prefab in the pack into a CookedPrefabAsset, so that prefabs can be spawned from a
+/// pack without cooking them at runtime. This runs whenever a pack is built, after all the prefabs
+/// have been imported, so a cooked prefab is never out of date with the prefabs it references
+fn cook_prefabs(
+    entries: &mut HashMap<AssetUuid, PackEntry>,
+    data: &mut Vec<u8>,
+) -> Result<(), String> {
+    let mut prefabs = HashMap::new();
+    for entry in entries.values() {
+        if entry.asset_type != PrefabAsset::UUID {
+            continue;
+        }
+
+        let entry_data = &data[entry.offset as usize..(entry.offset + entry.length) as usize];
+        let prefab_asset: PrefabAsset = bincode::deserialize(entry_data).map_err(|e| {
+            format!(
+                "failed to read prefab {} from {}: {}",
+                uuid::Uuid::from_bytes(entry.id.0),
+                entry.source.display(),
+                e
+            )
+        })?;
+        prefabs.insert(entry.id, (prefab_asset, entry.source.clone()));
+    }
+
+    // Every prefab is cooked from the same data, so the versions don't matter. Sharing the cache
+    // means a prefab that is referenced by several others is only cooked once
+    let versioned_prefabs = prefabs
+        .iter()
+        .map(|(prefab_id, (prefab_asset, _))| (*prefab_id, (prefab_asset, 0)))
+        .collect();
+    let mut cache = PrefabCookCache::default();
+
+    let universe = Universe::new();
+    let registered_components = crate::create_component_registry();
+    let registered_components_by_uuid = crate::create_component_registry_by_uuid();
+
+    // Cook in source file order so that building the same assets produces the same pack
+    let mut prefab_ids: Vec<_> = prefabs.keys().cloned().collect();
+    prefab_ids.sort_by_key(|prefab_id| (&prefabs[prefab_id].1, prefab_id.0));
+
+    for prefab_id in prefab_ids {
+        let source = &prefabs[&prefab_id].1;
+        let cooked_prefab = cook_prefab_with_cache(
+            &universe,
+            &registered_components,
+            &registered_components_by_uuid,
+            prefab_id,
+            &versioned_prefabs,
+            &mut cache,
+            None,
+        )
+        .map_err(|e| format!("failed to cook prefab from {}: {}", source.display(), e))?;
+
+        let cooked_data = bincode::serialize(&CookedPrefabAsset { cooked_prefab }).map_err(|e| {
+            format!("failed to serialize cooked prefab from {}: {}", source.display(), e)
+        })?;
+
+        let entry = PackEntry {
+            id: CookedPrefabAsset::asset_uuid_for_prefab(prefab_id),
+            asset_type: CookedPrefabAsset::UUID,
+            load_deps: Vec::new(),
+            search_tags: Vec::new(),
+            source: source.clone(),
+            offset: data.len() as u64,
+            length: cooked_data.len() as u64,
+        };
+        data.extend_from_slice(&cooked_data);
+        entries.insert(entry.id, entry);
+    }
+
+    Ok(())
+}
+
+/// Imports every source file in the given asset directories and writes the results to a pack
+/// file. Returns the number of assets written
+pub fn build_pack(
+    asset_dirs: &[PathBuf],
+    output: &Path,
+) -> Result<usize, String> {
+    let pack = import_assets(asset_dirs)?;
+    std::fs::write(output, pack.to_bytes()?)
+        .map_err(|e| format!("failed to write {}: {}", output.display(), e))?;
+
+    Ok(pack.entries.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(
+        id: u8,
+        offset: u64,
+        length: u64,
+    ) -> PackEntry {
+        PackEntry {
+            id: AssetUuid([id; 16]),
+            asset_type: [0; 16],
+            load_deps: vec![],
+            search_tags: vec![],
+            source: PathBuf::from("test.ron"),
+            offset,
+            length,
+        }
+    }
+
+    // Encodes a pack with the given entries, which don't have to fit the data
+    fn pack_bytes(
+        entries: Vec<PackEntry>,
+        data: &[u8],
+    ) -> Vec<u8> {
+        let pack = PackFile {
+            entries: entries.into_iter().map(|entry| (entry.id, entry)).collect(),
+            data: data.to_vec(),
+        };
+        pack.to_bytes().unwrap()
+    }
+
+    fn read_error(bytes: Vec<u8>) -> String {
+        match PackFile::from_bytes(bytes) {
+            Ok(_) => panic!("read an invalid pack file"),
+            Err(e) => e,
+        }
+    }
+
+    #[test]
+    fn round_trips_a_pack() {
+        let bytes = pack_bytes(vec![entry(1, 0, 3), entry(2, 3, 2)], b"abcde");
+        let pack = PackFile::from_bytes(bytes).unwrap();
+        assert_eq!(pack.entries().count(), 2);
+        assert_eq!(pack.data(pack.entry(&AssetUuid([1; 16])).unwrap()), b"abc");
+        assert_eq!(pack.data(pack.entry(&AssetUuid([2; 16])).unwrap()), b"de");
+    }
+
+    #[test]
+    fn rejects_files_that_are_not_packs() {
+        assert_eq!(read_error(b"not a pack".to_vec()), "not a pack file");
+        assert_eq!(read_error(PACK_FILE_MAGIC.to_vec()), "not a pack file");
+    }
+
+    #[test]
+    fn rejects_a_truncated_index() {
+        let bytes = pack_bytes(vec![entry(1, 0, 3)], b"");
+        let index_end = bytes.len();
+        assert!(read_error(bytes[..index_end - 1].to_vec()).contains("truncated"));
+    }
+
+    #[test]
+    fn rejects_an_index_length_that_overflows() {
+        let mut bytes = PACK_FILE_MAGIC.to_vec();
+        bytes.extend_from_slice(&u64::MAX.to_le_bytes());
+        assert!(read_error(bytes).contains("truncated"));
+    }
+
+    #[test]
+    fn rejects_data_outside_the_pack() {
+        let bytes = pack_bytes(vec![entry(1, 0, 3), entry(2, 3, 3)], b"abcde");
+        assert!(read_error(bytes).contains("outside the pack file"));
+
+        let bytes = pack_bytes(vec![entry(1, 6, 0)], b"abcde");
+        assert!(read_error(bytes).contains("outside the pack file"));
+    }
+
+    #[test]
+    fn rejects_data_ranges_that_overflow() {
+        let bytes = pack_bytes(vec![entry(1, u64::MAX, 2)], b"abcde");
+        assert!(read_error(bytes).contains("outside the pack file"));
+    }
+}
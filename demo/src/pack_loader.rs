@@ -0,0 +1,286 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::path::Path;
+use std::sync::Mutex;
+
+use atelier_core::AssetUuid;
+use atelier_loader::{AssetStorage, AssetTypeId, LoadHandle, LoadStatus, LoaderInfoProvider};
+
+use crate::asset_storage::GenericAssetStorage;
+use crate::pack_file::PackFile;
+
+// Assets in a pack file never change, so every load is the first version
+const PACK_ASSET_VERSION: u32 = 1;
+
+#[derive(Copy, Clone, PartialEq)]
+enum PackLoadStatus {
+    NotLoaded,
+    Loaded,
+
+    // The asset isn't in the pack, or it failed to deserialize
+    Failed,
+}
+
+struct PackLoad {
+    id: AssetUuid,
+    refs: u32,
+    status: PackLoadStatus,
+}
+
+#[derive(Default)]
+struct PackLoaderState {
+    next_load_handle: u64,
+    load_handles: HashMap<AssetUuid, LoadHandle>,
+    loads: HashMap<LoadHandle, PackLoad>,
+}
+
+/// Serves assets from a pack file (see pack_file) instead of the asset daemon. Assets are loaded
+/// when they are first referenced and freed when the last reference is dropped, like RpcLoader
+pub struct PackLoader {
+    pack: PackFile,
+    state: Mutex<PackLoaderState>,
+}
+
+impl PackLoader {
+    pub fn open(path: &Path) -> Result<Self, String> {
+        Ok(Self::new(PackFile::open(path)?))
+    }
+
+    pub fn new(pack: PackFile) -> Self {
+        PackLoader {
+            pack,
+            state: Mutex::new(PackLoaderState::default()),
+        }
+    }
+
+    pub fn pack(&self) -> &PackFile {
+        &self.pack
+    }
+
+    pub fn add_ref(
+        &self,
+        id: AssetUuid,
+    ) -> LoadHandle {
+        let mut state = self.state.lock().unwrap();
+        self.add_ref_locked(&mut state, id)
+    }
+
+    // Adding the first reference to an asset also references its dependencies, so that they are
+    // loaded first and stay loaded as long as it does
+    fn add_ref_locked(
+        &self,
+        state: &mut PackLoaderState,
+        id: AssetUuid,
+    ) -> LoadHandle {
+        let load_handle = match state.load_handles.get(&id) {
+            Some(load_handle) => *load_handle,
+            None => {
+                state.next_load_handle += 1;
+                let load_handle = LoadHandle(state.next_load_handle);
+                state.load_handles.insert(id, load_handle);
+                state.loads.insert(
+                    load_handle,
+                    PackLoad {
+                        id,
+                        refs: 0,
+                        status: PackLoadStatus::NotLoaded,
+                    },
+                );
+                load_handle
+            }
+        };
+
+        let load = state.loads.get_mut(&load_handle).unwrap();
+        load.refs += 1;
+        if load.refs == 1 {
+            if let Some(entry) = self.pack.entry(&id) {
+                for dep in &entry.load_deps {
+                    self.add_ref_locked(state, *dep);
+                }
+            }
+        }
+
+        load_handle
+    }
+
+    pub fn remove_ref(
+        &self,
+        load_handle: LoadHandle,
+    ) {
+        let mut state = self.state.lock().unwrap();
+        self.remove_ref_locked(&mut state, load_handle);
+    }
+
+    fn remove_ref_locked(
+        &self,
+        state: &mut PackLoaderState,
+        load_handle: LoadHandle,
+    ) {
+        let id = match state.loads.get_mut(&load_handle) {
+            Some(load) if load.refs > 0 => {
+                load.refs -= 1;
+                if load.refs > 0 {
+                    return;
+                }
+                load.id
+            }
+            _ => {
+                log::warn!("Removed a reference to {:?}, which has none", load_handle);
+                return;
+            }
+        };
+
+        if let Some(entry) = self.pack.entry(&id) {
+            for dep in &entry.load_deps {
+                if let Some(dep_load_handle) = state.load_handles.get(dep).cloned() {
+                    self.remove_ref_locked(state, dep_load_handle);
+                }
+            }
+        }
+    }
+
+    pub fn get_load(
+        &self,
+        id: AssetUuid,
+    ) -> Option<LoadHandle> {
+        self.state.lock().unwrap().load_handles.get(&id).cloned()
+    }
+
+    pub fn get_load_refs(
+        &self,
+        load_handle: LoadHandle,
+    ) -> Option<(AssetUuid, u32)> {
+        self.state
+            .lock()
+            .unwrap()
+            .loads
+            .get(&load_handle)
+            .map(|load| (load.id, load.refs))
+    }
+
+    pub fn get_asset_type(
+        &self,
+        load_handle: LoadHandle,
+    ) -> Option<AssetTypeId> {
+        let id = self.state.lock().unwrap().loads.get(&load_handle)?.id;
+        self.pack
+            .entry(&id)
+            .map(|entry| AssetTypeId(entry.asset_type))
+    }
+
+    pub fn get_load_status(
+        &self,
+        load_handle: LoadHandle,
+    ) -> LoadStatus {
+        let state = self.state.lock().unwrap();
+        match state.loads.get(&load_handle) {
+            None => LoadStatus::NotRequested,
+            Some(load) => match (load.refs, load.status) {
+                (_, PackLoadStatus::Failed) => LoadStatus::DoesNotExist,
+                (0, _) => LoadStatus::NotRequested,
+                (_, PackLoadStatus::Loaded) => LoadStatus::Loaded,
+                (_, PackLoadStatus::NotLoaded) => LoadStatus::Loading,
+            },
+        }
+    }
+
+    /// Loads assets that were referenced since the last call, once their dependencies are loaded,
+    /// and frees assets that are no longer referenced
+    pub fn process(
+        &mut self,
+        storage: &GenericAssetStorage,
+    ) -> Result<(), Box<dyn Error>> {
+        loop {
+            // Find the loads that are ready. The state can't stay locked while loading, because
+            // deserializing handles looks up load handles
+            let ready: Vec<_> = {
+                let state = self.state.lock().unwrap();
+                state
+                    .loads
+                    .iter()
+                    .filter(|(_, load)| load.refs > 0 && load.status == PackLoadStatus::NotLoaded)
+                    .filter(|(_, load)| {
+                        self.pack
+                            .entry(&load.id)
+                            .map(|entry| {
+                                entry.load_deps.iter().all(|dep| {
+                                    state
+                                        .load_handles
+                                        .get(dep)
+                                        .and_then(|dep_load_handle| {
+                                            state.loads.get(dep_load_handle)
+                                        })
+                                        .map(|dep| dep.status != PackLoadStatus::NotLoaded)
+                                        .unwrap_or(true)
+                                })
+                            })
+                            .unwrap_or(true)
+                    })
+                    .map(|(load_handle, load)| (*load_handle, load.id))
+                    .collect()
+            };
+
+            if ready.is_empty() {
+                break;
+            }
+
+            for (load_handle, id) in ready {
+                let status = match self.pack.entry(&id) {
+                    Some(entry) => {
+                        let result = storage.load_asset(
+                            self,
+                            &AssetTypeId(entry.asset_type),
+                            self.pack.data(entry),
+                            load_handle,
+                            PACK_ASSET_VERSION,
+                        );
+
+                        match result {
+                            Ok(()) => PackLoadStatus::Loaded,
+                            Err(e) => {
+                                log::error!("Failed to load asset {:?}: {}", id, e);
+                                PackLoadStatus::Failed
+                            }
+                        }
+                    }
+                    None => {
+                        log::error!("Asset {:?} is not in the pack file", id);
+                        PackLoadStatus::Failed
+                    }
+                };
+
+                let mut state = self.state.lock().unwrap();
+                state.loads.get_mut(&load_handle).unwrap().status = status;
+            }
+        }
+
+        // Free assets that are no longer referenced
+        let mut state = self.state.lock().unwrap();
+        for (load_handle, load) in &mut state.loads {
+            if load.refs == 0 && load.status == PackLoadStatus::Loaded {
+                if let Some(entry) = self.pack.entry(&load.id) {
+                    storage.free(&AssetTypeId(entry.asset_type), *load_handle);
+                }
+                load.status = PackLoadStatus::NotLoaded;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl LoaderInfoProvider for PackLoader {
+    fn get_load_handle(
+        &self,
+        id: &AssetUuid,
+    ) -> Option<LoadHandle> {
+        self.get_load(*id)
+    }
+
+    fn get_asset_id(
+        &self,
+        load: LoadHandle,
+    ) -> Option<AssetUuid> {
+        self.get_load_refs(load).map(|(id, _)| id)
+    }
+}
@@ -1,10 +1,89 @@
-use image2::{color, ImageBuf};
+use image2::{color, Image, ImageBuf};
 use serde::{Deserialize, Serialize};
 use type_uuid::*;
 
+/// How the color values of an image should be interpreted
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorSpace {
+    /// Color values are sRGB encoded. This is typical for textures authored in an image editor
+    Srgb,
+
+    /// Color values are linear. This is typical for data textures like normal maps
+    Linear,
+}
+
+impl Default for ColorSpace {
+    fn default() -> Self {
+        ColorSpace::Srgb
+    }
+}
+
+/// Describes how the pixels of an ImageAsset should be interpreted
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ImageColorInfo {
+    pub color_space: ColorSpace,
+
+    /// If true, the color channels have already been multiplied by alpha
+    pub premultiplied_alpha: bool,
+}
+
+/// The pixel format is chosen from the decoded pixels, not from the color type stored in the source
+/// file: images with any pixel that isn't fully opaque are Rgba8, opaque images whose pixels are
+/// all gray are Luma8 and everything else is Rgb8. So an RGBA PNG with no transparency imports as
+/// Rgb8, and editing an image can change its format. Code that reads an ImageAsset has to handle
+/// every format (see to_rgba8)
 #[derive(TypeUuid, Serialize, Deserialize, Debug)]
 #[uuid = "d4079e74-3ec9-4ebc-9b77-a87cafdfdada"]
 pub enum ImageAsset {
-    Rgb8(ImageBuf<u8, color::Rgb>),
-    // ...
+    Rgb8(ImageBuf<u8, color::Rgb>, ImageColorInfo),
+    Rgba8(ImageBuf<u8, color::Rgba>, ImageColorInfo),
+    Luma8(ImageBuf<u8, color::Gray>, ImageColorInfo),
+}
+
+impl ImageAsset {
+    pub fn width(&self) -> usize {
+        match self {
+            ImageAsset::Rgb8(image, _) => image.width(),
+            ImageAsset::Rgba8(image, _) => image.width(),
+            ImageAsset::Luma8(image, _) => image.width(),
+        }
+    }
+
+    pub fn height(&self) -> usize {
+        match self {
+            ImageAsset::Rgb8(image, _) => image.height(),
+            ImageAsset::Rgba8(image, _) => image.height(),
+            ImageAsset::Luma8(image, _) => image.height(),
+        }
+    }
+
+    pub fn color_info(&self) -> &ImageColorInfo {
+        match self {
+            ImageAsset::Rgb8(_, color_info) => color_info,
+            ImageAsset::Rgba8(_, color_info) => color_info,
+            ImageAsset::Luma8(_, color_info) => color_info,
+        }
+    }
+
+    /// Returns the pixels as tightly packed RGBA8, expanding the other formats as needed
+    pub fn to_rgba8(&self) -> Vec<u8> {
+        match self {
+            ImageAsset::Rgb8(image, _) => {
+                let mut rgba = Vec::with_capacity(image.data().len() / 3 * 4);
+                for rgb in image.data().chunks_exact(3) {
+                    rgba.extend_from_slice(rgb);
+                    rgba.push(255);
+                }
+                rgba
+            }
+            ImageAsset::Rgba8(image, _) => image.data().to_vec(),
+            ImageAsset::Luma8(image, _) => {
+                let mut rgba = Vec::with_capacity(image.data().len() * 4);
+                for luma in image.data() {
+                    rgba.extend_from_slice(&[*luma, *luma, *luma, 255]);
+                }
+                rgba
+            }
+        }
+    }
 }
@@ -0,0 +1,462 @@
+use std::collections::HashMap;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use atelier_core::{AssetRef, AssetUuid};
+use atelier_importer::{Error, ImportedAsset, Importer, ImporterValue, Result};
+use serde::{Deserialize, Serialize};
+use type_uuid::*;
+
+use super::importers::{create_image_asset, decode_rgba};
+use crate::pipeline::image::*;
+
+/// A named rectangle within a packed atlas image. Sprites reference these by UUID to draw part of
+/// the atlas
+#[derive(TypeUuid, Serialize, Deserialize, Debug, Clone)]
+#[uuid = "5a3f9d2b-6e41-4c8a-b7d0-91e2c4f86a17"]
+pub struct AtlasRegionAsset {
+    /// The name given to the region in the atlas file
+    pub name: String,
+
+    /// The packed ImageAsset that contains this region
+    pub image: AssetUuid,
+
+    /// The region's position and size in pixels. (0, 0) is the top-left of the image
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// An image that becomes a single region, named after the entry
+#[derive(Deserialize)]
+struct AtlasImageSource {
+    name: String,
+
+    /// Path relative to an asset directory
+    path: PathBuf,
+}
+
+/// An image split into equally sized cells. Each cell becomes a region named {name}_{index},
+/// numbered left to right and then top to bottom
+#[derive(Deserialize)]
+struct AtlasGridSource {
+    name: String,
+
+    /// Path relative to an asset directory
+    path: PathBuf,
+    columns: u32,
+    rows: u32,
+}
+
+/// The contents of an .atlas file, which is RON
+///
+/// ```ron
+/// (
+///     padding: 1,
+///     images: [
+///         (name: "amethyst", path: "amethyst.png"),
+///     ],
+///     grids: [
+///         (name: "explosion", path: "sprites/explosion.png", columns: 4, rows: 2),
+///     ],
+/// )
+/// ```
+#[derive(Deserialize)]
+struct AtlasDescription {
+    /// Transparent pixels left between regions so that filtering doesn't blend neighbors together
+    #[serde(default)]
+    padding: u32,
+    #[serde(default)]
+    images: Vec<AtlasImageSource>,
+    #[serde(default)]
+    grids: Vec<AtlasGridSource>,
+}
+
+#[derive(TypeUuid, Serialize, Deserialize, Default, Clone, Copy)]
+#[uuid = "c2d7f0e8-1b3a-4f6d-8e59-6a0b4d3c27f1"]
+#[serde(default)]
+pub struct AtlasImporterOptions {
+    /// How the color values in the source images should be interpreted
+    pub color_space: ColorSpace,
+
+    /// If true, the color channels of the packed image are multiplied by alpha
+    pub premultiply_alpha: bool,
+}
+
+/// Keeps the packed image's UUID and each region's UUID the same between imports, so that
+/// references to them survive changes to the atlas
+#[derive(TypeUuid, Serialize, Deserialize, Default)]
+#[uuid = "e83b51c6-0f92-4d7a-a3c4-5d18b6e9f024"]
+pub struct AtlasImporterState {
+    image_id: Option<AssetUuid>,
+    region_ids: HashMap<String, AssetUuid>,
+}
+
+// A region's pixels before packing
+struct AtlasPiece {
+    name: String,
+    width: usize,
+    height: usize,
+    rgba: Vec<u8>,
+}
+
+#[derive(TypeUuid)]
+#[uuid = "9f0c6e3d-2a47-4b8e-b15d-73c8a4e0d9b2"]
+struct AtlasImporter;
+impl Importer for AtlasImporter {
+    fn version_static() -> u32
+    where
+        Self: Sized,
+    {
+        3
+    }
+    fn version(&self) -> u32 {
+        Self::version_static()
+    }
+
+    type Options = AtlasImporterOptions;
+
+    type State = AtlasImporterState;
+
+    /// Reads the atlas description, packs the images it lists and produces the packed image and a
+    /// region asset for each piece
+    fn import(
+        &self,
+        source: &mut dyn Read,
+        options: Self::Options,
+        state: &mut Self::State,
+    ) -> Result<ImporterValue> {
+        let mut description = String::new();
+        source.read_to_string(&mut description)?;
+        let description: AtlasDescription =
+            ron::de::from_str(&description).map_err(|e| Error::Boxed(Box::new(e)))?;
+
+        let mut pieces = Vec::new();
+        let mut source_paths = Vec::new();
+
+        for image in &description.images {
+            let (width, height, rgba, path) = read_source_image(&image.path)?;
+            source_paths.push(path);
+            pieces.push(AtlasPiece {
+                name: image.name.clone(),
+                width,
+                height,
+                rgba,
+            });
+        }
+
+        for grid in &description.grids {
+            let (width, height, rgba, path) = read_source_image(&grid.path)?;
+            source_paths.push(path);
+
+            if grid.columns == 0 || grid.rows == 0 {
+                return Err(atlas_error(format!(
+                    "grid {} must have at least one column and row",
+                    grid.name
+                )));
+            }
+
+            if width % grid.columns as usize != 0 || height % grid.rows as usize != 0 {
+                return Err(atlas_error(format!(
+                    "grid {} is {}x{} pixels, which can't be split into {} columns and {} rows \
+                     of equal size",
+                    grid.name, width, height, grid.columns, grid.rows
+                )));
+            }
+
+            let cell_width = width / grid.columns as usize;
+            let cell_height = height / grid.rows as usize;
+            for row in 0..grid.rows as usize {
+                for column in 0..grid.columns as usize {
+                    pieces.push(AtlasPiece {
+                        name: format!("{}_{}", grid.name, row * grid.columns as usize + column),
+                        width: cell_width,
+                        height: cell_height,
+                        rgba: copy_rect(
+                            &rgba,
+                            width,
+                            column * cell_width,
+                            row * cell_height,
+                            cell_width,
+                            cell_height,
+                        ),
+                    });
+                }
+            }
+        }
+
+        let (atlas_width, atlas_height, positions) =
+            pack_pieces(&pieces, description.padding as usize);
+
+        let mut atlas_rgba = vec![0; atlas_width * atlas_height * 4];
+        for (piece, (x, y)) in pieces.iter().zip(&positions) {
+            for row in 0..piece.height {
+                let source_begin = row * piece.width * 4;
+                let dest_begin = ((y + row) * atlas_width + x) * 4;
+                atlas_rgba[dest_begin..dest_begin + piece.width * 4]
+                    .copy_from_slice(&piece.rgba[source_begin..source_begin + piece.width * 4]);
+            }
+        }
+
+        let image_id = state
+            .image_id
+            .unwrap_or_else(|| AssetUuid(*uuid::Uuid::new_v4().as_bytes()));
+
+        // Regions that were removed from the atlas drop their IDs
+        let mut region_ids = HashMap::with_capacity(pieces.len());
+        for piece in &pieces {
+            if region_ids.contains_key(&piece.name) {
+                return Err(atlas_error(format!("region {} is defined twice", piece.name)));
+            }
+
+            let region_id = state
+                .region_ids
+                .get(&piece.name)
+                .cloned()
+                .unwrap_or_else(|| AssetUuid(*uuid::Uuid::new_v4().as_bytes()));
+            region_ids.insert(piece.name.clone(), region_id);
+        }
+
+        *state = AtlasImporterState {
+            image_id: Some(image_id),
+            region_ids,
+        };
+
+        let image_asset = create_image_asset(
+            atlas_width,
+            atlas_height,
+            atlas_rgba,
+            options.color_space,
+            options.premultiply_alpha,
+        );
+
+        let mut assets = vec![ImportedAsset {
+            id: image_id,
+            search_tags: vec![],
+            build_deps: source_paths.into_iter().map(AssetRef::Path).collect(),
+            load_deps: vec![],
+            build_pipeline: None,
+            asset_data: Box::new(image_asset),
+        }];
+
+        for (piece, (x, y)) in pieces.iter().zip(&positions) {
+            assets.push(ImportedAsset {
+                id: state.region_ids[&piece.name],
+                search_tags: vec![("atlas_region".to_string(), Some(piece.name.clone()))],
+                build_deps: vec![],
+                load_deps: vec![AssetRef::Uuid(image_id)],
+                build_pipeline: None,
+                asset_data: Box::new(AtlasRegionAsset {
+                    name: piece.name.clone(),
+                    image: image_id,
+                    x: *x as u32,
+                    y: *y as u32,
+                    width: piece.width as u32,
+                    height: piece.height as u32,
+                }),
+            });
+        }
+
+        Ok(ImporterValue { assets })
+    }
+}
+
+/// Returns the paths, relative to an asset directory, of the images that an .atlas file is packed
+/// from. The daemon only imports a source file again when that file changes, so it watches these
+/// to know when an atlas has to be packed again
+pub(crate) fn read_atlas_image_paths(
+    atlas_path: &Path,
+) -> std::result::Result<Vec<PathBuf>, String> {
+    let description = std::fs::read_to_string(atlas_path)
+        .map_err(|e| format!("failed to read {}: {}", atlas_path.display(), e))?;
+    let description: AtlasDescription = ron::de::from_str(&description)
+        .map_err(|e| format!("failed to read {}: {}", atlas_path.display(), e))?;
+
+    let images = description.images.into_iter().map(|image| image.path);
+    let grids = description.grids.into_iter().map(|grid| grid.path);
+    Ok(images.chain(grids).collect())
+}
+
+fn atlas_error(message: String) -> Error {
+    Error::Boxed(Box::new(std::io::Error::new(
+        std::io::ErrorKind::InvalidData,
+        message,
+    )))
+}
+
+// Finds and decodes a source image, returning its width, height, pixels and path
+fn read_source_image(relative_path: &Path) -> Result<(usize, usize, Vec<u8>, PathBuf)> {
+    let path = crate::pipeline::find_source_file(relative_path).ok_or_else(|| {
+        atlas_error(format!(
+            "atlas image {} was not found in any asset directory",
+            relative_path.display()
+        ))
+    })?;
+
+    let bytes = std::fs::read(&path)?;
+    let (width, height, rgba) = decode_rgba(&bytes)?;
+    Ok((width, height, rgba, path))
+}
+
+// Copies a rectangle out of tightly packed RGBA8 pixels
+fn copy_rect(
+    rgba: &[u8],
+    image_width: usize,
+    x: usize,
+    y: usize,
+    width: usize,
+    height: usize,
+) -> Vec<u8> {
+    let mut result = Vec::with_capacity(width * height * 4);
+    for row in y..y + height {
+        let begin = (row * image_width + x) * 4;
+        result.extend_from_slice(&rgba[begin..begin + width * 4]);
+    }
+
+    result
+}
+
+// Places the pieces in rows ("shelves"), tallest first. Returns the size of the packed image and
+// the top-left corner of each piece, in the same order as the pieces
+fn pack_pieces(
+    pieces: &[AtlasPiece],
+    padding: usize,
+) -> (usize, usize, Vec<(usize, usize)>) {
+    // Aim for a roughly square image that is at least as wide as the widest piece
+    let area: usize = pieces
+        .iter()
+        .map(|piece| (piece.width + padding) * (piece.height + padding))
+        .sum();
+    let widest = pieces
+        .iter()
+        .map(|piece| piece.width + padding * 2)
+        .max()
+        .unwrap_or(1);
+    let atlas_width = ((area as f64).sqrt().ceil() as usize)
+        .max(widest)
+        .next_power_of_two();
+
+    let mut order: Vec<_> = (0..pieces.len()).collect();
+    order.sort_by_key(|index| std::cmp::Reverse(pieces[*index].height));
+
+    let mut positions = vec![(0, 0); pieces.len()];
+    let mut x = padding;
+    let mut y = padding;
+    let mut shelf_height = 0;
+    for index in order {
+        let piece = &pieces[index];
+        if x + piece.width + padding > atlas_width {
+            x = padding;
+            y += shelf_height + padding;
+            shelf_height = 0;
+        }
+
+        positions[index] = (x, y);
+        x += piece.width + padding;
+        shelf_height = shelf_height.max(piece.height);
+    }
+
+    let atlas_height = (y + shelf_height + padding).max(1);
+    (atlas_width, atlas_height, positions)
+}
+
+// The daemon picks importers by the last part of the extension, and .ron is taken by the importer
+// for SerdeImportable types, so atlases get an extension of their own
+inventory::submit!(atelier_importer::SourceFileImporter {
+    extension: ".atlas",
+    instantiator: || Box::new(AtlasImporter {}),
+});
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn piece(
+        width: usize,
+        height: usize,
+    ) -> AtlasPiece {
+        AtlasPiece {
+            name: format!("{}x{}", width, height),
+            width,
+            height,
+            rgba: vec![255; width * height * 4],
+        }
+    }
+
+    // Checks that every piece is inside the image, keeps the padding from its edges and doesn't
+    // overlap (or come within padding of) any other piece
+    fn check_packing(
+        pieces: &[AtlasPiece],
+        padding: usize,
+    ) -> (usize, usize) {
+        let (width, height, positions) = pack_pieces(pieces, padding);
+        assert_eq!(positions.len(), pieces.len());
+
+        for (piece, (x, y)) in pieces.iter().zip(&positions) {
+            assert!(*x >= padding && *y >= padding, "{} is at ({}, {})", piece.name, x, y);
+            assert!(x + piece.width + padding <= width, "{} is too wide", piece.name);
+            assert!(y + piece.height + padding <= height, "{} is too tall", piece.name);
+        }
+
+        for (i, (a, (ax, ay))) in pieces.iter().zip(&positions).enumerate() {
+            for (b, (bx, by)) in pieces.iter().zip(&positions).skip(i + 1) {
+                let apart = ax + a.width + padding <= *bx
+                    || bx + b.width + padding <= *ax
+                    || ay + a.height + padding <= *by
+                    || by + b.height + padding <= *ay;
+                assert!(apart, "{} and {} overlap", a.name, b.name);
+            }
+        }
+
+        (width, height)
+    }
+
+    #[test]
+    fn packs_pieces_without_overlap() {
+        let pieces: Vec<_> = [(16, 16), (8, 32), (32, 8), (5, 7), (16, 16), (1, 1), (30, 3)]
+            .iter()
+            .map(|(width, height)| piece(*width, *height))
+            .collect();
+
+        check_packing(&pieces, 0);
+        check_packing(&pieces, 1);
+        check_packing(&pieces, 3);
+    }
+
+    #[test]
+    fn packed_width_is_a_power_of_two_that_fits_the_widest_piece() {
+        let (width, _) = check_packing(&[piece(100, 2), piece(2, 2)], 1);
+        assert_eq!(width, 128);
+
+        let (width, _) = check_packing(&[piece(127, 2)], 1);
+        assert_eq!(width, 256);
+    }
+
+    #[test]
+    fn packs_tallest_pieces_first() {
+        let pieces = [piece(4, 2), piece(4, 8), piece(4, 4)];
+        let (_, _, positions) = pack_pieces(&pieces, 0);
+        assert_eq!(positions[1], (0, 0));
+        assert_eq!(positions[2], (4, 0));
+        assert_eq!(positions[0], (0, 8));
+    }
+
+    #[test]
+    fn packs_no_pieces_into_an_empty_image() {
+        let (width, height, positions) = pack_pieces(&[], 2);
+        assert!(width >= 1);
+        assert_eq!(height, 4);
+        assert!(positions.is_empty());
+    }
+
+    #[test]
+    fn copies_a_rectangle_of_pixels() {
+        // A 3x2 image where each pixel's channels are its index
+        let rgba: Vec<u8> = (0..6).flat_map(|index| vec![index; 4]).collect();
+        assert_eq!(
+            copy_rect(&rgba, 3, 1, 0, 2, 2),
+            vec![1, 1, 1, 1, 2, 2, 2, 2, 4, 4, 4, 4, 5, 5, 5, 5]
+        );
+    }
+}
@@ -2,6 +2,7 @@ use std::io::Read;
 
 use atelier_core::AssetUuid;
 use atelier_importer::{Error, ImportedAsset, Importer, ImporterValue, Result};
+use image2::{color, Image, ImageBuf};
 use serde::{Deserialize, Serialize};
 use type_uuid::*;
 
@@ -11,6 +12,24 @@ use crate::pipeline::image::*;
 #[uuid = "3c8367c8-45fb-40bb-a229-00e5e9c3fc70"]
 struct SimpleState(Option<AssetUuid>);
 
+/// Settings for importing an image. These are stored in the image's .meta file
+#[derive(TypeUuid, Serialize, Deserialize, Default, Clone, Copy)]
+#[uuid = "0e4a2c41-5d3b-4b7f-9a51-7c0f6b2d8e13"]
+#[serde(default)]
+pub struct ImageImporterOptions {
+    /// How the color values in the file should be interpreted
+    pub color_space: ColorSpace,
+
+    /// If true, the color channels of images with transparency are multiplied by alpha
+    pub premultiply_alpha: bool,
+
+    /// Images wider than this are scaled down to fit, preserving the aspect ratio
+    pub max_width: Option<u32>,
+
+    /// Images taller than this are scaled down to fit, preserving the aspect ratio
+    pub max_height: Option<u32>,
+}
+
 #[derive(TypeUuid)]
 #[uuid = "720d636b-b79c-42d4-8f46-a2d8e1ada46e"]
 struct ImageImporter;
@@ -19,13 +38,13 @@ impl Importer for ImageImporter {
     where
         Self: Sized,
     {
-        1
+        3
     }
     fn version(&self) -> u32 {
         Self::version_static()
     }
 
-    type Options = ();
+    type Options = ImageImporterOptions;
 
     type State = SimpleState;
 
@@ -33,7 +52,7 @@ impl Importer for ImageImporter {
     fn import(
         &self,
         source: &mut dyn Read,
-        _options: Self::Options,
+        options: Self::Options,
         state: &mut Self::State,
     ) -> Result<ImporterValue> {
         let id = state
@@ -42,8 +61,25 @@ impl Importer for ImageImporter {
         *state = SimpleState(Some(id));
         let mut bytes = Vec::new();
         source.read_to_end(&mut bytes)?;
-        let asset =
-            ImageAsset::Rgb8(image2::io::decode(&bytes).map_err(|e| Error::Boxed(Box::new(e)))?);
+
+        let (width, height, rgba) = decode_rgba(&bytes)?;
+        let (width, height, rgba) = match fit_dimensions(width, height, &options) {
+            Some((fit_width, fit_height)) => (
+                fit_width,
+                fit_height,
+                downscale_rgba(&rgba, width, height, fit_width, fit_height),
+            ),
+            None => (width, height, rgba),
+        };
+
+        let asset = create_image_asset(
+            width,
+            height,
+            rgba,
+            options.color_space,
+            options.premultiply_alpha,
+        );
+
         Ok(ImporterValue {
             assets: vec![ImportedAsset {
                 id,
@@ -56,6 +92,117 @@ impl Importer for ImageImporter {
         })
     }
 }
+
+/// Decodes an image file to RGBA8 so that nothing is lost. Returns the width, height and pixels
+pub(super) fn decode_rgba(bytes: &[u8]) -> Result<(usize, usize, Vec<u8>)> {
+    let image: ImageBuf<u8, color::Rgba> =
+        image2::io::decode(bytes).map_err(|e| Error::Boxed(Box::new(e)))?;
+    Ok((image.width(), image.height(), image.data().to_vec()))
+}
+
+/// Creates an ImageAsset from RGBA8 pixels, picking the smallest format that can hold them without
+/// loss. This looks at the pixel values, since the source image has been decoded to RGBA8 by now
+/// (see ImageAsset)
+pub(super) fn create_image_asset(
+    width: usize,
+    height: usize,
+    mut rgba: Vec<u8>,
+    color_space: ColorSpace,
+    premultiply: bool,
+) -> ImageAsset {
+    let is_opaque = rgba.chunks(4).all(|rgba| rgba[3] == 255);
+    let is_gray = rgba.chunks(4).all(|rgba| rgba[0] == rgba[1] && rgba[1] == rgba[2]);
+
+    let premultiplied_alpha = premultiply && !is_opaque;
+    if premultiplied_alpha {
+        premultiply_alpha(&mut rgba);
+    }
+
+    let color_info = ImageColorInfo {
+        color_space,
+        premultiplied_alpha,
+    };
+
+    if !is_opaque {
+        ImageAsset::Rgba8(ImageBuf::new_from(width, height, rgba), color_info)
+    } else if is_gray {
+        let luma = rgba.chunks(4).map(|rgba| rgba[0]).collect();
+        ImageAsset::Luma8(ImageBuf::new_from(width, height, luma), color_info)
+    } else {
+        let mut rgb = Vec::with_capacity(width * height * 3);
+        for rgba in rgba.chunks_exact(4) {
+            rgb.extend_from_slice(&rgba[..3]);
+        }
+        ImageAsset::Rgb8(ImageBuf::new_from(width, height, rgb), color_info)
+    }
+}
+
+// Returns the size the image should be scaled down to, or None if it already fits
+fn fit_dimensions(
+    width: usize,
+    height: usize,
+    options: &ImageImporterOptions,
+) -> Option<(usize, usize)> {
+    let max_width = options.max_width.map(|x| x as usize).unwrap_or(width);
+    let max_height = options.max_height.map(|x| x as usize).unwrap_or(height);
+    if width <= max_width && height <= max_height {
+        return None;
+    }
+
+    let scale = (max_width as f64 / width as f64).min(max_height as f64 / height as f64);
+    let fit_width = ((width as f64 * scale).round() as usize).max(1);
+    let fit_height = ((height as f64 * scale).round() as usize).max(1);
+    Some((fit_width, fit_height))
+}
+
+// Scales RGBA8 pixels down by averaging the source pixels that each destination pixel covers
+fn downscale_rgba(
+    rgba: &[u8],
+    width: usize,
+    height: usize,
+    new_width: usize,
+    new_height: usize,
+) -> Vec<u8> {
+    let mut result = Vec::with_capacity(new_width * new_height * 4);
+    for y in 0..new_height {
+        let y_begin = y * height / new_height;
+        let y_end = ((y + 1) * height / new_height).max(y_begin + 1);
+        for x in 0..new_width {
+            let x_begin = x * width / new_width;
+            let x_end = ((x + 1) * width / new_width).max(x_begin + 1);
+
+            let mut sum = [0u32; 4];
+            for source_y in y_begin..y_end {
+                for source_x in x_begin..x_end {
+                    let index = (source_y * width + source_x) * 4;
+                    for channel in 0..4 {
+                        sum[channel] += rgba[index + channel] as u32;
+                    }
+                }
+            }
+
+            let count = ((y_end - y_begin) * (x_end - x_begin)) as u32;
+            for channel in 0..4 {
+                result.push(((sum[channel] + count / 2) / count) as u8);
+            }
+        }
+    }
+
+    result
+}
+
+// Multiplies the color channels by alpha. This is done on the values as stored, even for sRGB data,
+// since that's what blending with a premultiplied-alpha blend state does with them. It also keeps
+// every color channel at or below alpha
+fn premultiply_alpha(rgba: &mut [u8]) {
+    for pixel in rgba.chunks_exact_mut(4) {
+        let alpha = pixel[3] as u32;
+        for channel in &mut pixel[..3] {
+            *channel = ((*channel as u32 * alpha + 127) / 255) as u8;
+        }
+    }
+}
+
 // make a macro to reduce duplication here :)
 inventory::submit!(atelier_importer::SourceFileImporter {
     extension: "png",
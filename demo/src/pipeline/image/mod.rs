@@ -1,4 +1,10 @@
 mod assets;
 mod importers;
+mod atlas;
 
+pub use assets::ColorSpace;
 pub use assets::ImageAsset;
+pub use assets::ImageColorInfo;
+pub use importers::ImageImporterOptions;
+pub use atlas::AtlasRegionAsset;
+pub(crate) use atlas::read_atlas_image_paths;
@@ -1,5 +1,21 @@
+mod source_dirs;
+pub use source_dirs::set_asset_dirs;
+pub(crate) use source_dirs::find_source_file;
+
 mod image;
 pub use image::ImageAsset;
+pub use image::ImageColorInfo;
+pub use image::ColorSpace;
+pub use image::ImageImporterOptions;
+pub use image::AtlasRegionAsset;
+pub(crate) use image::read_atlas_image_paths;
 
 mod prefab;
 pub use prefab::PrefabAsset;
+pub use prefab::PrefabInstance;
+pub use prefab::PrefabOverride;
+pub use prefab::CookedPrefabAsset;
+pub use prefab::PrefabFileFormat;
+pub use prefab::PrefabImportError;
+pub use prefab::read_prefab;
+pub use prefab::write_prefab;
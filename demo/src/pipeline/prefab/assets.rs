@@ -1,9 +1,77 @@
 use atelier_importer::{typetag, SerdeImportable};
 use serde::{Deserialize, Serialize};
 use type_uuid::TypeUuid;
+use atelier_core::AssetUuid;
+use prefab_format::{ComponentTypeUuid, EntityUuid};
+use std::collections::HashMap;
+
+use crate::prefab_cooking::PrefabInstanceId;
 
 #[derive(TypeUuid, Serialize, Deserialize, SerdeImportable)]
 #[uuid = "5e751ea4-e63b-4192-a008-f5bf8674e45b"]
 pub struct PrefabAsset {
     pub prefab: legion_prefab::Prefab,
+
+    /// The references to other prefabs, keyed by instance ID. Unlike the prefab_refs in
+    /// prefab.prefab_meta, this can hold several references to the same prefab, so it's what
+    /// cooking uses
+    pub instances: HashMap<PrefabInstanceId, PrefabInstance>,
+}
+
+/// One reference to another prefab, along with the overrides it applies to that prefab's entities
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct PrefabInstance {
+    /// The prefab being referenced
+    pub prefab_id: AssetUuid,
+
+    /// Overrides keyed by the entity's UUID in the referenced prefab's cooked data
+    pub overrides: HashMap<EntityUuid, Vec<PrefabOverride>>,
+}
+
+/// A change that a prefab makes to an entity of a prefab it references
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub enum PrefabOverride {
+    /// Changes values of a component the entity already has. The diff is a serde_diff diff, as RON,
+    /// written with the given schema version of the component type
+    PatchComponent {
+        component_type: ComponentTypeUuid,
+        schema_version: u32,
+        diff: String,
+    },
+
+    /// Adds a component to the entity. The data is the component's values, as RON, written with
+    /// the given schema version of the component type
+    AddComponent {
+        component_type: ComponentTypeUuid,
+        schema_version: u32,
+        data: String,
+    },
+
+    /// Removes a component from the entity
+    RemoveComponent { component_type: ComponentTypeUuid },
+
+    /// Removes the entity
+    RemoveEntity,
+}
+
+/// A prefab with all of its references to other prefabs resolved and overrides applied. This is
+/// cooked from the PrefabAssets when a pack file is built (see pack_file::cook_prefabs), so that
+/// the cooking code and uncooked data aren't needed to load it
+#[derive(TypeUuid, Serialize, Deserialize)]
+#[uuid = "1ad35f47-9a35-4ef8-9c5d-8e08f0cb2b7e"]
+pub struct CookedPrefabAsset {
+    pub cooked_prefab: legion_prefab::CookedPrefab,
+}
+
+// Namespace for the v5 UUIDs of cooked prefab assets
+const COOKED_PREFAB_NAMESPACE: uuid::Uuid = uuid::Uuid::from_bytes([
+    0x6d, 0x2e, 0x1f, 0x84, 0x3b, 0xc0, 0x47, 0x51, 0x9a, 0x0e, 0xd3, 0x72, 0x18, 0xb5, 0x4c, 0xe9,
+]);
+
+impl CookedPrefabAsset {
+    /// The cooked asset for a prefab gets an ID derived from the prefab's ID so that it can be
+    /// requested knowing only the prefab's ID
+    pub fn asset_uuid_for_prefab(prefab_uuid: AssetUuid) -> AssetUuid {
+        AssetUuid(*uuid::Uuid::new_v5(&COOKED_PREFAB_NAMESPACE, &prefab_uuid.0).as_bytes())
+    }
 }
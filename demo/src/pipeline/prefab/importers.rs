@@ -1,19 +1,26 @@
 use atelier_importer::{ImportedAsset, Importer, ImporterValue, SourceFileImporter};
-use atelier_core::AssetUuid;
+use atelier_core::{AssetRef, AssetUuid};
 use serde::{Deserialize, Serialize};
 use std::io::Read;
 use type_uuid::TypeUuid;
 
 use crate::pipeline::PrefabAsset;
+use super::prefab_file::{
+    is_json_prefab, read_prefab, write_prefab, PrefabFileFormat, PrefabImportError,
+};
 
 use legion::prelude::*;
 use legion_prefab::ComponentRegistration;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use prefab_format::ComponentTypeUuid;
 
 #[derive(Default, Deserialize, Serialize, TypeUuid, Clone, Copy)]
 #[uuid = "80583980-24d4-4034-8394-ea749b43f55d"]
-pub struct PrefabImporterOptions {}
+#[serde(default)]
+pub struct PrefabImporterOptions {
+    /// The encoding of the prefab file
+    pub format: PrefabFileFormat,
+}
 
 /// A simple state for Importer to retain the same UUID between imports
 /// for all single-asset source files
@@ -32,7 +39,7 @@ impl Importer for PrefabImporter {
     type Options = PrefabImporterOptions;
 
     fn version_static() -> u32 {
-        1
+        4
     }
 
     fn version(&self) -> u32 {
@@ -42,89 +49,146 @@ impl Importer for PrefabImporter {
     fn import(
         &self,
         source: &mut dyn Read,
-        _: Self::Options,
+        options: Self::Options,
         state: &mut Self::State,
     ) -> atelier_importer::Result<ImporterValue> {
-        ///////////////////////////////////////////////////////////////
-        // STEP 1: Read in the data
-        ///////////////////////////////////////////////////////////////
+        import_prefab(source, options.format, state)
+    }
+}
+
+/// Imports prefabs produced as JSON by external tools. These are named .prefab.json, but the daemon
+/// gives this importer every .json file, so JSON that isn't a prefab is skipped (see
+/// is_json_prefab)
+#[derive(Default, TypeUuid)]
+#[uuid = "e640e669-379b-4064-94df-af5dc33804fe"]
+pub struct JsonPrefabImporter {}
+
+impl Importer for JsonPrefabImporter {
+    type State = PrefabImporterState;
+    type Options = ();
+
+    fn version_static() -> u32 {
+        4
+    }
+
+    fn version(&self) -> u32 {
+        Self::version_static()
+    }
 
-        // Read in the data
+    fn import(
+        &self,
+        source: &mut dyn Read,
+        _: Self::Options,
+        state: &mut Self::State,
+    ) -> atelier_importer::Result<ImporterValue> {
         let mut bytes = Vec::new();
         source.read_to_end(&mut bytes)?;
 
-        ///////////////////////////////////////////////////////////////
-        // STEP 2: Deserialize the prefab into a legion world
-        ///////////////////////////////////////////////////////////////
-
-        // Create a deserializer
-        let mut de = ron::de::Deserializer::from_bytes(bytes.as_slice()).unwrap();
-
-        // Create the component registry
-        let registered_components = {
-            let comp_registrations = legion_prefab::iter_component_registrations();
-            use std::iter::FromIterator;
-            let component_types: HashMap<ComponentTypeUuid, ComponentRegistration> =
-                HashMap::from_iter(comp_registrations.map(|reg| (reg.uuid().clone(), reg.clone())));
-
-            component_types
-        };
-
-        let prefab_serde_context = legion_prefab::PrefabSerdeContext {
-            registered_components,
-        };
-
-        let prefab_deser = legion_prefab::PrefabFormatDeserializer::new(&prefab_serde_context);
-        prefab_format::deserialize(&mut de, &prefab_deser)?;
-        let prefab = prefab_deser.prefab();
-
-        let prefab_asset = PrefabAsset { prefab };
-
-        ///////////////////////////////////////////////////////////////
-        // STEP 3: Now we need to save it into an asset
-        ///////////////////////////////////////////////////////////////
-
-        {
-            // Print for debug
-            let legion_world_str =
-                ron::ser::to_string_pretty(&prefab_asset, ron::ser::PrettyConfig::default())
-                    .unwrap();
-
-            log::trace!("Serialized legion world:");
-            log::trace!("legion_world_str {}", legion_world_str);
-
-            let mut ron_ser =
-                ron::ser::Serializer::new(Some(ron::ser::PrettyConfig::default()), true);
-            let prefab_ser = legion_prefab::PrefabFormatSerializer::new(
-                &prefab_serde_context,
-                &prefab_asset.prefab,
-            );
-            prefab_format::serialize(&mut ron_ser, &prefab_ser, prefab_asset.prefab.prefab_id())
-                .expect("failed to round-trip prefab");
-            log::trace!(
-                "Round-tripped legion world: {}",
-                ron_ser.into_output_string()
-            );
+        if !is_json_prefab(&bytes) {
+            log::debug!("Skipping a JSON file that isn't a prefab");
+            return Ok(ImporterValue { assets: Vec::new() });
         }
 
-        // Add the ID to the .meta
-        let prefab_id = prefab_asset.prefab.prefab_id();
-        state.id = Some(AssetUuid(prefab_id));
-
-        Ok(ImporterValue {
-            assets: vec![ImportedAsset {
-                id: state.id.expect("AssetUuid not generated"),
-                search_tags: Vec::new(),
-                build_deps: Vec::new(),
-                load_deps: Vec::new(),
-                asset_data: Box::new(prefab_asset),
-                build_pipeline: None,
-            }],
-        })
+        import_prefab(&mut bytes.as_slice(), PrefabFileFormat::Json, state)
     }
 }
 
+fn import_prefab(
+    source: &mut dyn Read,
+    format: PrefabFileFormat,
+    state: &mut PrefabImporterState,
+) -> atelier_importer::Result<ImporterValue> {
+    ///////////////////////////////////////////////////////////////
+    // STEP 1: Read in the data
+    ///////////////////////////////////////////////////////////////
+
+    // Read in the data
+    let mut bytes = Vec::new();
+    source.read_to_end(&mut bytes)?;
+
+    ///////////////////////////////////////////////////////////////
+    // STEP 2: Deserialize the prefab into a legion world
+    ///////////////////////////////////////////////////////////////
+
+    // Create the component registry
+    let registered_components = {
+        let comp_registrations = legion_prefab::iter_component_registrations();
+        use std::iter::FromIterator;
+        let component_types: HashMap<ComponentTypeUuid, ComponentRegistration> =
+            HashMap::from_iter(comp_registrations.map(|reg| (reg.uuid().clone(), reg.clone())));
+
+        component_types
+    };
+
+    let prefab_serde_context = legion_prefab::PrefabSerdeContext {
+        registered_components,
+    };
+
+    let prefab_asset = read_prefab(&bytes, format, &prefab_serde_context)?;
+
+    ///////////////////////////////////////////////////////////////
+    // STEP 3: Now we need to save it into an asset
+    ///////////////////////////////////////////////////////////////
+
+    {
+        // Print for debug
+        let legion_world_str =
+            ron::ser::to_string_pretty(&prefab_asset, ron::ser::PrettyConfig::default())
+                .map_err(|e| PrefabImportError::new(format!("failed to serialize prefab: {}", e)))?;
+
+        log::trace!("Serialized legion world:");
+        log::trace!("legion_world_str {}", legion_world_str);
+
+        let round_tripped = write_prefab(
+            &prefab_asset.prefab,
+            &prefab_asset.instances,
+            PrefabFileFormat::Ron,
+            &prefab_serde_context,
+        )
+        .map_err(|e| PrefabImportError::new(format!("failed to round-trip prefab: {}", e)))?;
+        log::trace!(
+            "Round-tripped legion world: {}",
+            String::from_utf8_lossy(&round_tripped)
+        );
+    }
+
+    // Add the ID to the .meta
+    let prefab_id = prefab_asset.prefab.prefab_id();
+    state.id = Some(AssetUuid(prefab_id));
+
+    // Prefabs are cooked after import, by the editor (see PrefabCookJob) and when a pack file is
+    // built (see pack_file::cook_prefabs), so a change to a referenced prefab is picked up without
+    // importing this one again. Listing the directly referenced prefabs as load deps makes the
+    // loader load them along with this prefab
+    let directly_referenced_prefabs: Vec<_> = prefab_asset
+        .instances
+        .values()
+        .map(|instance| instance.prefab_id)
+        .collect::<HashSet<_>>()
+        .into_iter()
+        .map(AssetRef::Uuid)
+        .collect();
+
+    Ok(ImporterValue {
+        assets: vec![ImportedAsset {
+            id: AssetUuid(prefab_id),
+            search_tags: Vec::new(),
+            build_deps: Vec::new(),
+            load_deps: directly_referenced_prefabs,
+            asset_data: Box::new(prefab_asset),
+            build_pipeline: None,
+        }],
+    })
+}
+
 inventory::submit!(SourceFileImporter {
     extension: ".prefab",
     instantiator: || Box::new(PrefabImporter::default())
 });
+
+// The daemon picks importers by the last part of the extension, so .prefab.json files are imported
+// by the importer for .json. Other JSON files in the asset directories import as no assets
+inventory::submit!(SourceFileImporter {
+    extension: ".json",
+    instantiator: || Box::new(JsonPrefabImporter::default())
+});
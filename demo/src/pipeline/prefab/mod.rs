@@ -1,4 +1,13 @@
 mod importers;
 
+mod prefab_file;
+pub use prefab_file::PrefabFileFormat;
+pub use prefab_file::PrefabImportError;
+pub use prefab_file::read_prefab;
+pub use prefab_file::write_prefab;
+
 mod assets;
 pub use assets::PrefabAsset;
+pub use assets::PrefabInstance;
+pub use assets::PrefabOverride;
+pub use assets::CookedPrefabAsset;
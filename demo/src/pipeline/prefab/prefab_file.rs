@@ -0,0 +1,1428 @@
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::ops::Range;
+use serde::{Deserialize, Serialize};
+use serde::de::DeserializeSeed;
+use legion_prefab::{Prefab, PrefabSerdeContext};
+use prefab_format::{ComponentTypeUuid, EntityUuid, PrefabUuid, StorageDeserializer};
+use atelier_core::AssetUuid;
+
+use crate::component_schemas::{current_schema_versions, ComponentSchemaRegistration};
+use crate::component_value::{ComponentValue, SpannedValue};
+use crate::prefab_cooking::PrefabInstanceId;
+use super::assets::{PrefabAsset, PrefabInstance, PrefabOverride};
+
+/// The encodings a prefab file can be stored in
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PrefabFileFormat {
+    /// Human-readable, and the format the editor saves in
+    Ron,
+
+    /// Compact, for large generated prefabs. Component data in this format can't be migrated to a
+    /// newer schema version
+    Bincode,
+
+    /// For prefabs produced by external tools. Component data uses the same field names as RON.
+    /// JSON has no place for the component schema versions, so the data must use the current
+    /// versions
+    Json,
+}
+
+impl Default for PrefabFileFormat {
+    fn default() -> Self {
+        PrefabFileFormat::Ron
+    }
+}
+
+impl std::str::FromStr for PrefabFileFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "ron" => Ok(PrefabFileFormat::Ron),
+            "bincode" => Ok(PrefabFileFormat::Bincode),
+            "json" => Ok(PrefabFileFormat::Json),
+            _ => Err(format!("unknown prefab format {}, expected ron, bincode or json", s)),
+        }
+    }
+}
+
+impl PrefabFileFormat {
+    /// Determines the format of a prefab file from its contents
+    pub fn detect(bytes: &[u8]) -> Self {
+        let first_char = bytes.iter().find(|c| !c.is_ascii_whitespace());
+        if bytes.starts_with(BINCODE_PREFAB_MAGIC) {
+            PrefabFileFormat::Bincode
+        } else if first_char == Some(&b'{') {
+            PrefabFileFormat::Json
+        } else {
+            PrefabFileFormat::Ron
+        }
+    }
+}
+
+/// Returns true if the JSON looks like a prefab file, i.e. it's an object with a prefab that has an
+/// ID. The importer for .json files uses this to skip JSON that other tools keep alongside the
+/// assets, rather than failing to import it
+pub fn is_json_prefab(bytes: &[u8]) -> bool {
+    match serde_json::from_slice::<serde_json::Value>(bytes) {
+        Ok(value) => value
+            .get("prefab")
+            .and_then(|prefab| prefab.get("id"))
+            .map_or(false, |id| id.is_string()),
+        Err(_) => false,
+    }
+}
+
+// Binary prefab files start with this, followed by the length of the component schema versions as
+// a little-endian u64, the component schema versions and then the prefab file (the prefab and its
+// instances), all encoded with bincode
+const BINCODE_PREFAB_MAGIC: &[u8] = b"PREFAB-BINCODE\n";
+
+/// Describes why a prefab file could not be read. When importing, the daemon reports this along
+/// with the path of the file that failed, and keeps serving the other assets
+#[derive(Debug)]
+pub struct PrefabImportError {
+    /// The line and column in the file where the error was found, if known
+    pub position: Option<(usize, usize)>,
+
+    /// The entity that was being read when the error occurred, if known
+    pub entity: Option<EntityUuid>,
+
+    /// The component type that was being read when the error occurred, if known
+    pub component_type: Option<ComponentTypeUuid>,
+
+    /// What went wrong
+    pub message: String,
+}
+
+impl PrefabImportError {
+    pub(crate) fn new(message: String) -> Self {
+        PrefabImportError {
+            position: None,
+            entity: None,
+            component_type: None,
+            message,
+        }
+    }
+
+    // Syntax errors include the position, other errors (such as a component that fails to
+    // deserialize) get it from the entity and component that were being read
+    fn from_ron_error(
+        error: ron::de::Error,
+        entity: Option<EntityUuid>,
+        component_type: Option<ComponentTypeUuid>,
+    ) -> Self {
+        let position = match &error {
+            ron::de::Error::Parser(_, position) => Some((position.line, position.col)),
+            _ => None,
+        };
+
+        PrefabImportError {
+            position,
+            entity,
+            component_type,
+            message: error.to_string(),
+        }
+    }
+
+    // JSON errors always include the position
+    fn from_json_error(
+        error: serde_json::Error,
+        entity: Option<EntityUuid>,
+        component_type: Option<ComponentTypeUuid>,
+    ) -> Self {
+        PrefabImportError {
+            position: Some((error.line(), error.column())),
+            entity,
+            component_type,
+            message: error.to_string(),
+        }
+    }
+}
+
+impl std::error::Error for PrefabImportError {}
+
+impl core::fmt::Display for PrefabImportError {
+    fn fmt(
+        &self,
+        fmt: &mut core::fmt::Formatter,
+    ) -> core::fmt::Result {
+        if let Some((line, column)) = self.position {
+            write!(fmt, "line {}, column {}: ", line, column)?;
+        }
+
+        write!(fmt, "{}", self.message)?;
+
+        if let Some(entity) = self.entity {
+            write!(fmt, " (entity {}", uuid::Uuid::from_bytes(entity))?;
+            if let Some(component_type) = self.component_type {
+                write!(fmt, ", component type {}", uuid::Uuid::from_bytes(component_type))?;
+            }
+            write!(fmt, ")")?;
+        }
+
+        Ok(())
+    }
+}
+
+impl From<PrefabImportError> for atelier_importer::Error {
+    fn from(error: PrefabImportError) -> Self {
+        atelier_importer::Error::Boxed(Box::new(error))
+    }
+}
+
+// Wraps the legion_prefab deserializer to remember which entity and component were being read, so
+// that errors can say where in the prefab they happened
+struct ImportContextDeserializer<'a, 'b> {
+    inner: &'a legion_prefab::PrefabFormatDeserializer<'b>,
+    component_schemas: HashMap<ComponentTypeUuid, &'static ComponentSchemaRegistration>,
+    data_versions: HashMap<ComponentTypeUuid, u32>,
+    entity: Cell<Option<EntityUuid>>,
+    component_type: Cell<Option<ComponentTypeUuid>>,
+    prefab_ref: Cell<Option<PrefabUuid>>,
+}
+
+impl<'a, 'b> StorageDeserializer for ImportContextDeserializer<'a, 'b> {
+    fn begin_entity_object(
+        &self,
+        prefab: &PrefabUuid,
+        entity: &EntityUuid,
+    ) {
+        self.entity.set(Some(*entity));
+        self.inner.begin_entity_object(prefab, entity);
+    }
+
+    fn end_entity_object(
+        &self,
+        prefab: &PrefabUuid,
+        entity: &EntityUuid,
+    ) {
+        self.inner.end_entity_object(prefab, entity);
+        self.entity.set(None);
+    }
+
+    fn deserialize_component<'de, D: serde::Deserializer<'de>>(
+        &self,
+        prefab: &PrefabUuid,
+        entity: &EntityUuid,
+        component_type: &ComponentTypeUuid,
+        deserializer: D,
+    ) -> Result<(), D::Error> {
+        self.entity.set(Some(*entity));
+        self.component_type.set(Some(*component_type));
+
+        // RON prefabs are migrated before they're deserialized and JSON prefabs are always written
+        // with the current versions, so only binary prefabs can still have old data here
+        let data_version = self.data_versions.get(component_type).cloned().unwrap_or(1);
+        if let Some(schema) = self.component_schemas.get(component_type) {
+            if schema.version() != data_version {
+                use serde::de::Error;
+                return Err(D::Error::custom(format!(
+                    "{} data has version {} but the current version is {}. Binary prefabs can't \
+                     be migrated, convert the prefab to RON and import it to migrate it",
+                    schema.type_name(),
+                    data_version,
+                    schema.version()
+                )));
+            }
+        }
+
+        self.inner.deserialize_component(prefab, entity, component_type, deserializer)?;
+        self.component_type.set(None);
+        Ok(())
+    }
+
+    // References to other prefabs are listed in the file's instances rather than in the prefab,
+    // since prefab_format has no way to tell two references to the same prefab apart. A PrefabRef
+    // in the prefab is reported as an error once the prefab has been read
+    fn begin_prefab_ref(
+        &self,
+        _prefab: &PrefabUuid,
+        target_prefab: &PrefabUuid,
+    ) {
+        self.prefab_ref.set(Some(*target_prefab));
+    }
+
+    fn end_prefab_ref(
+        &self,
+        _prefab: &PrefabUuid,
+        _target_prefab: &PrefabUuid,
+    ) {
+    }
+
+    fn apply_component_diff<'de, D: serde::Deserializer<'de>>(
+        &self,
+        _parent_prefab: &PrefabUuid,
+        prefab_ref: &PrefabUuid,
+        _entity: &EntityUuid,
+        _component_type: &ComponentTypeUuid,
+        _diff: D,
+    ) -> Result<(), D::Error> {
+        use serde::de::Error;
+        Err(D::Error::custom(prefab_ref_error(prefab_ref)))
+    }
+}
+
+fn prefab_ref_error(target_prefab: &PrefabUuid) -> String {
+    format!(
+        "the prefab references prefab {} with a PrefabRef. References to other prefabs are \
+         listed in the file's instances, each with its own instance ID",
+        uuid::Uuid::from_bytes(*target_prefab)
+    )
+}
+
+// The layout of a prefab file:
+//
+// PrefabFile(
+//     prefab: Prefab(
+//         id: "5fd8256d-db36-4fe2-8211-c7b3446e1927",
+//         objects: [ /* the prefab's own entities */ ],
+//     ),
+//     instances: [
+//         PrefabInstance(
+//             id: "1c7e2b4d-3c55-4f0e-9f3e-2a9d5c1b8e41",
+//             prefab_id: "14dec17f-ae14-40a3-8e44-e487fc423287",
+//             entity_overrides: [
+//                 EntityOverrides(
+//                     entity_id: "62b3dbd1-56a8-469e-a262-41a66321da8b",
+//                     overrides: [
+//                         PatchComponent(
+//                             component_type: "f5780013-bae4-49f0-ac0e-a108ff52fec0",
+//                             diff: r##"[ Enter(Field("position")), Value(Vec2(300.0, 300.0)) ]"##,
+//                         ),
+//                         AddComponent(
+//                             component_type: "c05e5c27-58ca-4d68-b825-b20f67fdaf37",
+//                             data: r##"(half_extents: Vec2(1.0, 1.0))"##,
+//                         ),
+//                         RemoveComponent(component_type: "36df3006-a5ad-4997-9ccc-0860f49195ad"),
+//                     ],
+//                 ),
+//                 EntityOverrides(
+//                     entity_id: "0e3b5f6a-8d4c-4a1e-b7f2-9c6d1e8a2b35",
+//                     overrides: [RemoveEntity],
+//                 ),
+//             ],
+//         ),
+//     ],
+// )
+//
+// The prefab is read and written by prefab_format. Overrides are written like PrefabOverride, with
+// the diffs and component data as RON strings since they can only be deserialized once the
+// component type is known. Each reference to another prefab is written
+// with an ID that stays the same when references are added, removed or reordered, so overrides
+// (and the entity UUIDs derived from the instance, see cooked_entity_uuid) stay with the instance
+// they were made on
+const PREFAB_FILE_FIELDS: &[&str] = &["prefab", "instances"];
+
+#[derive(Deserialize)]
+#[serde(field_identifier, rename_all = "snake_case")]
+enum PrefabFileField {
+    Prefab,
+    Instances,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename = "PrefabInstance")]
+struct PrefabInstanceData {
+    #[serde(with = "uuid_string")]
+    id: PrefabInstanceId,
+    prefab_id: AssetUuid,
+    #[serde(default)]
+    entity_overrides: Vec<EntityOverridesData>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename = "EntityOverrides")]
+struct EntityOverridesData {
+    #[serde(with = "uuid_string")]
+    entity_id: EntityUuid,
+    overrides: Vec<PrefabOverrideData>,
+}
+
+#[derive(Serialize, Deserialize)]
+enum PrefabOverrideData {
+    PatchComponent {
+        #[serde(with = "uuid_string")]
+        component_type: ComponentTypeUuid,
+        diff: String,
+    },
+    AddComponent {
+        #[serde(with = "uuid_string")]
+        component_type: ComponentTypeUuid,
+        data: String,
+    },
+    RemoveComponent {
+        #[serde(with = "uuid_string")]
+        component_type: ComponentTypeUuid,
+    },
+    RemoveEntity,
+}
+
+// Entity, component type and instance IDs are written as UUID strings in RON and JSON, like
+// prefab_format does, and as bytes in bincode
+mod uuid_string {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<S: Serializer>(
+        uuid: &[u8; 16],
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            serializer.serialize_str(&uuid::Uuid::from_bytes(*uuid).to_string())
+        } else {
+            uuid.serialize(serializer)
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<[u8; 16], D::Error> {
+        if deserializer.is_human_readable() {
+            use serde::de::Error;
+            let uuid_string = String::deserialize(deserializer)?;
+            uuid::Uuid::parse_str(&uuid_string)
+                .map(|uuid| *uuid.as_bytes())
+                .map_err(|e| D::Error::custom(format!("invalid UUID {}: {}", uuid_string, e)))
+        } else {
+            <[u8; 16]>::deserialize(deserializer)
+        }
+    }
+}
+
+// Reads a prefab file, passing the prefab to prefab_format and returning the instances as written
+struct PrefabFileSeed<'a, 'b, 'c> {
+    storage: &'a ImportContextDeserializer<'b, 'c>,
+}
+
+impl<'de, 'a, 'b, 'c> serde::de::DeserializeSeed<'de> for PrefabFileSeed<'a, 'b, 'c> {
+    type Value = Vec<PrefabInstanceData>;
+
+    fn deserialize<D: serde::Deserializer<'de>>(
+        self,
+        deserializer: D,
+    ) -> Result<Self::Value, D::Error> {
+        deserializer.deserialize_struct("PrefabFile", PREFAB_FILE_FIELDS, self)
+    }
+}
+
+impl<'de, 'a, 'b, 'c> serde::de::Visitor<'de> for PrefabFileSeed<'a, 'b, 'c> {
+    type Value = Vec<PrefabInstanceData>;
+
+    fn expecting(
+        &self,
+        formatter: &mut core::fmt::Formatter,
+    ) -> core::fmt::Result {
+        formatter.write_str("a PrefabFile with a prefab and its instances")
+    }
+
+    // Bincode writes the fields in order without their names
+    fn visit_seq<A: serde::de::SeqAccess<'de>>(
+        self,
+        mut seq: A,
+    ) -> Result<Self::Value, A::Error> {
+        use serde::de::Error;
+        seq.next_element_seed(PrefabSeed {
+            storage: self.storage,
+        })?
+        .ok_or_else(|| A::Error::invalid_length(0, &"a PrefabFile with 2 fields"))?;
+        seq.next_element()?
+            .ok_or_else(|| A::Error::invalid_length(1, &"a PrefabFile with 2 fields"))
+    }
+
+    fn visit_map<A: serde::de::MapAccess<'de>>(
+        self,
+        mut map: A,
+    ) -> Result<Self::Value, A::Error> {
+        use serde::de::Error;
+        let mut has_prefab = false;
+        let mut instances = None;
+        while let Some(field) = map.next_key()? {
+            match field {
+                PrefabFileField::Prefab if has_prefab => {
+                    return Err(A::Error::duplicate_field("prefab"))
+                }
+                PrefabFileField::Prefab => {
+                    map.next_value_seed(PrefabSeed {
+                        storage: self.storage,
+                    })?;
+                    has_prefab = true;
+                }
+                PrefabFileField::Instances if instances.is_some() => {
+                    return Err(A::Error::duplicate_field("instances"))
+                }
+                PrefabFileField::Instances => instances = Some(map.next_value()?),
+            }
+        }
+
+        if !has_prefab {
+            return Err(A::Error::missing_field("prefab"));
+        }
+
+        // A prefab that doesn't reference other prefabs can leave out its instances
+        Ok(instances.unwrap_or_default())
+    }
+}
+
+struct PrefabSeed<'a, 'b, 'c> {
+    storage: &'a ImportContextDeserializer<'b, 'c>,
+}
+
+impl<'de, 'a, 'b, 'c> serde::de::DeserializeSeed<'de> for PrefabSeed<'a, 'b, 'c> {
+    type Value = ();
+
+    fn deserialize<D: serde::Deserializer<'de>>(
+        self,
+        deserializer: D,
+    ) -> Result<Self::Value, D::Error> {
+        prefab_format::deserialize(deserializer, self.storage).map(|_| ())
+    }
+}
+
+// Writes a prefab file in the layout described above PREFAB_FILE_FIELDS
+struct PrefabFileData<'a, 'b, 'c> {
+    prefab: SerializePrefab<'a, 'b, 'c>,
+    instances: Vec<PrefabInstanceData>,
+}
+
+impl<'a, 'b, 'c> Serialize for PrefabFileData<'a, 'b, 'c> {
+    fn serialize<S: serde::Serializer>(
+        &self,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeStruct;
+        let mut file = serializer.serialize_struct("PrefabFile", PREFAB_FILE_FIELDS.len())?;
+        file.serialize_field("prefab", &self.prefab)?;
+        file.serialize_field("instances", &self.instances)?;
+        file.end()
+    }
+}
+
+struct SerializePrefab<'a, 'b, 'c> {
+    storage: &'a legion_prefab::PrefabFormatSerializer<'b, 'c>,
+    prefab_id: PrefabUuid,
+}
+
+impl<'a, 'b, 'c> Serialize for SerializePrefab<'a, 'b, 'c> {
+    fn serialize<S: serde::Serializer>(
+        &self,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        prefab_format::serialize(serializer, self.storage, self.prefab_id)
+    }
+}
+
+// Converts the instances as written in a prefab file to PrefabInstances
+fn read_instances(
+    instances: Vec<PrefabInstanceData>,
+    data_versions: &HashMap<ComponentTypeUuid, u32>,
+) -> Result<HashMap<PrefabInstanceId, PrefabInstance>, PrefabImportError> {
+    let mut prefab_instances = HashMap::with_capacity(instances.len());
+    for instance in instances {
+        let mut overrides: HashMap<EntityUuid, Vec<PrefabOverride>> = HashMap::new();
+        for entity_overrides in instance.entity_overrides {
+            let prefab_overrides = overrides.entry(entity_overrides.entity_id).or_default();
+            prefab_overrides.extend(
+                entity_overrides
+                    .overrides
+                    .into_iter()
+                    .map(|prefab_override| read_prefab_override(prefab_override, data_versions)),
+            );
+        }
+
+        let prefab_instance = PrefabInstance {
+            prefab_id: instance.prefab_id,
+            overrides,
+        };
+        if prefab_instances.insert(instance.id, prefab_instance).is_some() {
+            return Err(PrefabImportError::new(format!(
+                "instance ID {} is used by more than one instance",
+                uuid::Uuid::from_bytes(instance.id)
+            )));
+        }
+    }
+
+    Ok(prefab_instances)
+}
+
+// Converts PrefabInstances to the instances written in a prefab file. Instances and entities are
+// sorted by UUID so that saving the same prefab twice writes the same file
+fn write_instances(
+    instances: &HashMap<PrefabInstanceId, PrefabInstance>,
+) -> Result<Vec<PrefabInstanceData>, String> {
+    let component_schemas = crate::component_schemas::component_schemas();
+
+    let mut instance_ids: Vec<_> = instances.keys().collect();
+    instance_ids.sort();
+
+    let mut instances_data = Vec::with_capacity(instance_ids.len());
+    for instance_id in instance_ids {
+        let instance = &instances[instance_id];
+        let mut entity_ids: Vec<_> = instance.overrides.keys().collect();
+        entity_ids.sort();
+
+        let mut entity_overrides = Vec::with_capacity(entity_ids.len());
+        for entity_id in entity_ids {
+            let overrides = instance.overrides[entity_id]
+                .iter()
+                .map(|prefab_override| write_prefab_override(prefab_override, &component_schemas))
+                .collect::<Result<_, _>>()?;
+            entity_overrides.push(EntityOverridesData {
+                entity_id: *entity_id,
+                overrides,
+            });
+        }
+
+        instances_data.push(PrefabInstanceData {
+            id: *instance_id,
+            prefab_id: instance.prefab_id,
+            entity_overrides,
+        });
+    }
+
+    Ok(instances_data)
+}
+
+// Converts an override as written in a prefab file to a PrefabOverride
+fn read_prefab_override(
+    prefab_override: PrefabOverrideData,
+    data_versions: &HashMap<ComponentTypeUuid, u32>,
+) -> PrefabOverride {
+    let data_version = |component_type: &ComponentTypeUuid| {
+        data_versions.get(component_type).cloned().unwrap_or(1)
+    };
+
+    match prefab_override {
+        PrefabOverrideData::PatchComponent {
+            component_type,
+            diff,
+        } => PrefabOverride::PatchComponent {
+            component_type,
+            schema_version: data_version(&component_type),
+            diff,
+        },
+        PrefabOverrideData::AddComponent {
+            component_type,
+            data,
+        } => PrefabOverride::AddComponent {
+            component_type,
+            schema_version: data_version(&component_type),
+            data,
+        },
+        PrefabOverrideData::RemoveComponent { component_type } => {
+            PrefabOverride::RemoveComponent { component_type }
+        }
+        PrefabOverrideData::RemoveEntity => PrefabOverride::RemoveEntity,
+    }
+}
+
+// Converts a PrefabOverride to the override written in a prefab file. Data written with an older
+// schema version is migrated, since the file records only the current versions
+fn write_prefab_override(
+    prefab_override: &PrefabOverride,
+    component_schemas: &HashMap<ComponentTypeUuid, &'static ComponentSchemaRegistration>,
+) -> Result<PrefabOverrideData, String> {
+    match prefab_override {
+        PrefabOverride::PatchComponent {
+            component_type,
+            schema_version,
+            diff,
+        } => {
+            let diff = match component_schemas.get(component_type) {
+                Some(schema) => schema.migrate_diff(*schema_version, diff)?,
+                None => diff.clone(),
+            };
+            Ok(PrefabOverrideData::PatchComponent {
+                component_type: *component_type,
+                diff,
+            })
+        }
+        PrefabOverride::AddComponent {
+            component_type,
+            schema_version,
+            data,
+        } => {
+            let data = match component_schemas.get(component_type) {
+                Some(schema) => schema.migrate_ron(*schema_version, data)?,
+                None => data.clone(),
+            };
+            Ok(PrefabOverrideData::AddComponent {
+                component_type: *component_type,
+                data,
+            })
+        }
+        PrefabOverride::RemoveComponent { component_type } => {
+            Ok(PrefabOverrideData::RemoveComponent {
+                component_type: *component_type,
+            })
+        }
+        PrefabOverride::RemoveEntity => Ok(PrefabOverrideData::RemoveEntity),
+    }
+}
+
+// Returns true if any of the component data in a prefab with the given schema versions is older
+// than the current version of its component type
+fn is_migration_needed(
+    component_schemas: &HashMap<ComponentTypeUuid, &'static ComponentSchemaRegistration>,
+    data_versions: &HashMap<ComponentTypeUuid, u32>,
+) -> bool {
+    component_schemas.iter().any(|(component_type, schema)| {
+        data_versions.get(component_type).cloned().unwrap_or(1) != schema.version()
+    })
+}
+
+// Upgrades the component data and overrides in a RON prefab that were written with older schema
+// versions, and returns the prefab with the upgraded data. The prefab is read as ComponentValues
+// rather than deserialized so that the names of structs and enum variants in the data are kept.
+//
+// Only the values that change are rewritten. Each is written on one line followed by as many line
+// breaks as the text it replaces had, so errors found when deserializing the migrated prefab are
+// reported on the line they're on in the file
+fn migrate_ron_prefab(
+    text: &str,
+    component_schemas: &HashMap<ComponentTypeUuid, &'static ComponentSchemaRegistration>,
+    data_versions: &HashMap<ComponentTypeUuid, u32>,
+) -> Result<String, PrefabImportError> {
+    let document = ComponentValue::parse_spanned(text).map_err(|e| {
+        PrefabImportError::new(format!("failed to read prefab to migrate it: {}", e))
+    })?;
+    let prefab_file = document.root();
+
+    let mut migrator = PrefabMigrator {
+        text,
+        component_schemas,
+        data_versions,
+        replacements: Vec::new(),
+    };
+
+    let objects = prefab_file
+        .field("prefab")
+        .and_then(|prefab| prefab.field("objects"))
+        .map(|objects| objects.items())
+        .unwrap_or_default();
+    for object in objects {
+        match object.value() {
+            ComponentValue::Tuple {
+                name: Some(name),
+                fields,
+            } if fields.len() == 1 && name == "Entity" => {
+                migrator.migrate_entity(object.items()[0])?
+            }
+            _ => {}
+        }
+    }
+
+    let instances = prefab_file
+        .field("instances")
+        .map(|instances| instances.items())
+        .unwrap_or_default();
+    for instance in instances {
+        migrator.migrate_instance(instance)?;
+    }
+
+    // Syntax errors and unknown fields are left for the deserializer to report
+    let mut replacements = migrator.replacements;
+    replacements.sort_by_key(|(span, _)| span.start);
+
+    let mut migrated_text = String::with_capacity(text.len());
+    let mut position = 0;
+    for (span, value) in replacements {
+        migrated_text.push_str(&text[position..span.start]);
+        migrated_text.push_str(&value.to_ron_string_single_line());
+        let line_breaks = text[span.clone()].matches('\n').count();
+        migrated_text.push_str(&"\n".repeat(line_breaks));
+        position = span.end;
+    }
+    migrated_text.push_str(&text[position..]);
+
+    Ok(migrated_text)
+}
+
+struct PrefabMigrator<'a> {
+    text: &'a str,
+    component_schemas: &'a HashMap<ComponentTypeUuid, &'static ComponentSchemaRegistration>,
+    data_versions: &'a HashMap<ComponentTypeUuid, u32>,
+
+    // The values that were migrated and the span of the text they replace
+    replacements: Vec<(Range<usize>, ComponentValue)>,
+}
+
+impl<'a> PrefabMigrator<'a> {
+    fn migrate_entity(
+        &mut self,
+        entity: SpannedValue,
+    ) -> Result<(), PrefabImportError> {
+        let entity_id = entity.field("id").and_then(|id| read_uuid_value(id.value()));
+        let components = entity
+            .field("components")
+            .map(|components| components.items())
+            .unwrap_or_default();
+
+        for component in components {
+            let component_type = component
+                .field("type")
+                .and_then(|component_type| read_uuid_value(component_type.value()));
+            let component_type = match component_type {
+                Some(component_type) => component_type,
+                None => continue,
+            };
+
+            if let Some(data) = component.field("data") {
+                let migrated = self
+                    .migrate_data(&component_type, data.value().clone())
+                    .map_err(|message| self.error(&data, entity_id, component_type, message))?;
+                self.replace(&data, migrated);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn migrate_instance(
+        &mut self,
+        instance: SpannedValue,
+    ) -> Result<(), PrefabImportError> {
+        let entity_overrides = instance
+            .field("entity_overrides")
+            .map(|entity_overrides| entity_overrides.items())
+            .unwrap_or_default();
+
+        for entity_override in entity_overrides {
+            let entity_id = entity_override
+                .field("entity_id")
+                .and_then(|entity_id| read_uuid_value(entity_id.value()));
+            let overrides = entity_override
+                .field("overrides")
+                .map(|overrides| overrides.items())
+                .unwrap_or_default();
+
+            for prefab_override in overrides {
+                // Only the overrides that carry component data need to be migrated
+                let field_name = match prefab_override.value() {
+                    ComponentValue::Struct {
+                        name: Some(kind), ..
+                    } if kind == "PatchComponent" => "diff",
+                    ComponentValue::Struct {
+                        name: Some(kind), ..
+                    } if kind == "AddComponent" => "data",
+                    _ => continue,
+                };
+
+                let component_type = prefab_override
+                    .field("component_type")
+                    .and_then(|component_type| read_uuid_value(component_type.value()));
+                let component_type = match component_type {
+                    Some(component_type) => component_type,
+                    None => continue,
+                };
+
+                let field = match prefab_override.field(field_name) {
+                    Some(field) => field,
+                    None => continue,
+                };
+                let text = match field.value() {
+                    ComponentValue::String(text) => text,
+                    _ => continue,
+                };
+
+                let result = if field_name == "diff" {
+                    self.migrate_diff(&component_type, text)
+                } else {
+                    self.migrate_ron(&component_type, text)
+                };
+                let migrated = result
+                    .map_err(|message| self.error(&field, entity_id, component_type, message))?;
+                self.replace(&field, ComponentValue::String(migrated));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn replace(
+        &mut self,
+        original: &SpannedValue,
+        migrated: ComponentValue,
+    ) {
+        if *original.value() != migrated {
+            self.replacements.push((original.span(), migrated));
+        }
+    }
+
+    fn error(
+        &self,
+        value: &SpannedValue,
+        entity: Option<EntityUuid>,
+        component_type: ComponentTypeUuid,
+        message: String,
+    ) -> PrefabImportError {
+        let preceding_text = &self.text[..value.span().start];
+        let line = preceding_text.matches('\n').count() + 1;
+        let line_start = preceding_text.rfind('\n').map(|index| index + 1).unwrap_or(0);
+        let column = preceding_text[line_start..].chars().count() + 1;
+
+        PrefabImportError {
+            position: Some((line, column)),
+            entity,
+            component_type: Some(component_type),
+            message,
+        }
+    }
+
+    fn migrate_diff(
+        &self,
+        component_type: &ComponentTypeUuid,
+        diff: &str,
+    ) -> Result<String, String> {
+        match self.component_schemas.get(component_type) {
+            Some(schema) => schema.migrate_diff(self.data_version(component_type), diff),
+            None => Ok(diff.to_string()),
+        }
+    }
+
+    fn migrate_ron(
+        &self,
+        component_type: &ComponentTypeUuid,
+        data: &str,
+    ) -> Result<String, String> {
+        match self.component_schemas.get(component_type) {
+            Some(schema) => schema.migrate_ron(self.data_version(component_type), data),
+            None => Ok(data.to_string()),
+        }
+    }
+
+    fn migrate_data(
+        &self,
+        component_type: &ComponentTypeUuid,
+        data: ComponentValue,
+    ) -> Result<ComponentValue, String> {
+        match self.component_schemas.get(component_type) {
+            Some(schema) => schema.migrate(self.data_version(component_type), data),
+            None => Ok(data),
+        }
+    }
+
+    fn data_version(
+        &self,
+        component_type: &ComponentTypeUuid,
+    ) -> u32 {
+        self.data_versions.get(component_type).cloned().unwrap_or(1)
+    }
+}
+
+// Reads an entity or component type UUID, or returns None if the value isn't one
+fn read_uuid_value(value: &ComponentValue) -> Option<[u8; 16]> {
+    match value {
+        ComponentValue::String(uuid) => uuid::Uuid::parse_str(uuid).ok().map(|x| *x.as_bytes()),
+        _ => None,
+    }
+}
+
+/// Reads a prefab file in the given format. Component data written with an older schema version is
+/// migrated to the current version
+pub fn read_prefab(
+    bytes: &[u8],
+    format: PrefabFileFormat,
+    prefab_serde_context: &PrefabSerdeContext,
+) -> Result<PrefabAsset, PrefabImportError> {
+    let detected_format = PrefabFileFormat::detect(bytes);
+    if detected_format != format {
+        return Err(PrefabImportError::new(format!(
+            "expected a {:?} prefab but the file contains a {:?} prefab. Set the format in the \
+             importer options of the .meta file to match",
+            format, detected_format
+        )));
+    }
+
+    let prefab_deser = legion_prefab::PrefabFormatDeserializer::new(prefab_serde_context);
+    let mut context_deser = ImportContextDeserializer {
+        inner: &prefab_deser,
+        component_schemas: crate::component_schemas::component_schemas(),
+        data_versions: HashMap::new(),
+        entity: Cell::new(None),
+        component_type: Cell::new(None),
+        prefab_ref: Cell::new(None),
+    };
+
+    let instances = match format {
+        PrefabFileFormat::Ron => {
+            // Find what schema version the component data was written with
+            let text = std::str::from_utf8(bytes)
+                .map_err(|e| PrefabImportError::new(format!("prefab file is not UTF-8: {}", e)))?;
+            let data_versions = crate::component_schemas::read_schema_versions_header(text)
+                .map_err(PrefabImportError::new)?;
+
+            // Upgrade any component data and overrides written with an older version
+            let migrated_text;
+            let bytes = if is_migration_needed(&context_deser.component_schemas, &data_versions) {
+                migrated_text =
+                    migrate_ron_prefab(text, &context_deser.component_schemas, &data_versions)?;
+                migrated_text.as_bytes()
+            } else {
+                bytes
+            };
+            context_deser.data_versions = current_schema_versions();
+
+            // Create a deserializer
+            let mut de = ron::de::Deserializer::from_bytes(bytes)
+                .map_err(|e| PrefabImportError::from_ron_error(e, None, None))?;
+
+            let seed = PrefabFileSeed {
+                storage: &context_deser,
+            };
+            let instances = seed.deserialize(&mut de).map_err(|e| {
+                PrefabImportError::from_ron_error(
+                    e,
+                    context_deser.entity.get(),
+                    context_deser.component_type.get(),
+                )
+            })?;
+
+            // Catch trailing garbage after the prefab
+            de.end().map_err(|e| PrefabImportError::from_ron_error(e, None, None))?;
+            instances
+        }
+        PrefabFileFormat::Json => {
+            // JSON prefabs are always written with the current versions
+            context_deser.data_versions = current_schema_versions();
+
+            let mut de = serde_json::Deserializer::from_slice(bytes);
+            let seed = PrefabFileSeed {
+                storage: &context_deser,
+            };
+            let instances = seed.deserialize(&mut de).map_err(|e| {
+                PrefabImportError::from_json_error(
+                    e,
+                    context_deser.entity.get(),
+                    context_deser.component_type.get(),
+                )
+            })?;
+
+            // Catch trailing garbage after the prefab
+            de.end().map_err(|e| PrefabImportError::from_json_error(e, None, None))?;
+            instances
+        }
+        PrefabFileFormat::Bincode => {
+            let bytes = &bytes[BINCODE_PREFAB_MAGIC.len()..];
+            if bytes.len() < 8 {
+                return Err(PrefabImportError::new("binary prefab is truncated".to_string()));
+            }
+
+            let mut versions_length = [0; 8];
+            versions_length.copy_from_slice(&bytes[..8]);
+            let versions_length = u64::from_le_bytes(versions_length) as usize;
+            let bytes = &bytes[8..];
+            if bytes.len() < versions_length {
+                return Err(PrefabImportError::new("binary prefab is truncated".to_string()));
+            }
+
+            context_deser.data_versions = bincode::deserialize(&bytes[..versions_length])
+                .map_err(|e| {
+                    PrefabImportError::new(format!(
+                        "malformed component schema versions in binary prefab: {}",
+                        e
+                    ))
+                })?;
+
+            let acceptor = DeserializePrefabAcceptor {
+                storage: &context_deser,
+            };
+            let reader = bincode::SliceReader::new(&bytes[versions_length..]);
+            bincode::with_deserializer(reader, acceptor).map_err(|message| PrefabImportError {
+                position: None,
+                entity: context_deser.entity.get(),
+                component_type: context_deser.component_type.get(),
+                message,
+            })?
+        }
+    };
+
+    if let Some(target_prefab) = context_deser.prefab_ref.get() {
+        return Err(PrefabImportError::new(prefab_ref_error(&target_prefab)));
+    }
+
+    let instances = read_instances(instances, &context_deser.data_versions)?;
+    Ok(PrefabAsset {
+        prefab: prefab_deser.prefab(),
+        instances,
+    })
+}
+
+/// Writes a prefab file in the given format, recording the current schema version of the component
+/// types so that the data can be migrated if they change. instances are the prefab's references
+/// to other prefabs (see PrefabAsset::instances)
+pub fn write_prefab(
+    prefab: &Prefab,
+    instances: &HashMap<PrefabInstanceId, PrefabInstance>,
+    format: PrefabFileFormat,
+    prefab_serde_context: &PrefabSerdeContext,
+) -> Result<Vec<u8>, String> {
+    let prefab_ser = legion_prefab::PrefabFormatSerializer::new(prefab_serde_context, prefab);
+    let prefab_file = PrefabFileData {
+        prefab: SerializePrefab {
+            storage: &prefab_ser,
+            prefab_id: prefab.prefab_id(),
+        },
+        instances: write_instances(instances)?,
+    };
+
+    match format {
+        PrefabFileFormat::Ron => {
+            let mut ron_ser =
+                ron::ser::Serializer::new(Some(ron::ser::PrettyConfig::default()), true);
+            prefab_file
+                .serialize(&mut ron_ser)
+                .map_err(|e| format!("failed to serialize prefab: {}", e))?;
+
+            let output = format!(
+                "{}{}",
+                crate::component_schemas::schema_versions_header(),
+                ron_ser.into_output_string()
+            );
+            Ok(output.into_bytes())
+        }
+        PrefabFileFormat::Json => {
+            let mut json_ser = serde_json::Serializer::pretty(Vec::new());
+            prefab_file
+                .serialize(&mut json_ser)
+                .map_err(|e| format!("failed to serialize prefab: {}", e))?;
+            Ok(json_ser.into_inner())
+        }
+        PrefabFileFormat::Bincode => {
+            let versions = current_schema_versions();
+            let versions = bincode::serialize(&versions)
+                .map_err(|e| format!("failed to serialize component schema versions: {}", e))?;
+
+            let mut output = BINCODE_PREFAB_MAGIC.to_vec();
+            output.extend_from_slice(&(versions.len() as u64).to_le_bytes());
+            output.extend_from_slice(&versions);
+
+            let acceptor = SerializePrefabAcceptor {
+                prefab_file: &prefab_file,
+            };
+            bincode::with_serializer(&mut output, acceptor)?;
+            Ok(output)
+        }
+    }
+}
+
+// Runs the prefab file deserializer with a bincode deserializer
+struct DeserializePrefabAcceptor<'a, 'b, 'c> {
+    storage: &'a ImportContextDeserializer<'b, 'c>,
+}
+
+impl<'de, 'a, 'b, 'c> bincode::DeserializerAcceptor<'de>
+    for DeserializePrefabAcceptor<'a, 'b, 'c>
+{
+    type Output = Result<Vec<PrefabInstanceData>, String>;
+
+    fn accept<T: serde::Deserializer<'de>>(
+        self,
+        de: T,
+    ) -> Self::Output {
+        let seed = PrefabFileSeed {
+            storage: self.storage,
+        };
+        seed.deserialize(de).map_err(|e| e.to_string())
+    }
+}
+
+// Runs the prefab file serializer with a bincode serializer
+struct SerializePrefabAcceptor<'a, 'b, 'c, 'd> {
+    prefab_file: &'a PrefabFileData<'b, 'c, 'd>,
+}
+
+impl<'a, 'b, 'c, 'd> bincode::SerializerAcceptor for SerializePrefabAcceptor<'a, 'b, 'c, 'd> {
+    type Output = Result<(), String>;
+
+    fn accept<T: serde::Serializer>(
+        self,
+        ser: T,
+    ) -> Self::Output
+    where
+        T::Ok: 'static,
+    {
+        self.prefab_file
+            .serialize(ser)
+            .map(|_| ())
+            .map_err(|e| e.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TEST_BOX_TYPE: &str = "3b0b7a52-5e64-4f0c-9d1a-7c2f4e8b6a10";
+
+    // Version 2 of the test component renamed `size` to `half_extents`
+    fn migrate_test_box_v1_to_v2(mut value: ComponentValue) -> Result<ComponentValue, String> {
+        match value.remove_field("size") {
+            Some(ComponentValue::String(_)) => Err("size must be a Vec2".to_string()),
+            Some(size) => {
+                value.set_field("half_extents", size);
+                Ok(value)
+            }
+            None => Ok(value),
+        }
+    }
+
+    fn migrate(text: &str) -> Result<String, PrefabImportError> {
+        let component_type = *uuid::Uuid::parse_str(TEST_BOX_TYPE).unwrap().as_bytes();
+        let schema: &'static ComponentSchemaRegistration =
+            Box::leak(Box::new(ComponentSchemaRegistration::new(
+                component_type,
+                "TestBoxComponentDef",
+                2,
+                &[migrate_test_box_v1_to_v2],
+            )));
+
+        let mut component_schemas = HashMap::new();
+        component_schemas.insert(component_type, schema);
+        migrate_ron_prefab(text, &component_schemas, &HashMap::new())
+    }
+
+    fn test_prefab(box_size: &str) -> String {
+        format!(
+            r####"PrefabFile(
+    prefab: Prefab(
+        id: "3991506e-ed7e-4bcb-8cfd-3366b31a6439",
+        objects: [
+            // A box
+            Entity(PrefabEntity(
+                id: "e938c98b-df7e-41d7-a2e4-9f028702b022",
+                components: [
+                    EntityComponent(
+                        type: "{component_type}",
+                        data: TestBoxComponentDef(
+                            size: {box_size},
+                        ),
+                    ),
+                ],
+            )),
+        ],
+    ),
+    instances: [
+        PrefabInstance(
+            id: "9b6f1e32-4c0d-4f7a-8e21-5d3a7c9b0f64",
+            prefab_id: "14dec17f-ae14-40a3-8e44-e487fc423287",
+            entity_overrides: [
+                EntityOverrides(
+                    entity_id: "62b3dbd1-56a8-469e-a262-41a66321da8b",
+                    overrides: [
+                        PatchComponent(
+                            component_type: "{component_type}",
+                            diff: r##"[Enter(Field("size")), Value(Vec2(3.0, 4.0))]"##,
+                        ),
+                        AddComponent(
+                            component_type: "{component_type}",
+                            data: "(size: Vec2(5.0, 6.0))",
+                        ),
+                        RemoveEntity,
+                    ],
+                ),
+            ],
+        ),
+    ],
+)
+"####,
+            component_type = TEST_BOX_TYPE,
+            box_size = box_size
+        )
+    }
+
+    #[test]
+    fn migrates_component_data_and_overrides() {
+        let migrated = migrate(&test_prefab("Vec2(1.0, 2.0)")).unwrap();
+        let document = ComponentValue::parse_spanned(&migrated).unwrap();
+
+        let prefab = document.root().field("prefab").unwrap();
+        let entity = prefab.field("objects").unwrap().items()[0].items()[0];
+        let component = entity.field("components").unwrap().items()[0];
+        assert_eq!(
+            component.field("data").unwrap().value(),
+            &ComponentValue::parse("TestBoxComponentDef(half_extents: Vec2(1.0, 2.0))").unwrap()
+        );
+
+        assert!(
+            migrated.contains(r#"diff: "[Enter(Field(\"half_extents\")), Value(Vec2(3.0, 4.0))]""#)
+        );
+        assert!(migrated.contains(r#"data: "(half_extents: Vec2(5.0, 6.0))""#));
+        assert!(migrated.contains("RemoveEntity,"));
+    }
+
+    #[test]
+    fn keeps_the_line_of_unchanged_text() {
+        let text = test_prefab("Vec2(1.0, 2.0)");
+        let migrated = migrate(&text).unwrap();
+        assert_eq!(migrated.lines().count(), text.lines().count());
+
+        // Text after a migrated value, including comments, stays on the same line
+        let line_of =
+            |text: &str, pattern: &str| text.lines().position(|line| line.contains(pattern));
+        for pattern in &["// A box", "instances: [", "prefab_id:", "RemoveEntity,"] {
+            assert!(line_of(&text, pattern).is_some());
+            assert_eq!(
+                line_of(&text, pattern),
+                line_of(&migrated, pattern),
+                "{}",
+                pattern
+            );
+        }
+    }
+
+    #[test]
+    fn leaves_unchanged_values_alone() {
+        let text = test_prefab("Vec2(1.0, 2.0)").replace("size", "half_extents");
+        assert_eq!(migrate(&text).unwrap(), text);
+    }
+
+    #[test]
+    fn reports_where_migration_failed() {
+        let error = migrate(&test_prefab("\"large\"")).unwrap_err();
+        assert_eq!(error.position, Some((11, 31)));
+        assert_eq!(
+            error.component_type,
+            Some(*uuid::Uuid::parse_str(TEST_BOX_TYPE).unwrap().as_bytes())
+        );
+        assert!(error.message.contains("size must be a Vec2"), "{}", error);
+    }
+
+    #[test]
+    fn recognizes_json_prefabs() {
+        let prefab = br#"{
+            "prefab": { "id": "7c1e5a42-9d3b-4f86-a0e2-5b8d6c4f1a93", "objects": [] },
+            "instances": []
+        }"#;
+        assert!(is_json_prefab(prefab));
+        assert!(is_json_prefab(include_bytes!(
+            "../../../../assets/generated_level.prefab.json"
+        )));
+
+        assert!(!is_json_prefab(br#"{ "name": "not a prefab" }"#));
+        assert!(!is_json_prefab(br#"{ "prefab": "level.prefab" }"#));
+        assert!(!is_json_prefab(br#"[1, 2, 3]"#));
+        assert!(!is_json_prefab(b"not JSON"));
+    }
+
+    fn serde_context() -> PrefabSerdeContext {
+        PrefabSerdeContext {
+            registered_components: crate::create_component_registry_by_uuid(),
+        }
+    }
+
+    // A prefab with one entity, which references another prefab with an override of each kind
+    fn bincode_test_prefab() -> (Prefab, HashMap<PrefabInstanceId, PrefabInstance>) {
+        use legion::prelude::*;
+        use type_uuid::TypeUuid;
+        use crate::components::UniformScale2DComponent;
+
+        let universe = Universe::new();
+        let mut world = universe.create_world();
+        let entity = world.insert((), vec![(UniformScale2DComponent { uniform_scale: 2.0 },)])[0];
+        let mut entities = HashMap::new();
+        entities.insert([1; 16], entity);
+
+        let prefab = Prefab {
+            world,
+            prefab_meta: legion_prefab::PrefabMeta {
+                id: [2; 16],
+                prefab_refs: Default::default(),
+                entities,
+            },
+        };
+
+        let component_type = UniformScale2DComponent::UUID;
+        let mut overrides = HashMap::new();
+        overrides.insert(
+            [3; 16],
+            vec![
+                PrefabOverride::PatchComponent {
+                    component_type,
+                    schema_version: 1,
+                    diff: r#"[ Enter(Field("uniform_scale")), Value(3.0) ]"#.to_string(),
+                },
+                PrefabOverride::RemoveComponent { component_type },
+                PrefabOverride::AddComponent {
+                    component_type,
+                    schema_version: 1,
+                    data: "(uniform_scale: 4.0)".to_string(),
+                },
+            ],
+        );
+        overrides.insert([5; 16], vec![PrefabOverride::RemoveEntity]);
+
+        let mut instances = HashMap::new();
+        instances.insert(
+            [6; 16],
+            PrefabInstance {
+                prefab_id: AssetUuid([7; 16]),
+                overrides,
+            },
+        );
+
+        (prefab, instances)
+    }
+
+    #[test]
+    fn writes_the_bincode_layout() {
+        let (prefab, instances) = bincode_test_prefab();
+        let bytes =
+            write_prefab(&prefab, &instances, PrefabFileFormat::Bincode, &serde_context()).unwrap();
+        assert_eq!(PrefabFileFormat::detect(&bytes), PrefabFileFormat::Bincode);
+
+        // The magic, then the length of the schema versions and the versions themselves
+        assert!(bytes.starts_with(BINCODE_PREFAB_MAGIC));
+        let bytes = &bytes[BINCODE_PREFAB_MAGIC.len()..];
+        let mut versions_length = [0; 8];
+        versions_length.copy_from_slice(&bytes[..8]);
+        let versions_length = u64::from_le_bytes(versions_length) as usize;
+        let versions: HashMap<ComponentTypeUuid, u32> =
+            bincode::deserialize(&bytes[8..8 + versions_length]).unwrap();
+        assert_eq!(versions, current_schema_versions());
+
+        // Followed by the prefab file
+        assert!(bytes.len() > 8 + versions_length);
+    }
+
+    #[test]
+    fn round_trips_bincode_prefabs() {
+        let (prefab, instances) = bincode_test_prefab();
+        let bytes =
+            write_prefab(&prefab, &instances, PrefabFileFormat::Bincode, &serde_context()).unwrap();
+
+        let prefab_asset = read_prefab(&bytes, PrefabFileFormat::Bincode, &serde_context())
+            .unwrap_or_else(|e| panic!("{}", e));
+        assert_eq!(prefab_asset.prefab.prefab_id(), prefab.prefab_id());
+        assert_eq!(
+            prefab_asset.prefab.prefab_meta.entities.keys().collect::<Vec<_>>(),
+            vec![&[1; 16]]
+        );
+        assert_eq!(prefab_asset.instances, instances);
+    }
+
+    #[test]
+    fn rejects_truncated_bincode_prefabs() {
+        let read_error = |bytes: &[u8]| {
+            match read_prefab(bytes, PrefabFileFormat::Bincode, &serde_context()) {
+                Ok(_) => panic!("read a truncated prefab"),
+                Err(e) => e,
+            }
+        };
+
+        // Cut off in the length of the schema versions
+        let mut bytes = BINCODE_PREFAB_MAGIC.to_vec();
+        bytes.extend_from_slice(&[1, 0, 0]);
+        assert!(read_error(&bytes).message.contains("truncated"));
+
+        // Cut off in the schema versions
+        bytes.truncate(BINCODE_PREFAB_MAGIC.len());
+        bytes.extend_from_slice(&100u64.to_le_bytes());
+        bytes.extend_from_slice(&[0; 10]);
+        assert!(read_error(&bytes).message.contains("truncated"));
+
+        // Cut off in the prefab file
+        let (prefab, instances) = bincode_test_prefab();
+        let bytes =
+            write_prefab(&prefab, &instances, PrefabFileFormat::Bincode, &serde_context()).unwrap();
+        read_error(&bytes[..bytes.len() - 1]);
+    }
+}
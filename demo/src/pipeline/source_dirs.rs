@@ -0,0 +1,27 @@
+//! Importers are only given the contents of the file being imported. Importers that combine other
+//! source files (like the texture atlas importer) look them up relative to the directories that
+//! the asset daemon watches
+
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+
+lazy_static::lazy_static! {
+    static ref ASSET_DIRS: RwLock<Vec<PathBuf>> = RwLock::new(Vec::new());
+}
+
+/// Sets the directories that source files referenced by other source files are relative to. The
+/// asset daemon calls this before it starts importing
+pub fn set_asset_dirs(asset_dirs: Vec<PathBuf>) {
+    *ASSET_DIRS.write().unwrap() = asset_dirs;
+}
+
+/// Returns the path of the source file with the given path relative to one of the asset
+/// directories, or None if it isn't in any of them
+pub fn find_source_file(relative_path: &Path) -> Option<PathBuf> {
+    ASSET_DIRS
+        .read()
+        .unwrap()
+        .iter()
+        .map(|asset_dir| asset_dir.join(relative_path))
+        .find(|path| path.is_file())
+}
@@ -1,214 +1,1213 @@
-use crate::resources::AssetResource;
+use crate::resources::{AssetLoader, AssetResource};
 
 use atelier_loader::{
     handle::{AssetHandle, Handle},
-    rpc_loader::RpcLoader,
     LoadStatus, Loader,
 };
 use std::collections::HashMap;
+use std::sync::Arc;
 use legion::prelude::*;
 use crate::clone_merge::SpawnCloneImpl;
 
 use legion::storage::ComponentTypeId;
-use prefab_format::ComponentTypeUuid;
+use prefab_format::{ComponentTypeUuid, EntityUuid};
 use legion_prefab::{ComponentRegistration, CookedPrefab};
-use crate::pipeline::PrefabAsset;
+use crate::pipeline::{PrefabAsset, PrefabOverride};
 use atelier_core::AssetUuid;
 
-pub fn cook_prefab(
+/// Errors that can occur while cooking a prefab
+#[derive(Debug)]
+pub enum CookError {
+    /// A prefab references itself, either directly or through other prefabs. The chain starts and
+    /// ends with the same prefab, i.e. [A, B, A] means A references B and B references A.
+    PrefabCycle(Vec<AssetUuid>),
+
+    /// The prefab could not be loaded (it doesn't exist or failed to load)
+    PrefabLoadFailed(AssetUuid),
+
+    /// PrefabCookJob::cook() was called before the prefab and everything it references loaded
+    NotReadyToCook(AssetUuid),
+
+    /// An override in the given prefab targets an entity that isn't in any referenced prefab
+    MissingEntity {
+        prefab_id: AssetUuid,
+        entity_id: EntityUuid,
+    },
+
+    /// An override in the given prefab targets a component type that isn't registered
+    UnknownComponentType {
+        prefab_id: AssetUuid,
+        entity_id: EntityUuid,
+        component_type: ComponentTypeUuid,
+    },
+
+    /// The diff data for a component override could not be parsed
+    MalformedOverrideDiff {
+        prefab_id: AssetUuid,
+        entity_id: EntityUuid,
+        component_type: ComponentTypeUuid,
+        error: ron::de::Error,
+    },
+
+    /// A component override was written with an older schema version and could not be upgraded
+    OverrideMigrationFailed {
+        prefab_id: AssetUuid,
+        entity_id: EntityUuid,
+        component_type: ComponentTypeUuid,
+        message: String,
+    },
+
+    /// The cooked prefab could not be serialized and read back without changes
+    RoundTripFailed { prefab_id: AssetUuid, message: String },
+}
+
+impl std::error::Error for CookError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match *self {
+            CookError::MalformedOverrideDiff { ref error, .. } => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl core::fmt::Display for CookError {
+    fn fmt(
+        &self,
+        fmt: &mut core::fmt::Formatter,
+    ) -> core::fmt::Result {
+        match *self {
+            CookError::PrefabCycle(ref chain) => {
+                let chain: Vec<_> = chain.iter().map(|id| id.to_string()).collect();
+                write!(fmt, "prefab reference cycle: {}", chain.join(" -> "))
+            }
+            CookError::PrefabLoadFailed(ref prefab_id) => write!(
+                fmt,
+                "failed to load prefab {} (if its file failed to import, the asset daemon's log \
+                 has the file, line and column)",
+                prefab_id
+            ),
+            CookError::NotReadyToCook(ref prefab_id) => write!(
+                fmt,
+                "prefab {} can't be cooked until it and the prefabs it references have loaded",
+                prefab_id
+            ),
+            CookError::MissingEntity {
+                ref prefab_id,
+                ref entity_id,
+            } => write!(
+                fmt,
+                "prefab {} overrides entity {} which does not exist in any referenced prefab",
+                prefab_id,
+                uuid::Uuid::from_bytes(*entity_id)
+            ),
+            CookError::UnknownComponentType {
+                ref prefab_id,
+                ref entity_id,
+                ref component_type,
+            } => write!(
+                fmt,
+                "prefab {} overrides unregistered component type {} on entity {}",
+                prefab_id,
+                uuid::Uuid::from_bytes(*component_type),
+                uuid::Uuid::from_bytes(*entity_id)
+            ),
+            CookError::MalformedOverrideDiff {
+                ref prefab_id,
+                ref entity_id,
+                ref component_type,
+                ref error,
+            } => write!(
+                fmt,
+                "prefab {} has a malformed override for component type {} on entity {}: {}",
+                prefab_id,
+                uuid::Uuid::from_bytes(*component_type),
+                uuid::Uuid::from_bytes(*entity_id),
+                error
+            ),
+            CookError::OverrideMigrationFailed {
+                ref prefab_id,
+                ref entity_id,
+                ref component_type,
+                ref message,
+            } => write!(
+                fmt,
+                "prefab {} has an override for component type {} on entity {} that could not be \
+                 migrated to the current version: {}",
+                prefab_id,
+                uuid::Uuid::from_bytes(*component_type),
+                uuid::Uuid::from_bytes(*entity_id),
+                message
+            ),
+            CookError::RoundTripFailed {
+                ref prefab_id,
+                ref message,
+            } => write!(
+                fmt,
+                "the cooked data for prefab {} does not round-trip: {}",
+                prefab_id, message
+            ),
+        }
+    }
+}
+
+/// Records where the data in a cooked prefab came from. Entities and components are identified by
+/// the entity's UUID in the cooked prefab
+#[derive(Default, Debug, Clone)]
+pub struct CookProvenance {
+    /// The prefab that each cooked entity was defined in
+    entity_sources: HashMap<EntityUuid, AssetUuid>,
+
+    /// The prefabs that wrote each component of each cooked entity, in the order they wrote it.
+    /// The first entry is the prefab that defined (or added) the component, and the last entry is
+    /// the prefab whose data ended up in the cooked prefab
+    component_writers: HashMap<(EntityUuid, ComponentTypeUuid), Vec<AssetUuid>>,
+}
+
+impl CookProvenance {
+    /// The prefab that the given cooked entity was defined in
+    pub fn entity_source(
+        &self,
+        entity_uuid: &EntityUuid,
+    ) -> Option<AssetUuid> {
+        self.entity_sources.get(entity_uuid).cloned()
+    }
+
+    /// The prefabs that wrote the given component of the given cooked entity, in order
+    pub fn component_writers(
+        &self,
+        entity_uuid: &EntityUuid,
+        component_type: &ComponentTypeUuid,
+    ) -> &[AssetUuid] {
+        self.component_writers
+            .get(&(*entity_uuid, *component_type))
+            .map(|x| x.as_slice())
+            .unwrap_or(&[])
+    }
+
+    /// All the component types that were written for the given cooked entity
+    pub fn component_types(
+        &self,
+        entity_uuid: &EntityUuid,
+    ) -> impl Iterator<Item = &ComponentTypeUuid> {
+        let entity_uuid = *entity_uuid;
+        self.component_writers
+            .keys()
+            .filter(move |(e, _)| *e == entity_uuid)
+            .map(|(_, component_type)| component_type)
+    }
+
+    // Adds the provenance of a referenced prefab's cooked data, renaming its entities to the UUIDs
+    // they have in the referencing prefab
+    fn extend_from_instance(
+        &mut self,
+        other: &CookProvenance,
+        instance_id: PrefabInstanceId,
+    ) {
+        for (entity_uuid, source) in &other.entity_sources {
+            self.entity_sources
+                .insert(cooked_entity_uuid(Some(instance_id), *entity_uuid), *source);
+        }
+
+        for ((entity_uuid, component_type), writers) in &other.component_writers {
+            self.component_writers.insert(
+                (cooked_entity_uuid(Some(instance_id), *entity_uuid), *component_type),
+                writers.clone(),
+            );
+        }
+    }
+
+    fn record_entity(
+        &mut self,
+        entity_uuid: EntityUuid,
+        prefab_id: AssetUuid,
+    ) {
+        self.entity_sources.insert(entity_uuid, prefab_id);
+    }
+
+    fn record_component_write(
+        &mut self,
+        entity_uuid: EntityUuid,
+        component_type: ComponentTypeUuid,
+        prefab_id: AssetUuid,
+    ) {
+        self.component_writers
+            .entry((entity_uuid, component_type))
+            .or_default()
+            .push(prefab_id);
+    }
+
+    fn remove_component(
+        &mut self,
+        entity_uuid: &EntityUuid,
+        component_type: &ComponentTypeUuid,
+    ) {
+        self.component_writers.remove(&(*entity_uuid, *component_type));
+    }
+
+    fn remove_entity(
+        &mut self,
+        entity_uuid: &EntityUuid,
+    ) {
+        self.entity_sources.remove(entity_uuid);
+        self.component_writers.retain(|(e, _), _| e != entity_uuid);
+    }
+}
+
+/// Loads a prefab and all the prefabs it references without blocking. Call update() once per
+/// frame (after the asset manager updates) until is_ready_to_cook() returns true, then call cook()
+pub struct PrefabCookJob {
+    /// The prefab that will be cooked
+    prefab_uuid: AssetUuid,
+
+    /// Prefabs that have been requested but have not finished loading
+    pending: HashMap<AssetUuid, Handle<PrefabAsset>>,
+
+    /// Prefabs that have finished loading
+    loaded: HashMap<AssetUuid, Handle<PrefabAsset>>,
+}
+
+impl PrefabCookJob {
+    pub fn new(
+        asset_manager: &AssetResource,
+        prefab_uuid: AssetUuid,
+    ) -> Self {
+        let mut job = PrefabCookJob {
+            prefab_uuid,
+            pending: Default::default(),
+            loaded: Default::default(),
+        };
+
+        job.request_prefab(asset_manager, prefab_uuid);
+        job
+    }
+
+    pub fn prefab_uuid(&self) -> AssetUuid {
+        self.prefab_uuid
+    }
+
+    /// Handle to the prefab being cooked. This will be None until it has loaded
+    pub fn prefab_handle(&self) -> Option<&Handle<PrefabAsset>> {
+        self.loaded.get(&self.prefab_uuid)
+    }
+
+    /// Handles to the prefab and every prefab it references that has finished loading
+    pub fn loaded_prefabs(&self) -> &HashMap<AssetUuid, Handle<PrefabAsset>> {
+        &self.loaded
+    }
+
+    /// Number of prefabs that have finished loading
+    pub fn loaded_prefab_count(&self) -> usize {
+        self.loaded.len()
+    }
+
+    /// Number of prefabs that have been requested, including the ones that have finished loading.
+    /// This can grow as loaded prefabs reveal more references
+    pub fn requested_prefab_count(&self) -> usize {
+        self.loaded.len() + self.pending.len()
+    }
+
+    /// Returns true when the prefab and everything it references has loaded
+    pub fn is_ready_to_cook(&self) -> bool {
+        self.pending.is_empty()
+    }
+
+    fn request_prefab(
+        &mut self,
+        asset_manager: &AssetResource,
+        id: AssetUuid,
+    ) {
+        if self.pending.contains_key(&id) || self.loaded.contains_key(&id) {
+            return;
+        }
+
+        let load_handle = asset_manager.loader().add_ref(id);
+        let handle = Handle::<PrefabAsset>::new(asset_manager.tx().clone(), load_handle);
+        self.pending.insert(id, handle);
+    }
+
+    /// Checks on all pending prefabs. Any that have loaded will have the prefabs they reference
+    /// requested
+    pub fn update(
+        &mut self,
+        asset_manager: &AssetResource,
+    ) -> Result<(), CookError> {
+        let mut newly_loaded = vec![];
+        for (id, handle) in &self.pending {
+            match handle.load_status::<AssetLoader>(asset_manager.loader()) {
+                LoadStatus::Loaded => newly_loaded.push(*id),
+                LoadStatus::DoesNotExist | LoadStatus::Error(_) => {
+                    return Err(CookError::PrefabLoadFailed(*id))
+                }
+                _ => {}
+            }
+        }
+
+        for id in newly_loaded {
+            let handle = self.pending.remove(&id).unwrap();
+
+            // Get a list of prefabs this asset references. We clone these into a new list due to borrowing restrictions
+            let other_prefab_ids: Vec<_> = {
+                let prefab_asset: &PrefabAsset = handle
+                    .asset(asset_manager.storage())
+                    .ok_or(CookError::PrefabLoadFailed(id))?;
+
+                prefab_asset
+                    .instances
+                    .values()
+                    .map(|instance| instance.prefab_id)
+                    .collect()
+            };
+
+            for other_prefab_id in other_prefab_ids {
+                self.request_prefab(asset_manager, other_prefab_id);
+            }
+
+            self.loaded.insert(id, handle);
+        }
+
+        Ok(())
+    }
+
+    /// Cooks the loaded prefabs. Returns CookError::NotReadyToCook if is_ready_to_cook() is false.
+    /// Prefabs that are in the cache with the same version are not cooked again. If provenance is
+    /// provided, it's populated with where the cooked data came from
+    pub fn cook(
+        &self,
+        universe: &Universe,
+        asset_manager: &AssetResource,
+        registered_components: &HashMap<ComponentTypeId, ComponentRegistration>,
+        registered_components_by_uuid: &HashMap<ComponentTypeUuid, ComponentRegistration>,
+        cache: &mut PrefabCookCache,
+        provenance: Option<&mut CookProvenance>,
+    ) -> Result<CookedPrefab, CookError> {
+        if !self.is_ready_to_cook() {
+            return Err(CookError::NotReadyToCook(self.prefab_uuid));
+        }
+
+        let mut prefabs = HashMap::with_capacity(self.loaded.len());
+        for (prefab_id, prefab_handle) in &self.loaded {
+            let prefab_asset: &PrefabAsset = prefab_handle
+                .asset(asset_manager.storage())
+                .ok_or(CookError::PrefabLoadFailed(*prefab_id))?;
+            let version = prefab_handle
+                .asset_version::<PrefabAsset, _>(asset_manager.storage())
+                .ok_or(CookError::PrefabLoadFailed(*prefab_id))?;
+            prefabs.insert(*prefab_id, (prefab_asset, version));
+        }
+
+        cook_prefab_with_cache(
+            universe,
+            registered_components,
+            registered_components_by_uuid,
+            self.prefab_uuid,
+            &prefabs,
+            cache,
+            provenance,
+        )
+    }
+}
+
+/// Cooks a prefab using already-loaded prefab data, reusing the cooked data in cache for any prefab
+/// whose version (and the versions of everything it references) hasn't changed. prefabs must
+/// contain the prefab and every prefab it references, directly or indirectly, along with the
+/// version of each. If provenance is provided, it's populated with where the cooked data came from
+pub fn cook_prefab_with_cache(
     universe: &Universe,
-    asset_manager: &mut AssetResource,
     registered_components: &HashMap<ComponentTypeId, ComponentRegistration>,
     registered_components_by_uuid: &HashMap<ComponentTypeUuid, ComponentRegistration>,
     prefab_uuid: AssetUuid,
-) -> CookedPrefab {
+    prefabs: &HashMap<AssetUuid, (&PrefabAsset, u32)>,
+    cache: &mut PrefabCookCache,
+    provenance: Option<&mut CookProvenance>,
+) -> Result<CookedPrefab, CookError> {
+    // Find the prefabs that each prefab references
+    let mut references = HashMap::with_capacity(prefabs.len());
+    for (prefab_id, (prefab_asset, _)) in prefabs {
+        let other_prefab_ids: Vec<_> = prefab_asset
+            .instances
+            .values()
+            .map(|instance| instance.prefab_id)
+            .collect();
+        references.insert(*prefab_id, other_prefab_ids);
+    }
+
+    // This will hold the asset IDs sorted with dependencies first. Building it ensures that there
+    // are no cycles and that every referenced prefab is available
+    let mut prefab_cook_order = vec![];
+    visit_prefab_references(
+        prefab_uuid,
+        &references,
+        &mut prefab_cook_order,
+        &mut vec![],
+    )?;
+
+    for id in &prefab_cook_order {
+        log::trace!("prefabs_in_cook_order: {}", id);
+    }
+
     let resources = Resources::default();
 
     // Create the clone_merge impl. For prefab cooking, we will clone everything so we don't need to
     // set up any transformations
-    let clone_merge_impl = SpawnCloneImpl::new(registered_components.clone(), &resources);
+    let clone_merge_impl = SpawnCloneImpl::new(registered_components, &resources);
 
-    // This will allow us to look up prefabs by AssetUuid
-    let mut prefab_lookup = HashMap::new();
+    // Cook each prefab, starting with the ones that don't reference anything. Each prefab is built
+    // from the cooked data of the prefabs it references, so a prefab only needs to be cooked again
+    // if it or something it references changed
+    let mut cooked_prefabs = HashMap::with_capacity(prefab_cook_order.len());
+    for prefab_id in &prefab_cook_order {
+        let (prefab_asset, version) = *prefabs
+            .get(prefab_id)
+            .ok_or(CookError::PrefabLoadFailed(*prefab_id))?;
 
-    // This will allow us to look up the cooked entity ID by the entity's original UUID
-    let mut entity_lookup = HashMap::new();
+        let cooked = match cache.get(*prefab_id, version, &cooked_prefabs) {
+            Some(cooked) => {
+                log::trace!("Reusing cooked data for prefab {} version {}", prefab_id, version);
+                cooked
+            }
+            None => {
+                log::debug!("Cooking prefab {} version {}", prefab_id, version);
+                let cooked = Arc::new(cook_single_prefab(
+                    universe,
+                    &clone_merge_impl,
+                    registered_components_by_uuid,
+                    *prefab_id,
+                    version,
+                    prefab_asset,
+                    &cooked_prefabs,
+                )?);
+                cache.insert(*prefab_id, cooked.clone());
+                cooked
+            }
+        };
 
-    // This will hold the asset IDs sorted with dependencies first. This ensures that
-    // prefab_lookup and entity_lookup are populated with all dependent prefabs/entities
-    let mut prefab_cook_order = vec![];
+        cooked_prefabs.insert(*prefab_id, cooked);
+    }
 
-    // Recursively do a blocking load on the prefab and the other prefabs it depends on. This
-    // populates prefab_lookup and prefab_cook_order
-    request_prefab_dependency(
-        asset_manager,
-        prefab_uuid,
-        &mut prefab_lookup,
-        &mut prefab_cook_order,
+    // Copy the cooked prefab out of the cache
+    let cooked = &cooked_prefabs[&prefab_uuid];
+    let mut world = universe.create_world();
+    let mut result_mappings = HashMap::new();
+    world.clone_from(
+        &cooked.cooked_prefab.world,
+        &clone_merge_impl,
+        None,
+        Some(&mut result_mappings),
     );
 
-    for id in &prefab_cook_order {
-        log::trace!("prefabs_in_cook_order: {}", id);
+    let entities = cooked
+        .cooked_prefab
+        .entities
+        .iter()
+        .map(|(entity_uuid, entity)| (*entity_uuid, result_mappings[entity]))
+        .collect();
+
+    if let Some(provenance) = provenance {
+        *provenance = cooked.provenance.clone();
+    }
+
+    // the resulting world can now be saved
+    let cooked_prefab = legion_prefab::CookedPrefab { world, entities };
+
+    // Verify that the data can properly round-trip
+    {
+        let round_trip_failed = |message: String| CookError::RoundTripFailed {
+            prefab_id: prefab_uuid,
+            message,
+        };
+
+        let cooked_prefab_string =
+            ron::ser::to_string_pretty(&cooked_prefab, ron::ser::PrettyConfig::default())
+                .map_err(|e| round_trip_failed(e.to_string()))?;
+
+        let restored = ron::de::from_str::<legion_prefab::CookedPrefab>(&cooked_prefab_string)
+            .map_err(|e| round_trip_failed(e.to_string()))?;
+
+        let cooked_prefab_string2 =
+            ron::ser::to_string_pretty(&restored, ron::ser::PrettyConfig::default())
+                .map_err(|e| round_trip_failed(e.to_string()))?;
+
+        if cooked_prefab_string != cooked_prefab_string2 {
+            return Err(round_trip_failed(
+                "the cooked data changed when it was read back".to_string(),
+            ));
+        }
+
+        log::trace!("{}", cooked_prefab_string2);
+    }
+
+    Ok(cooked_prefab)
+}
+
+/// Does a blocking load on the prefab and all prefabs that it references, then cooks it. If
+/// provenance is provided, it's populated with where the cooked data came from
+pub fn cook_prefab(
+    universe: &Universe,
+    asset_manager: &mut AssetResource,
+    registered_components: &HashMap<ComponentTypeId, ComponentRegistration>,
+    registered_components_by_uuid: &HashMap<ComponentTypeUuid, ComponentRegistration>,
+    prefab_uuid: AssetUuid,
+    provenance: Option<&mut CookProvenance>,
+) -> Result<CookedPrefab, CookError> {
+    let mut cook_job = PrefabCookJob::new(asset_manager, prefab_uuid);
+
+    // Block until everything loads
+    while !cook_job.is_ready_to_cook() {
+        asset_manager.update();
+        cook_job.update(asset_manager)?;
+    }
+
+    cook_job.cook(
+        universe,
+        asset_manager,
+        registered_components,
+        registered_components_by_uuid,
+        &mut PrefabCookCache::default(),
+        provenance,
+    )
+}
+
+/// Identifies one reference to another prefab within the prefab that references it (see
+/// PrefabAsset::instances). The ID is written in the prefab file so that it stays the same when
+/// other references are added, removed or reordered. Entities are renamed at every level of
+/// referencing (see cooked_entity_uuid), so each instance of a prefab gets its own entity UUIDs
+pub type PrefabInstanceId = [u8; 16];
+
+/// Returns the UUID that an entity from the given prefab instance has in the prefab that references
+/// it. entity_uuid is the entity's UUID in the referenced prefab's cooked data, so for entities
+/// nested several references deep this is applied once per level. Entities that belong to the
+/// prefab being cooked (instance_id is None) keep their UUID
+pub fn cooked_entity_uuid(
+    instance_id: Option<PrefabInstanceId>,
+    entity_uuid: EntityUuid,
+) -> EntityUuid {
+    match instance_id {
+        Some(instance_id) => {
+            *uuid::Uuid::new_v5(&uuid::Uuid::from_bytes(instance_id), &entity_uuid).as_bytes()
+        }
+        None => entity_uuid,
     }
+}
+
+// The cooked data for a single prefab, including the prefabs it references. Prefabs that reference
+// this one are cooked by copying this data and applying their overrides to it
+struct CachedPrefabCook {
+    // The version of the prefab asset that was cooked
+    version: u32,
+
+    // The cooked data of the prefabs this prefab references at the time it was cooked. If any of
+    // these have been replaced in the cache, this needs to be cooked again
+    dependencies: Vec<Arc<CachedPrefabCook>>,
+
+    // The cooked data. Entity UUIDs are the ones the entities have in this prefab, and are what
+    // overrides in prefabs that reference this one use to find them
+    cooked_prefab: CookedPrefab,
+
+    // Where the cooked data came from
+    provenance: CookProvenance,
+}
+
+/// Holds cooked prefabs keyed by prefab UUID and version so that when a prefab changes, only the
+/// prefabs that reference it (directly or indirectly) need to be cooked again
+#[derive(Default)]
+pub struct PrefabCookCache {
+    cooked_prefabs: HashMap<(AssetUuid, u32), Arc<CachedPrefabCook>>,
+}
+
+impl PrefabCookCache {
+    /// Number of cooked prefabs in the cache
+    pub fn len(&self) -> usize {
+        self.cooked_prefabs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cooked_prefabs.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.cooked_prefabs.clear();
+    }
+
+    // Returns the cooked data for the given version of the prefab if it was cooked from the same
+    // data as what is currently in cooked_dependencies
+    fn get(
+        &self,
+        prefab_id: AssetUuid,
+        version: u32,
+        cooked_dependencies: &HashMap<AssetUuid, Arc<CachedPrefabCook>>,
+    ) -> Option<Arc<CachedPrefabCook>> {
+        let cached = self.cooked_prefabs.get(&(prefab_id, version))?;
+        let dependencies_unchanged = cached.dependencies.iter().all(|dependency| {
+            cooked_dependencies
+                .values()
+                .any(|cooked_dependency| Arc::ptr_eq(dependency, cooked_dependency))
+        });
+
+        if dependencies_unchanged {
+            Some(cached.clone())
+        } else {
+            None
+        }
+    }
+
+    // Adds the cooked data, replacing any other version of the same prefab
+    fn insert(
+        &mut self,
+        prefab_id: AssetUuid,
+        cooked: Arc<CachedPrefabCook>,
+    ) {
+        self.cooked_prefabs.retain(|(id, _), _| *id != prefab_id);
+        self.cooked_prefabs.insert((prefab_id, cooked.version), cooked);
+    }
+}
+
+// An entity within a prefab instance
+#[derive(Clone, Copy)]
+struct InstanceEntity {
+    cooked_entity: Entity,
+    cooked_uuid: EntityUuid,
+}
+
+// Cooks a single prefab. The prefabs it references must already be cooked and in
+// cooked_dependencies. Their cooked data is copied into a new world, then the overrides in this
+// prefab are applied to it. This way "base" prefab data is overridden by the prefabs that reference
+// it. Finally the entities in this prefab are copied in
+fn cook_single_prefab(
+    universe: &Universe,
+    clone_merge_impl: &SpawnCloneImpl,
+    registered_components_by_uuid: &HashMap<ComponentTypeUuid, ComponentRegistration>,
+    prefab_id: AssetUuid,
+    version: u32,
+    prefab_asset: &PrefabAsset,
+    cooked_dependencies: &HashMap<AssetUuid, Arc<CachedPrefabCook>>,
+) -> Result<CachedPrefabCook, CookError> {
+    let prefab = &prefab_asset.prefab;
 
     // Create a new world to hold the cooked data
     let mut world = universe.create_world();
 
-    // merge all entity data from all prefabs. This data doesn't include any overrides, so order
-    // doesn't matter
-    for (_, prefab_handle) in &prefab_lookup {
-        let prefab_asset: &PrefabAsset = prefab_handle.asset(asset_manager.storage()).unwrap();
+    // This will allow us to look up the cooked entity ID by the entity's cooked UUID
+    let mut entity_lookup = HashMap::new();
+
+    let mut provenance = CookProvenance::default();
+    let mut dependencies = Vec::with_capacity(prefab_asset.instances.len());
+    let component_schemas = crate::component_schemas::component_schemas();
 
+    // Iterate all the other prefabs that this prefab references. Each reference is a separate
+    // instance with its own copy of the referenced prefab's entities
+    log::trace!("Iterating prefabs referenced by prefab {}", prefab_id);
+    for (dependency_instance_id, dependency_instance) in &prefab_asset.instances {
+        let dependency_prefab_id = dependency_instance.prefab_id;
+        let dependency_instance_id = *dependency_instance_id;
         log::trace!(
-            "Cloning entities from prefab {}",
-            AssetUuid(prefab_asset.prefab.prefab_meta.id)
+            "Processing reference to prefab {} (instance {})",
+            dependency_prefab_id,
+            uuid::Uuid::from_bytes(dependency_instance_id)
         );
-        log::trace!("{:#?}", prefab_asset.prefab.prefab_meta.entities);
 
-        // Clone all the entities from the prefab into the cooked world. As the data is copied,
-        // entity will get a new Entity assigned to it in the cooked world. result_mappings will
-        // be populated as this happens so that we can trace where data in the prefab landed in
-        // the cooked world
+        let dependency = cooked_dependencies
+            .get(&dependency_prefab_id)
+            .ok_or(CookError::PrefabLoadFailed(dependency_prefab_id))?;
+
+        // Copy the referenced prefab's cooked data into the world. As the data is copied, each
+        // entity gets a new Entity assigned to it. result_mappings will be populated as this
+        // happens so that we can trace where the data landed
         let mut result_mappings = HashMap::new();
         world.clone_from(
-            &prefab_asset.prefab.world,
-            &clone_merge_impl,
+            &dependency.cooked_prefab.world,
+            clone_merge_impl,
             None,
             Some(&mut result_mappings),
         );
 
-        // Iterate the entities in this prefab. Determine where they are stored in the cooked
-        // world and store this in entity_lookup
-        for (entity_uuid, prefab_entity) in &prefab_asset.prefab.prefab_meta.entities {
-            let cooked_entity = result_mappings[prefab_entity];
-            entity_lookup.insert(*entity_uuid, cooked_entity);
-            log::trace!(
-                "entity {} ({:?}) will be {:?} in cooked data",
-                uuid::Uuid::from_bytes(*entity_uuid),
-                prefab_entity,
-                cooked_entity
+        // Find the entities of this instance, keyed by their UUID in the referenced prefab's cooked
+        // data, which is what overrides use
+        let mut dependency_entities =
+            HashMap::with_capacity(dependency.cooked_prefab.entities.len());
+        for (dependency_entity_uuid, dependency_entity) in &dependency.cooked_prefab.entities {
+            let cooked_entity = result_mappings[dependency_entity];
+            let cooked_uuid =
+                cooked_entity_uuid(Some(dependency_instance_id), *dependency_entity_uuid);
+            dependency_entities.insert(
+                *dependency_entity_uuid,
+                InstanceEntity {
+                    cooked_entity,
+                    cooked_uuid,
+                },
             );
         }
-    }
 
-    // apply component override data. iteration of prefabs is in order such that "base" prefabs
-    // are processed first
-    for prefab_id in &prefab_cook_order {
-        // fetch the data for the prefab
-        let prefab_handle = &prefab_lookup[prefab_id];
-        let prefab_asset: &PrefabAsset = prefab_handle.asset(asset_manager.storage()).unwrap();
+        provenance.extend_from_instance(&dependency.provenance, dependency_instance_id);
 
-        // Iterate all the other prefabs that this prefab references
-        log::trace!(
-            "Iterating prefabs referenced by prefab {}",
-            uuid::Uuid::from_bytes(prefab_asset.prefab.prefab_meta.id)
-        );
-        for (dependency_prefab_id, dependency_prefab_ref) in
-            &prefab_asset.prefab.prefab_meta.prefab_refs
-        {
-            // Iterate all the entities for which we have override data
+        // Iterate all the entities for which we have override data
+        for (entity_id, prefab_overrides) in &dependency_instance.overrides {
             log::trace!(
-                "Processing reference to prefab {}",
-                uuid::Uuid::from_bytes(*dependency_prefab_id)
+                "Processing referenced entity {}",
+                uuid::Uuid::from_bytes(*entity_id)
             );
-            for (entity_id, component_overrides) in &dependency_prefab_ref.overrides {
-                log::trace!(
-                    "Processing referenced entity {}",
-                    uuid::Uuid::from_bytes(*entity_id)
-                );
-
-                // Find where this entity is stored within the cooked data
-                let cooked_entity = entity_lookup[entity_id];
-                log::trace!("This entity is stored at {:?}", cooked_entity);
-
-                // Iterate all the component types for which we have override data
-                for component_override in component_overrides {
-                    log::trace!(
-                        "processing component type {}",
-                        uuid::Uuid::from_bytes(component_override.component_type)
-                    );
-                    let component_registration =
-                        &registered_components_by_uuid[&component_override.component_type];
-
-                    let mut deserializer =
-                        ron::de::Deserializer::from_str(&component_override.data).unwrap();
-
-                    let mut de = erased_serde::Deserializer::erase(&mut deserializer);
-                    component_registration.apply_diff(&mut de, &mut world, cooked_entity);
+
+            // Find where this entity is stored within the cooked data
+            let instance_entity =
+                *dependency_entities
+                    .get(entity_id)
+                    .ok_or_else(|| CookError::MissingEntity {
+                        prefab_id,
+                        entity_id: *entity_id,
+                    })?;
+            let cooked_entity = instance_entity.cooked_entity;
+            log::trace!("This entity is stored at {:?}", cooked_entity);
+
+            let find_registration = |component_type: &ComponentTypeUuid| {
+                registered_components_by_uuid
+                    .get(component_type)
+                    .ok_or_else(|| CookError::UnknownComponentType {
+                        prefab_id,
+                        entity_id: *entity_id,
+                        component_type: *component_type,
+                    })
+            };
+
+            let malformed_override =
+                |component_type: &ComponentTypeUuid, error: ron::de::Error| {
+                    CookError::MalformedOverrideDiff {
+                        prefab_id,
+                        entity_id: *entity_id,
+                        component_type: *component_type,
+                        error,
+                    }
+                };
+
+            // Overrides are stored with the schema version they were written with, so ones from
+            // before a component type changed are upgraded before they're applied
+            let migration_failed = |component_type: &ComponentTypeUuid, message: String| {
+                CookError::OverrideMigrationFailed {
+                    prefab_id,
+                    entity_id: *entity_id,
+                    component_type: *component_type,
+                    message,
+                }
+            };
+
+            // Apply the overrides for this entity in the order they were written
+            let mut remove_entity = false;
+            for prefab_override in prefab_overrides {
+                log::trace!("processing override {:?}", prefab_override);
+
+                match prefab_override {
+                    PrefabOverride::PatchComponent {
+                        component_type,
+                        schema_version,
+                        diff,
+                    } => {
+                        let component_registration = find_registration(component_type)?;
+                        let diff = match component_schemas.get(component_type) {
+                            Some(schema) => schema
+                                .migrate_diff(*schema_version, diff)
+                                .map_err(|message| migration_failed(component_type, message))?,
+                            None => diff.clone(),
+                        };
+
+                        let mut deserializer = ron::de::Deserializer::from_str(&diff)
+                            .map_err(|error| malformed_override(component_type, error))?;
+                        let mut de = erased_serde::Deserializer::erase(&mut deserializer);
+                        component_registration.apply_diff(&mut de, &mut world, cooked_entity);
+
+                        provenance.record_component_write(
+                            instance_entity.cooked_uuid,
+                            *component_type,
+                            prefab_id,
+                        );
+                    }
+                    PrefabOverride::AddComponent {
+                        component_type,
+                        schema_version,
+                        data,
+                    } => {
+                        let component_registration = find_registration(component_type)?;
+                        let data = match component_schemas.get(component_type) {
+                            Some(schema) => schema
+                                .migrate_ron(*schema_version, data)
+                                .map_err(|message| migration_failed(component_type, message))?,
+                            None => data.clone(),
+                        };
+
+                        let mut deserializer = ron::de::Deserializer::from_str(&data)
+                            .map_err(|error| malformed_override(component_type, error))?;
+                        let mut de = erased_serde::Deserializer::erase(&mut deserializer);
+                        component_registration.deserialize_single(
+                            &mut de,
+                            &mut world,
+                            cooked_entity,
+                        );
+
+                        provenance.record_component_write(
+                            instance_entity.cooked_uuid,
+                            *component_type,
+                            prefab_id,
+                        );
+                    }
+                    PrefabOverride::RemoveComponent { component_type } => {
+                        let component_registration = find_registration(component_type)?;
+
+                        component_registration.remove_from_entity(&mut world, cooked_entity);
+
+                        provenance.remove_component(&instance_entity.cooked_uuid, component_type);
+                    }
+                    PrefabOverride::RemoveEntity => {
+                        // Deleted after all the other overrides for this entity are processed
+                        remove_entity = true;
+                    }
                 }
             }
+
+            if remove_entity {
+                log::trace!("Removing entity {:?}", cooked_entity);
+                world.delete(cooked_entity);
+                dependency_entities.remove(entity_id);
+                provenance.remove_entity(&instance_entity.cooked_uuid);
+            }
+        }
+
+        for instance_entity in dependency_entities.values() {
+            entity_lookup.insert(instance_entity.cooked_uuid, instance_entity.cooked_entity);
         }
+
+        dependencies.push(dependency.clone());
     }
 
-    // the resulting world can now be saved
-    let cooked_prefab = legion_prefab::CookedPrefab {
-        world: world,
-        entities: entity_lookup,
-    };
+    log::trace!("Cloning entities from prefab {}", prefab_id);
+    log::trace!("{:#?}", prefab.prefab_meta.entities);
 
-    // Verify that the data can properly round-trip
-    {
-        let cooked_prefab_string =
-            ron::ser::to_string_pretty(&cooked_prefab, ron::ser::PrettyConfig::default()).unwrap();
+    // Clone all the entities from the prefab into the cooked world
+    let mut result_mappings = HashMap::new();
+    world.clone_from(
+        &prefab.world,
+        clone_merge_impl,
+        None,
+        Some(&mut result_mappings),
+    );
 
-        let restored =
-            ron::de::from_str::<legion_prefab::CookedPrefab>(&cooked_prefab_string).unwrap();
+    // Iterate the entities in this prefab. Determine where they are stored in the cooked
+    // world and store this in entity_lookup
+    for (entity_uuid, prefab_entity) in &prefab.prefab_meta.entities {
+        let cooked_entity = result_mappings[prefab_entity];
+        entity_lookup.insert(*entity_uuid, cooked_entity);
 
-        let cooked_prefab_string2 =
-            ron::ser::to_string_pretty(&restored, ron::ser::PrettyConfig::default()).unwrap();
+        // Record that this prefab defined the entity and all of its components
+        provenance.record_entity(*entity_uuid, prefab_id);
+        for (component_type, component_registration) in registered_components_by_uuid {
+            if prefab
+                .world
+                .has_component_by_id(*prefab_entity, component_registration.component_type_id())
+            {
+                provenance.record_component_write(*entity_uuid, *component_type, prefab_id);
+            }
+        }
 
-        assert_eq!(cooked_prefab_string, cooked_prefab_string2);
-        log::trace!("{}", cooked_prefab_string2);
+        log::trace!(
+            "entity {} ({:?}) will be {:?} in cooked data",
+            uuid::Uuid::from_bytes(*entity_uuid),
+            prefab_entity,
+            cooked_entity
+        );
     }
 
-    cooked_prefab
+    Ok(CachedPrefabCook {
+        version,
+        dependencies,
+        cooked_prefab: CookedPrefab {
+            world,
+            entities: entity_lookup,
+        },
+        provenance,
+    })
 }
 
-// This function does a recursive blocking load on the provided prefab asset and all prefabs
-// that it references. As it does this, prefab_lookup and prefab_cook_order are populated
-fn request_prefab_dependency(
-    asset_manager: &mut AssetResource,
+// Visits the references of the given prefab recursively, pushing prefabs onto prefab_cook_order
+// after the prefabs they reference so that ancestor prefab data gets processed first.
+// prefab_stack holds the chain of prefabs currently being visited and is used to detect cycles
+fn visit_prefab_references(
     id: AssetUuid,
-    prefab_lookup: &mut HashMap<AssetUuid, Handle<PrefabAsset>>,
+    references: &HashMap<AssetUuid, Vec<AssetUuid>>,
     prefab_cook_order: &mut Vec<AssetUuid>,
-) {
-    // Request the asset
-    let load_handle = asset_manager.loader().add_ref(id);
-    let handle = Handle::<PrefabAsset>::new(asset_manager.tx().clone(), load_handle);
+    prefab_stack: &mut Vec<AssetUuid>,
+) -> Result<(), CookError> {
+    prefab_stack.push(id);
+    for other_prefab_id in references.get(&id).into_iter().flatten() {
+        // If the referenced prefab is already being visited, following it would recurse forever
+        if let Some(index) = prefab_stack.iter().position(|x| x == other_prefab_id) {
+            let mut chain = prefab_stack[index..].to_vec();
+            chain.push(*other_prefab_id);
+            return Err(CookError::PrefabCycle(chain));
+        }
 
-    // Block until it loads
-    loop {
-        asset_manager.update();
-        if let LoadStatus::Loaded = handle.load_status::<RpcLoader>(asset_manager.loader()) {
-            break;
+        if !prefab_cook_order.contains(other_prefab_id) {
+            visit_prefab_references(
+                *other_prefab_id,
+                references,
+                prefab_cook_order,
+                prefab_stack,
+            )?;
         }
     }
+    prefab_stack.pop();
+
+    // Write data.. this needs to happen after we visit prefabs that we reference
+    prefab_cook_order.push(id);
+    Ok(())
+}
 
-    // Grab a reference to the asset
-    let prefab_asset: &PrefabAsset = handle.asset(asset_manager.storage()).unwrap();
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    // Get a list of prefabs this asset references. We clone these into a new list due to borrowing restrictions
-    let other_prefab_ids: Vec<_> = prefab_asset
-        .prefab
-        .prefab_meta
-        .prefab_refs
-        .iter()
-        .map(|(other_prefab_id, _)| AssetUuid(other_prefab_id.clone()))
-        .collect();
+    fn prefab_id(n: u8) -> AssetUuid {
+        AssetUuid([n; 16])
+    }
 
-    // Use recursion to visit the tree ensuring that ancestor prefab data gets processed first
-    for other_prefab_id in other_prefab_ids {
-        if !prefab_lookup.contains_key(&other_prefab_id) {
-            request_prefab_dependency(
-                asset_manager,
-                other_prefab_id,
-                prefab_lookup,
-                prefab_cook_order,
-            );
+    // Returns the cook order for prefab 1, given (prefab, referenced prefabs) pairs
+    fn cook_order(references: &[(u8, &[u8])]) -> Result<Vec<AssetUuid>, CookError> {
+        let references: HashMap<_, _> = references
+            .iter()
+            .map(|(id, others)| {
+                let others: Vec<_> = others.iter().map(|x| prefab_id(*x)).collect();
+                (prefab_id(*id), others)
+            })
+            .collect();
+
+        let mut prefab_cook_order = vec![];
+        let mut prefab_stack = vec![];
+        visit_prefab_references(
+            prefab_id(1),
+            &references,
+            &mut prefab_cook_order,
+            &mut prefab_stack,
+        )?;
+        assert!(prefab_stack.is_empty());
+        Ok(prefab_cook_order)
+    }
+
+    fn expect_cycle(
+        result: Result<Vec<AssetUuid>, CookError>,
+        expected_chain: &[u8],
+    ) {
+        match result {
+            Err(CookError::PrefabCycle(chain)) => {
+                let expected_chain: Vec<_> =
+                    expected_chain.iter().map(|x| prefab_id(*x)).collect();
+                assert_eq!(chain, expected_chain);
+            }
+            other => panic!("expected a cycle, got {:?}", other),
         }
     }
 
-    // Write data.. this needs to happen after we visit prefabs that we reference
-    prefab_lookup.insert(id, handle);
-    prefab_cook_order.push(id);
+    #[test]
+    fn cooks_referenced_prefabs_first() {
+        let order = cook_order(&[(1, &[2, 3]), (2, &[4]), (3, &[4]), (4, &[])]).unwrap();
+        assert_eq!(order, vec![prefab_id(4), prefab_id(2), prefab_id(3), prefab_id(1)]);
+    }
+
+    #[test]
+    fn cooks_a_prefab_without_references() {
+        assert_eq!(cook_order(&[]).unwrap(), vec![prefab_id(1)]);
+    }
+
+    #[test]
+    fn detects_a_prefab_that_references_itself() {
+        expect_cycle(cook_order(&[(1, &[1])]), &[1, 1]);
+    }
+
+    #[test]
+    fn detects_a_cycle_through_other_prefabs() {
+        expect_cycle(cook_order(&[(1, &[2]), (2, &[3]), (3, &[1])]), &[1, 2, 3, 1]);
+    }
+
+    #[test]
+    fn reports_only_the_prefabs_in_the_cycle() {
+        expect_cycle(cook_order(&[(1, &[2]), (2, &[3]), (3, &[4]), (4, &[2])]), &[2, 3, 4, 2]);
+    }
+
+    #[test]
+    fn allows_a_prefab_to_be_referenced_more_than_once() {
+        let order = cook_order(&[(1, &[2, 2, 3]), (3, &[2])]).unwrap();
+        assert_eq!(order, vec![prefab_id(2), prefab_id(3), prefab_id(1)]);
+    }
+
+    fn entity_uuid(n: u8) -> EntityUuid {
+        [n; 16]
+    }
+
+    #[test]
+    fn cooked_entity_uuids_are_stable() {
+        // Overrides on nested prefabs are keyed by these, so they must never change
+        let cooked = cooked_entity_uuid(Some([7; 16]), entity_uuid(1));
+        assert_eq!(
+            uuid::Uuid::from_bytes(cooked).to_string(),
+            "29b938ff-b447-5ee4-8f8d-706e6400f470"
+        );
+
+        let nested = cooked_entity_uuid(Some([8; 16]), cooked);
+        assert_eq!(
+            uuid::Uuid::from_bytes(nested).to_string(),
+            "d84dd0de-255d-522d-bfcf-44540f34f7ef"
+        );
+    }
+
+    #[test]
+    fn cooked_entity_uuids_are_unique_per_instance() {
+        let a = cooked_entity_uuid(Some([7; 16]), entity_uuid(1));
+        let b = cooked_entity_uuid(Some([8; 16]), entity_uuid(1));
+        let c = cooked_entity_uuid(Some([7; 16]), entity_uuid(2));
+        assert_ne!(a, b);
+        assert_ne!(a, c);
+        assert_ne!(a, entity_uuid(1));
+
+        // The prefab's own entities keep their UUIDs
+        assert_eq!(cooked_entity_uuid(None, entity_uuid(1)), entity_uuid(1));
+    }
+
+    // Prefab 1 has one entity, entity_uuid(1), with a UniformScale2DComponent of 1.0
+    fn base_prefab(universe: &Universe) -> PrefabAsset {
+        use crate::components::UniformScale2DComponent;
+
+        let mut world = universe.create_world();
+        let entity = world.insert((), vec![(UniformScale2DComponent { uniform_scale: 1.0 },)])[0];
+        let mut entities = HashMap::new();
+        entities.insert(entity_uuid(1), entity);
+
+        PrefabAsset {
+            prefab: legion_prefab::Prefab {
+                world,
+                prefab_meta: legion_prefab::PrefabMeta {
+                    id: prefab_id(1).0,
+                    prefab_refs: Default::default(),
+                    entities,
+                },
+            },
+            instances: HashMap::new(),
+        }
+    }
+
+    // Cooks prefab 2, which has no entities of its own and references prefab 1 once per instance,
+    // with the given overrides on the given entity
+    fn cook_instances(
+        instances: &[(PrefabInstanceId, EntityUuid, Vec<PrefabOverride>)],
+    ) -> Result<CookedPrefab, CookError> {
+        let universe = Universe::new();
+        let base = base_prefab(&universe);
+        let level = PrefabAsset {
+            prefab: legion_prefab::Prefab {
+                world: universe.create_world(),
+                prefab_meta: legion_prefab::PrefabMeta {
+                    id: prefab_id(2).0,
+                    prefab_refs: Default::default(),
+                    entities: HashMap::new(),
+                },
+            },
+            instances: instances
+                .iter()
+                .map(|(instance_id, entity_id, overrides)| {
+                    let mut entity_overrides = HashMap::new();
+                    entity_overrides.insert(*entity_id, overrides.clone());
+                    let instance = crate::pipeline::PrefabInstance {
+                        prefab_id: prefab_id(1),
+                        overrides: entity_overrides,
+                    };
+                    (*instance_id, instance)
+                })
+                .collect(),
+        };
+
+        let mut prefabs = HashMap::new();
+        prefabs.insert(prefab_id(1), (&base, 0));
+        prefabs.insert(prefab_id(2), (&level, 0));
+        cook_prefab_with_cache(
+            &universe,
+            &crate::create_component_registry(),
+            &crate::create_component_registry_by_uuid(),
+            prefab_id(2),
+            &prefabs,
+            &mut PrefabCookCache::default(),
+            None,
+        )
+    }
+
+    // Returns the uniform scale of the base prefab's entity in the given instance, or None if the
+    // entity has no UniformScale2DComponent. Panics if the entity was removed
+    fn uniform_scale(
+        cooked_prefab: &CookedPrefab,
+        instance_id: PrefabInstanceId,
+    ) -> Option<f32> {
+        use crate::components::UniformScale2DComponent;
+
+        let entity = cooked_prefab.entities[&cooked_entity_uuid(Some(instance_id), entity_uuid(1))];
+        cooked_prefab
+            .world
+            .get_component::<UniformScale2DComponent>(entity)
+            .map(|component| component.uniform_scale)
+    }
+
+    fn uniform_scale_type() -> ComponentTypeUuid {
+        use type_uuid::TypeUuid;
+        crate::components::UniformScale2DComponent::UUID
+    }
+
+    #[test]
+    fn applies_overrides_to_each_instance_separately() {
+        let patch = PrefabOverride::PatchComponent {
+            component_type: uniform_scale_type(),
+            schema_version: 1,
+            diff: r#"[ Enter(Field("uniform_scale")), Value(3.0) ]"#.to_string(),
+        };
+
+        let cooked_prefab = cook_instances(&[
+            ([10; 16], entity_uuid(1), vec![patch]),
+            ([11; 16], entity_uuid(1), vec![]),
+            ([12; 16], entity_uuid(1), vec![PrefabOverride::RemoveEntity]),
+        ])
+        .unwrap();
+
+        assert_eq!(uniform_scale(&cooked_prefab, [10; 16]), Some(3.0));
+        assert_eq!(uniform_scale(&cooked_prefab, [11; 16]), Some(1.0));
+        assert_eq!(cooked_prefab.entities.len(), 2);
+        assert!(!cooked_prefab
+            .entities
+            .contains_key(&cooked_entity_uuid(Some([12; 16]), entity_uuid(1))));
+    }
+
+    #[test]
+    fn applies_overrides_in_order() {
+        let remove = PrefabOverride::RemoveComponent {
+            component_type: uniform_scale_type(),
+        };
+        let add = PrefabOverride::AddComponent {
+            component_type: uniform_scale_type(),
+            schema_version: 1,
+            data: "(uniform_scale: 2.0)".to_string(),
+        };
+
+        let cooked_prefab = cook_instances(&[
+            ([10; 16], entity_uuid(1), vec![remove.clone()]),
+            ([11; 16], entity_uuid(1), vec![remove, add]),
+        ])
+        .unwrap();
+
+        assert_eq!(uniform_scale(&cooked_prefab, [10; 16]), None);
+        assert_eq!(uniform_scale(&cooked_prefab, [11; 16]), Some(2.0));
+    }
+
+    #[test]
+    fn fails_on_an_override_for_a_missing_entity() {
+        let remove = PrefabOverride::RemoveEntity;
+        match cook_instances(&[([10; 16], entity_uuid(9), vec![remove])]) {
+            Err(CookError::MissingEntity {
+                prefab_id: missing_from,
+                entity_id,
+            }) => {
+                assert_eq!(missing_from, prefab_id(2));
+                assert_eq!(entity_id, entity_uuid(9));
+            }
+            other => panic!("expected a missing entity, got {:?}", other.err()),
+        }
+    }
 }
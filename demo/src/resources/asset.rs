@@ -1,13 +1,110 @@
-use atelier_loader::{handle::RefOp, rpc_loader::RpcLoader, Loader};
+use atelier_loader::{crossbeam_channel::Receiver, handle::RefOp, rpc_loader::RpcLoader, Loader};
+use atelier_loader::{AssetStorage, AssetTypeId, LoadHandle, LoadInfo, LoadStatus};
+use atelier_core::AssetUuid;
 
+use std::error::Error;
 use std::sync::Arc;
 
-use crate::asset_storage::GenericAssetStorage;
+use crate::asset_storage::{AssetPostProcess, GenericAssetStorage};
+pub use crate::asset_storage::{AssetEvent, AssetEventKind, ReplacedVersionRef};
+use crate::pack_loader::PackLoader;
 
 use type_uuid::TypeUuid;
 
+/// Where AssetResource gets asset data from
+pub enum AssetSource {
+    /// Request assets from a running asset daemon
+    Rpc(RpcLoader),
+
+    /// Load assets from a pack file, so no daemon is needed
+    Pack(PackLoader),
+}
+
+// The loader for an AssetSource. PackLoader skips AssetLoadOp and needs GenericAssetStorage, so
+// only AssetResource::update can process this. It's crate-private so nothing else can call
+// Loader::process on it
+pub(crate) struct AssetLoader(AssetSource);
+
+impl Loader for AssetLoader {
+    fn get_load(
+        &self,
+        id: AssetUuid,
+    ) -> Option<LoadHandle> {
+        match &self.0 {
+            AssetSource::Rpc(loader) => loader.get_load(id),
+            AssetSource::Pack(loader) => loader.get_load(id),
+        }
+    }
+
+    fn get_load_info(
+        &self,
+        load: LoadHandle,
+    ) -> Option<LoadInfo> {
+        match &self.0 {
+            AssetSource::Rpc(loader) => loader.get_load_info(load),
+            AssetSource::Pack(loader) => loader
+                .get_load_refs(load)
+                .map(|(asset_id, refs)| LoadInfo { asset_id, refs }),
+        }
+    }
+
+    fn get_load_status(
+        &self,
+        load: LoadHandle,
+    ) -> LoadStatus {
+        match &self.0 {
+            AssetSource::Rpc(loader) => loader.get_load_status(load),
+            AssetSource::Pack(loader) => loader.get_load_status(load),
+        }
+    }
+
+    fn add_ref(
+        &self,
+        id: AssetUuid,
+    ) -> LoadHandle {
+        match &self.0 {
+            AssetSource::Rpc(loader) => loader.add_ref(id),
+            AssetSource::Pack(loader) => loader.add_ref(id),
+        }
+    }
+
+    fn get_asset(
+        &self,
+        load: LoadHandle,
+    ) -> Option<(AssetTypeId, LoadHandle)> {
+        match &self.0 {
+            AssetSource::Rpc(loader) => loader.get_asset(load),
+            AssetSource::Pack(loader) => loader
+                .get_asset_type(load)
+                .map(|asset_type| (asset_type, load)),
+        }
+    }
+
+    fn remove_ref(
+        &self,
+        load: LoadHandle,
+    ) {
+        match &self.0 {
+            AssetSource::Rpc(loader) => loader.remove_ref(load),
+            AssetSource::Pack(loader) => loader.remove_ref(load),
+        }
+    }
+
+    fn process(
+        &mut self,
+        asset_storage: &dyn AssetStorage,
+    ) -> Result<(), Box<dyn Error>> {
+        match &mut self.0 {
+            AssetSource::Rpc(loader) => loader.process(asset_storage),
+            AssetSource::Pack(_) => {
+                Err("PackLoader can only be processed by AssetResource::update".into())
+            }
+        }
+    }
+}
+
 pub struct AssetResource {
-    loader: RpcLoader,
+    loader: AssetLoader,
     storage: GenericAssetStorage,
     tx: Arc<atelier_loader::crossbeam_channel::Sender<RefOp>>,
     rx: atelier_loader::crossbeam_channel::Receiver<RefOp>,
@@ -15,37 +112,63 @@ pub struct AssetResource {
 
 impl Default for AssetResource {
     fn default() -> Self {
+        Self::new(AssetSource::Rpc(RpcLoader::default()))
+    }
+}
+
+impl AssetResource {
+    pub fn new(source: AssetSource) -> Self {
         let (tx, rx) = atelier_loader::crossbeam_channel::unbounded();
         let tx = Arc::new(tx);
         let storage = GenericAssetStorage::new(tx.clone());
 
-        let loader = RpcLoader::default();
-
         AssetResource {
-            loader,
+            loader: AssetLoader(source),
             storage,
             tx,
             rx,
         }
     }
-}
 
-impl AssetResource {
     pub fn add_storage<T: TypeUuid + for<'a> serde::Deserialize<'a> + 'static + Send>(&mut self) {
         self.storage.add_storage::<T>();
     }
 
+    pub fn add_async_storage<T: TypeUuid + for<'a> serde::Deserialize<'a> + 'static + Send>(
+        &mut self,
+        post_process: Option<AssetPostProcess<T>>,
+    ) {
+        self.storage.add_async_storage::<T>(post_process);
+    }
+
+    /// Returns a receiver for Loaded, Reloaded, Freed and Failed events for assets of the given
+    /// type. Systems should drain it every frame
+    pub fn subscribe<T: TypeUuid + 'static>(&self) -> Receiver<AssetEvent<T>> {
+        self.storage.subscribe::<T>()
+    }
+
     pub fn update(&mut self) {
+        self.storage.begin_frame();
         atelier_loader::handle::process_ref_ops(&self.loader, &self.rx);
-        self.loader
-            .process(&self.storage)
-            .expect("failed to process loader");
+        match &mut self.loader.0 {
+            AssetSource::Rpc(loader) => loader.process(&self.storage),
+            AssetSource::Pack(loader) => loader.process(&self.storage),
+        }
+        .expect("failed to process loader");
     }
 
-    pub fn loader(&self) -> &RpcLoader {
+    pub(crate) fn loader(&self) -> &AssetLoader {
         &self.loader
     }
 
+    /// Returns true if assets come from a pack file rather than the asset daemon
+    pub fn is_pack(&self) -> bool {
+        match self.loader.0 {
+            AssetSource::Pack(_) => true,
+            AssetSource::Rpc(_) => false,
+        }
+    }
+
     pub fn storage(&self) -> &GenericAssetStorage {
         &self.storage
     }
@@ -0,0 +1,140 @@
+use legion::prelude::*;
+use atelier_core::AssetUuid;
+use atelier_loader::{
+    handle::{AssetHandle, Handle},
+    LoadStatus, Loader,
+};
+use legion_prefab::CookedPrefab;
+
+use crate::pipeline::CookedPrefabAsset;
+use crate::prefab_cooking::{PrefabCookCache, PrefabCookJob};
+use crate::resources::{AssetLoader, AssetResource, UniverseResource};
+
+// A prefab that has been requested but hasn't been spawned yet
+enum PendingPrefab {
+    // Pack files hold a CookedPrefabAsset for every prefab (see pack_file::cook_prefabs)
+    Cooked(Handle<CookedPrefabAsset>),
+
+    // The daemon only serves uncooked prefabs, so they're loaded and cooked here
+    Uncooked(PrefabCookJob),
+}
+
+/// Spawns prefabs without opening them in the editor. When assets come from a pack file, this
+/// spawns the CookedPrefabAsset that was cooked when the pack was built, so the uncooked prefabs
+/// aren't needed. Otherwise the prefab and everything it references is loaded from the daemon and
+/// cooked, so the spawned prefab always reflects the current version of each of them
+#[derive(Default)]
+pub struct CookedPrefabResource {
+    // Prefabs that have been requested but haven't loaded yet
+    pending: Vec<(AssetUuid, PendingPrefab)>,
+
+    // Prefabs cooked from daemon assets. A prefab is only cooked again if it or something it
+    // references has a new version
+    prefab_cook_cache: PrefabCookCache,
+}
+
+impl CookedPrefabResource {
+    /// Starts loading the given prefab. It's spawned into the world by spawn_loaded_prefabs once it
+    /// has loaded
+    pub fn spawn_prefab(
+        &mut self,
+        asset_resource: &AssetResource,
+        prefab_uuid: AssetUuid,
+    ) {
+        let pending_prefab = if asset_resource.is_pack() {
+            let cooked_prefab_uuid = CookedPrefabAsset::asset_uuid_for_prefab(prefab_uuid);
+            let load_handle = asset_resource.loader().add_ref(cooked_prefab_uuid);
+            let handle = Handle::<CookedPrefabAsset>::new(asset_resource.tx().clone(), load_handle);
+            PendingPrefab::Cooked(handle)
+        } else {
+            PendingPrefab::Uncooked(PrefabCookJob::new(asset_resource, prefab_uuid))
+        };
+
+        self.pending.push((prefab_uuid, pending_prefab));
+    }
+
+    /// Spawns all requested prefabs that have finished loading into the world
+    pub fn spawn_loaded_prefabs(
+        world: &mut World,
+        resources: &Resources,
+    ) {
+        let asset_resource = resources.get::<AssetResource>().unwrap();
+        let component_registry = crate::create_component_registry();
+
+        let mut loaded = vec![];
+        let mut cooked = vec![];
+        {
+            let mut cooked_prefab_resource = resources.get_mut::<CookedPrefabResource>().unwrap();
+            let pending = std::mem::replace(&mut cooked_prefab_resource.pending, vec![]);
+            for (prefab_uuid, mut pending_prefab) in pending {
+                let still_pending = match &mut pending_prefab {
+                    PendingPrefab::Cooked(handle) => {
+                        match handle.load_status::<AssetLoader>(asset_resource.loader()) {
+                            LoadStatus::Loaded => {
+                                loaded.push(handle.clone());
+                                false
+                            }
+                            LoadStatus::DoesNotExist | LoadStatus::Error(_) => {
+                                log::error!(
+                                    "Failed to load the cooked prefab for {:?}",
+                                    prefab_uuid
+                                );
+                                false
+                            }
+                            _ => true,
+                        }
+                    }
+                    PendingPrefab::Uncooked(cook_job) => {
+                        match cook_job.update(&*asset_resource) {
+                            Ok(()) if !cook_job.is_ready_to_cook() => true,
+                            Ok(()) => {
+                                let universe = resources.get::<UniverseResource>().unwrap();
+                                match cook_job.cook(
+                                    &*universe,
+                                    &*asset_resource,
+                                    &component_registry,
+                                    &crate::create_component_registry_by_uuid(),
+                                    &mut cooked_prefab_resource.prefab_cook_cache,
+                                    None,
+                                ) {
+                                    Ok(cooked_prefab) => cooked.push(cooked_prefab),
+                                    Err(e) => {
+                                        log::error!("Failed to cook prefab {}: {}", prefab_uuid, e)
+                                    }
+                                }
+                                false
+                            }
+                            Err(e) => {
+                                log::error!("Failed to load prefab {}: {}", prefab_uuid, e);
+                                false
+                            }
+                        }
+                    }
+                };
+
+                if still_pending {
+                    cooked_prefab_resource.pending.push((prefab_uuid, pending_prefab));
+                }
+            }
+        }
+
+        if loaded.is_empty() && cooked.is_empty() {
+            return;
+        }
+
+        let clone_impl = crate::create_spawn_clone_impl(&component_registry, resources);
+        let spawn = |world: &mut World, cooked_prefab: &CookedPrefab| {
+            world.clone_from(&cooked_prefab.world, &clone_impl, None, None);
+        };
+
+        for handle in loaded {
+            if let Some(cooked_prefab_asset) = handle.asset(asset_resource.storage()) {
+                spawn(world, &cooked_prefab_asset.cooked_prefab);
+            }
+        }
+
+        for cooked_prefab in &cooked {
+            spawn(world, cooked_prefab);
+        }
+    }
+}
@@ -6,15 +6,17 @@ use crate::resources::SimulationTimePauseReason;
 use atelier_core::AssetUuid;
 use legion_prefab::{CookedPrefab, ComponentRegistration, Prefab};
 use std::sync::Arc;
+use std::net::SocketAddr;
 use crate::resources::time::TimeState;
 use atelier_loader::handle::{TypedAssetStorage, AssetHandle};
-use crate::pipeline::PrefabAsset;
+use crate::pipeline::{PrefabAsset, PrefabFileFormat, PrefabInstance};
 use crate::component_diffs::{ComponentDiff, apply_diff_to_prefab, WorldDiff};
 use prefab_format::{ComponentTypeUuid, EntityUuid};
 use itertools::Itertools;
 use std::collections::vec_deque;
 use crate::clone_merge::CopyCloneImpl;
 use crate::transactions::{TransactionBuilder, TransactionDiffs, TransactionEntityInfo, Transaction};
+use crate::prefab_cooking::{CookProvenance, PrefabCookCache, PrefabCookJob, PrefabInstanceId};
 use imgui::ImString;
 
 #[derive(Clone, Copy)]
@@ -35,6 +37,9 @@ enum EditorOp {
     /// Save the current pre-play state to the currently open prefab file
     SavePrefab,
 
+    /// Write the current pre-play state of the opened prefab as JSON
+    ExportPrefabJson,
+
     /// Unpauses the simulation, allowing in-editor testing
     Play,
 
@@ -119,11 +124,20 @@ pub struct OpenedPrefabState {
     /// on disk changing)
     prefab_handle: atelier_loader::handle::Handle<PrefabAsset>,
 
+    /// Handles to the prefabs that the opened prefab references (directly or indirectly) and the
+    /// versions that were cooked. Holding the handles keeps the prefabs loaded so that changes to
+    /// them can be detected and trigger a reload
+    referenced_prefabs: HashMap<AssetUuid, (atelier_loader::handle::Handle<PrefabAsset>, u32)>,
+
     /// The opened prefab in uncooked form. Any diffs that are applied to the world also get applied
     /// to the prefab and cooked prefab so that when we save data, we can just persist this field.
     /// Long-term, the responsibility for this would be on the asset daemon
     uncooked_prefab: Arc<Prefab>,
 
+    /// The references to other prefabs made by the opened prefab, kept so that they're written
+    /// back, with their instance IDs, when the prefab is saved
+    instances: Arc<HashMap<PrefabInstanceId, PrefabInstance>>,
+
     /// The opened prefab in cooked form. This is used for reloads and applying edits against
     cooked_prefab: Arc<CookedPrefab>,
 
@@ -139,6 +153,10 @@ impl OpenedPrefabState {
         &self.cooked_prefab
     }
 
+    pub fn cook_provenance(&self) -> &Arc<CookProvenance> {
+        &self.cook_provenance
+    }
+
     pub fn prefab_to_world_mappings(&self) -> &HashMap<Entity, Entity> {
         &self.prefab_to_world_mappings
     }
@@ -152,6 +170,16 @@ impl OpenedPrefabState {
     }
 }
 
+/// A prefab that is being loaded and cooked. When the cook finishes, it replaces the opened prefab
+struct PendingPrefabOpen {
+    /// Loads the prefab and the prefabs it references over multiple frames
+    cook_job: PrefabCookJob,
+
+    /// If this is a reload of the opened prefab, the entities that were selected when the reload
+    /// started. Otherwise None, and the world and undo history are cleared when the cook finishes
+    reload_selected_uuids: Option<HashSet<EntityUuid>>,
+}
+
 /// Diffs that are pending being applied
 struct TransactionDiffsPendingApply {
     /// The diffs required to apply/revert the transaction
@@ -187,6 +215,26 @@ pub struct EditorStateResource {
     // If a prefab is opened, this holds the state associated with editing it
     opened_prefab: Option<Arc<OpenedPrefabState>>,
 
+    // If a prefab is being loaded and cooked, this tracks its progress. opened_prefab is not
+    // replaced until the cook finishes
+    pending_prefab_open: Option<PendingPrefabOpen>,
+
+    // If the last attempt to open a prefab failed, this describes why. It's shown to the user
+    // until dismissed
+    open_prefab_error: Option<String>,
+
+    // If the last attempt to save the opened prefab failed, this describes why. It's shown to the
+    // user until dismissed
+    save_prefab_error: Option<String>,
+
+    // The daemon that imports the opened prefab, used to find the file to save it to. This is None
+    // when assets come from a pack file, since there are no source files to save to
+    daemon_address: Option<SocketAddr>,
+
+    // Prefabs that have been cooked, so that reloading a prefab only re-cooks the prefabs that
+    // changed and the prefabs that reference them
+    prefab_cook_cache: PrefabCookCache,
+
     // We queue important operations to happen as many of them require taking fairly invasive
     // mut references to the world and resources. Each frame we drain this and execute each
     // operation
@@ -218,7 +266,7 @@ pub struct EditorStateResource {
 }
 
 impl EditorStateResource {
-    pub fn new() -> Self {
+    pub fn new(daemon_address: Option<SocketAddr>) -> Self {
         EditorStateResource {
             editor_mode: EditorMode::Inactive,
             window_options_running: WindowOptions::new_runtime(),
@@ -226,6 +274,11 @@ impl EditorStateResource {
             active_editor_tool: EditorTool::Translate,
             add_component_search_text: ImString::with_capacity(255),
             opened_prefab: None,
+            pending_prefab_open: None,
+            open_prefab_error: None,
+            save_prefab_error: None,
+            daemon_address,
+            prefab_cook_cache: Default::default(),
             pending_editor_ops: Default::default(),
 
             diffs_pending_apply: Default::default(),
@@ -254,6 +307,33 @@ impl EditorStateResource {
         self.opened_prefab.clone()
     }
 
+    /// If a prefab is being opened, returns the number of prefabs that have loaded and the number
+    /// that have been requested so far
+    pub fn pending_prefab_open_progress(&self) -> Option<(usize, usize)> {
+        self.pending_prefab_open.as_ref().map(|pending_prefab_open| {
+            (
+                pending_prefab_open.cook_job.loaded_prefab_count(),
+                pending_prefab_open.cook_job.requested_prefab_count(),
+            )
+        })
+    }
+
+    pub fn open_prefab_error(&self) -> Option<&str> {
+        self.open_prefab_error.as_ref().map(|x| x.as_str())
+    }
+
+    pub fn clear_open_prefab_error(&mut self) {
+        self.open_prefab_error = None;
+    }
+
+    pub fn save_prefab_error(&self) -> Option<&str> {
+        self.save_prefab_error.as_ref().map(|x| x.as_str())
+    }
+
+    pub fn clear_save_prefab_error(&mut self) {
+        self.save_prefab_error = None;
+    }
+
     pub fn is_editor_active(&self) -> bool {
         self.editor_mode != EditorMode::Inactive
     }
@@ -312,56 +392,145 @@ impl EditorStateResource {
         };
     }
 
+    /// Starts loading and cooking the given prefab. It will replace the opened prefab once
+    /// update_pending_prefab_open() finishes the cook
     pub fn open_prefab(
-        world: &mut World,
         resources: &Resources,
         prefab_uuid: AssetUuid,
     ) {
-        {
-            let mut asset_resource = resources.get_mut::<AssetResource>().unwrap();
+        Self::begin_prefab_open(resources, prefab_uuid, None);
+    }
 
-            use atelier_loader::Loader;
-            use atelier_loader::handle::AssetHandle;
+    fn begin_prefab_open(
+        resources: &Resources,
+        prefab_uuid: AssetUuid,
+        reload_selected_uuids: Option<HashSet<EntityUuid>>,
+    ) {
+        let asset_resource = resources.get::<AssetResource>().unwrap();
+        let mut editor_state = resources.get_mut::<EditorStateResource>().unwrap();
+
+        // Any prefab that was in the middle of opening is dropped, releasing its handles
+        editor_state.pending_prefab_open = Some(PendingPrefabOpen {
+            cook_job: PrefabCookJob::new(&*asset_resource, prefab_uuid),
+            reload_selected_uuids,
+        });
+    }
 
-            let load_handle = asset_resource.loader().add_ref(prefab_uuid);
-            let handle = atelier_loader::handle::Handle::<crate::pipeline::PrefabAsset>::new(
-                asset_resource.tx().clone(),
-                load_handle,
-            );
+    /// Advances the pending prefab open, if there is one. When its cook finishes, the cooked prefab
+    /// replaces the opened prefab and is spawned into the world
+    pub fn update_pending_prefab_open(
+        world: &mut World,
+        resources: &Resources,
+    ) {
+        // Advance the cook job and take it once it's ready to cook (or has failed)
+        let pending_prefab_open = {
+            let asset_resource = resources.get::<AssetResource>().unwrap();
+            let mut editor_state = resources.get_mut::<EditorStateResource>().unwrap();
 
-            let version = loop {
-                asset_resource.update();
-                if let atelier_loader::LoadStatus::Loaded = handle
-                    .load_status::<atelier_loader::rpc_loader::RpcLoader>(
-                    asset_resource.loader(),
-                ) {
-                    break handle
-                        .asset_version::<PrefabAsset, _>(asset_resource.storage())
-                        .unwrap();
+            let update_result = match &mut editor_state.pending_prefab_open {
+                Some(pending_prefab_open) => {
+                    pending_prefab_open.cook_job.update(&*asset_resource)
                 }
+                None => return,
             };
 
+            if let Err(e) = update_result {
+                let prefab_uuid = editor_state
+                    .pending_prefab_open
+                    .take()
+                    .unwrap()
+                    .cook_job
+                    .prefab_uuid();
+                log::error!("Failed to load prefab {}: {}", prefab_uuid, e);
+                editor_state.open_prefab_error =
+                    Some(format!("Failed to load prefab {}:\n{}", prefab_uuid, e));
+                return;
+            }
+
+            let is_ready_to_cook = editor_state
+                .pending_prefab_open
+                .as_ref()
+                .unwrap()
+                .cook_job
+                .is_ready_to_cook();
+
+            if !is_ready_to_cook {
+                return;
+            }
+
+            editor_state.pending_prefab_open.take().unwrap()
+        };
+
+        let cook_job = &pending_prefab_open.cook_job;
+        let prefab_uuid = cook_job.prefab_uuid();
+
+        {
+            let asset_resource = resources.get::<AssetResource>().unwrap();
             let mut editor_state = resources.get_mut::<EditorStateResource>().unwrap();
 
-            // Load the uncooked prefab from disk and cook it. (Eventually this will be handled
-            // during atelier's build step
-            let mut universe = resources.get_mut::<UniverseResource>().unwrap();
-            let cooked_prefab = Arc::new(crate::prefab_cooking::cook_prefab(
+            // Cook the loaded prefabs, tracking which prefab each component came from
+            let universe = resources.get::<UniverseResource>().unwrap();
+            let component_registry = editor_state.component_registry.clone();
+            let component_registry_by_uuid = editor_state.component_registry_by_uuid.clone();
+            let mut cook_provenance = CookProvenance::default();
+            let cooked_prefab = match cook_job.cook(
                 &*universe,
-                &mut *asset_resource,
-                &editor_state.component_registry,
-                &editor_state.component_registry_by_uuid,
-                prefab_uuid,
-            ));
+                &*asset_resource,
+                &component_registry,
+                &component_registry_by_uuid,
+                &mut editor_state.prefab_cook_cache,
+                Some(&mut cook_provenance),
+            ) {
+                Ok(cooked_prefab) => Arc::new(cooked_prefab),
+                Err(e) => {
+                    log::error!("Failed to cook prefab {}: {}", prefab_uuid, e);
+                    editor_state.open_prefab_error =
+                        Some(format!("Failed to cook prefab {}:\n{}", prefab_uuid, e));
+                    return;
+                }
+            };
+
+            let handle = cook_job.prefab_handle().unwrap().clone();
+            let version = handle
+                .asset_version::<PrefabAsset, _>(asset_resource.storage())
+                .unwrap();
+
+            let mut referenced_prefabs = HashMap::new();
+            for (referenced_prefab_uuid, referenced_prefab_handle) in cook_job.loaded_prefabs() {
+                if *referenced_prefab_uuid != prefab_uuid {
+                    let referenced_prefab_version = referenced_prefab_handle
+                        .asset_version::<PrefabAsset, _>(asset_resource.storage())
+                        .unwrap();
+                    referenced_prefabs.insert(
+                        *referenced_prefab_uuid,
+                        (referenced_prefab_handle.clone(), referenced_prefab_version),
+                    );
+                }
+            }
 
             // Duplicate the prefab data so we can apply diffs to it. This is temporary and will eventually be
             // done within the daemon. (This is kind of like a clone() on the uncooked prefab asset)
             let noop_diff = WorldDiff::new(vec![], vec![]);
+            let prefab_asset = handle.asset(asset_resource.storage()).unwrap();
             let uncooked_prefab = Arc::new(crate::component_diffs::apply_diff_to_prefab(
-                &handle.asset(asset_resource.storage()).unwrap().prefab,
+                &prefab_asset.prefab,
                 &universe.universe,
                 &noop_diff,
             ));
+            let instances = Arc::new(prefab_asset.instances.clone());
+
+            if pending_prefab_open.reload_selected_uuids.is_some() {
+                // Delete the old stuff from the world
+                if let Some(opened_prefab) = &editor_state.opened_prefab {
+                    for x in opened_prefab.prefab_to_world_mappings.values() {
+                        world.delete(*x);
+                    }
+                }
+            } else {
+                // This is a different prefab, so start over with an empty world and no undo history
+                editor_state.clear_undo_history();
+                *world = universe.universe.create_world();
+            }
 
             // Store the cooked prefab and relevant metadata in an Arc on the EditorStateResource.
             // Eventually the cooked prefab data would be held by AssetStorage and we'd just hold
@@ -370,16 +539,27 @@ impl EditorStateResource {
                 uuid: prefab_uuid,
                 version,
                 prefab_handle: handle,
+                referenced_prefabs,
                 uncooked_prefab,
+                instances,
                 cooked_prefab,
+                cook_provenance: Arc::new(cook_provenance),
                 prefab_to_world_mappings: Default::default(),
                 world_to_prefab_mappings: Default::default(),
             };
 
             editor_state.opened_prefab = Some(Arc::new(opened_prefab));
+            editor_state.open_prefab_error = None;
         }
 
         Self::reset(world, resources);
+
+        // Restore selection
+        if let Some(selected_uuids) = &pending_prefab_open.reload_selected_uuids {
+            let mut editor_state = resources.get_mut::<EditorStateResource>().unwrap();
+            let mut selection_resource = resources.get_mut::<EditorSelectionResource>().unwrap();
+            editor_state.restore_selected_uuids(&mut *selection_resource, world, selected_uuids);
+        }
     }
 
     fn reset(
@@ -405,7 +585,8 @@ impl EditorStateResource {
         // If a prefab is opened, reset all the data
         if let Some(opened_prefab) = opened_prefab {
             let mut prefab_to_world_mappings = HashMap::default();
-            let clone_impl = crate::create_spawn_clone_impl(resources);
+            let component_registry = crate::create_component_registry();
+            let clone_impl = crate::create_spawn_clone_impl(&component_registry, resources);
             world.clone_from(
                 &opened_prefab.cooked_prefab.world,
                 &clone_impl,
@@ -434,8 +615,11 @@ impl EditorStateResource {
             let new_opened_prefab = OpenedPrefabState {
                 uuid: opened_prefab.uuid,
                 cooked_prefab: opened_prefab.cooked_prefab.clone(),
+                cook_provenance: opened_prefab.cook_provenance.clone(),
                 prefab_handle: opened_prefab.prefab_handle.clone(),
+                referenced_prefabs: opened_prefab.referenced_prefabs.clone(),
                 uncooked_prefab: opened_prefab.uncooked_prefab.clone(),
+                instances: opened_prefab.instances.clone(),
                 version: opened_prefab.version,
                 prefab_to_world_mappings,
                 world_to_prefab_mappings,
@@ -454,6 +638,10 @@ impl EditorStateResource {
         self.pending_editor_ops.push(EditorOp::SavePrefab);
     }
 
+    pub fn enqueue_export_prefab_json(&mut self) {
+        self.pending_editor_ops.push(EditorOp::ExportPrefabJson);
+    }
+
     pub fn enqueue_play(&mut self) {
         self.pending_editor_ops.push(EditorOp::Play);
     }
@@ -514,22 +702,15 @@ impl EditorStateResource {
             .collect();
         for editor_op in editor_ops {
             match editor_op {
-                EditorOp::OpenPrefab(asset_uuid) => {
-                    let new_world = {
-                        let mut editor_state = resources.get_mut::<EditorStateResource>().unwrap();
-                        editor_state.clear_undo_history();
-
-                        let universe = resources.get::<UniverseResource>().unwrap();
-                        let world = universe.universe.create_world();
-                        world
-                    };
-                    *world = new_world;
-                    Self::open_prefab(world, resources, asset_uuid)
-                }
+                EditorOp::OpenPrefab(asset_uuid) => Self::open_prefab(resources, asset_uuid),
                 EditorOp::SavePrefab => {
                     let mut editor_state = resources.get_mut::<EditorStateResource>().unwrap();
                     editor_state.save();
                 }
+                EditorOp::ExportPrefabJson => {
+                    let editor_state = resources.get::<EditorStateResource>().unwrap();
+                    editor_state.export_json();
+                }
                 EditorOp::Play => {
                     let mut editor_state = resources.get_mut::<EditorStateResource>().unwrap();
                     let mut time_state = resources.get_mut::<TimeResource>().unwrap();
@@ -631,17 +812,33 @@ impl EditorStateResource {
         world: &mut World,
         resources: &Resources,
     ) {
-        // Detect if we need to reload. Do this comparing the prefab asset's version with the cooked prefab's version
+        // Detect if we need to reload. Do this comparing the versions of the prefab asset and the
+        // prefabs it references with the versions that were cooked
         let mut prefab_to_reload = None;
         {
             let editor_state = resources.get::<EditorStateResource>().unwrap();
+
+            // If a prefab is already being opened, wait for it to finish
+            if editor_state.pending_prefab_open.is_some() {
+                return;
+            }
+
             if let Some(opened_prefab) = &editor_state.opened_prefab {
                 let mut asset_resource = resources.get_mut::<AssetResource>().unwrap();
                 let version = opened_prefab
                     .prefab_handle
                     .asset_version::<PrefabAsset, _>(asset_resource.storage())
                     .unwrap();
-                if opened_prefab.version != version {
+
+                let referenced_prefab_changed = opened_prefab
+                    .referenced_prefabs
+                    .values()
+                    .any(|(handle, cooked_version)| {
+                        handle.asset_version::<PrefabAsset, _>(asset_resource.storage())
+                            != Some(*cooked_version)
+                    });
+
+                if opened_prefab.version != version || referenced_prefab_changed {
                     prefab_to_reload = Some(opened_prefab.clone());
                 }
             }
@@ -658,18 +855,8 @@ impl EditorStateResource {
                 editor_state.get_selected_uuids(&*selection_resource, world)
             };
 
-            // Delete the old stuff from the world
-            for x in opened_prefab.prefab_to_world_mappings.values() {
-                world.delete(*x);
-            }
-
-            // re-cook and load the prefab
-            Self::open_prefab(world, resources, opened_prefab.uuid);
-
-            // Restore selection
-            let mut editor_state = resources.get_mut::<EditorStateResource>().unwrap();
-            let mut selection_resource = resources.get_mut::<EditorSelectionResource>().unwrap();
-            editor_state.restore_selected_uuids(&mut *selection_resource, world, &selected_uuids);
+            // re-cook and load the prefab. The old data stays in the world until the cook finishes
+            Self::begin_prefab_open(resources, opened_prefab.uuid, Some(selected_uuids));
         }
     }
 
@@ -873,8 +1060,11 @@ impl EditorStateResource {
                 let new_opened_prefab = OpenedPrefabState {
                     uuid: opened_prefab.uuid,
                     cooked_prefab: new_cooked_prefab,
+                    cook_provenance: opened_prefab.cook_provenance.clone(),
                     prefab_handle: opened_prefab.prefab_handle.clone(),
+                    referenced_prefabs: opened_prefab.referenced_prefabs.clone(),
                     uncooked_prefab: new_uncooked_prefab,
+                    instances: opened_prefab.instances.clone(),
                     version: opened_prefab.version,
                     prefab_to_world_mappings: Default::default(), // These will get populated by reset()
                     world_to_prefab_mappings: Default::default(), // These will get populated by reset()
@@ -920,14 +1110,43 @@ impl EditorStateResource {
     }
 
     fn save(&mut self) {
+        if let Err(e) = self.write_opened_prefab() {
+            log::error!("{}", e);
+            self.save_prefab_error = Some(e);
+        }
+    }
+
+    // Persists the uncooked prefab to the file it was imported from. The daemon notices the change
+    // and imports it again
+    fn write_opened_prefab(&self) -> Result<(), String> {
         //
         // Check that a prefab is opened
         //
-        if self.opened_prefab.is_none() {
-            return;
-        }
+        let opened_prefab = match &self.opened_prefab {
+            Some(opened_prefab) => opened_prefab,
+            None => return Ok(()),
+        };
 
-        let opened_prefab = self.opened_prefab.as_ref().unwrap();
+        //
+        // Find the file the prefab was imported from
+        //
+        let daemon_address = self.daemon_address.ok_or_else(|| {
+            format!(
+                "Failed to save prefab {}: assets are loaded from a pack file, so there is no \
+                 source file to save to",
+                opened_prefab.uuid
+            )
+        })?;
+
+        let path = crate::daemon_client::find_source_path(daemon_address, opened_prefab.uuid)
+            .map_err(|e| format!("Failed to save prefab {}: {}", opened_prefab.uuid, e))?
+            .ok_or_else(|| {
+                format!(
+                    "Failed to save prefab {}: the daemon doesn't know which file it was \
+                     imported from",
+                    opened_prefab.uuid
+                )
+            })?;
 
         //
         // Persist the uncooked prefab to disk
@@ -937,22 +1156,62 @@ impl EditorStateResource {
             registered_components,
         };
 
-        let mut ron_ser = ron::ser::Serializer::new(Some(ron::ser::PrettyConfig::default()), true);
-        let prefab_ser = legion_prefab::PrefabFormatSerializer::new(
-            &prefab_serde_context,
+        // Keep the file in the format it's already in so that it matches its importer options
+        let format = std::fs::read(&path)
+            .map(|existing| PrefabFileFormat::detect(&existing))
+            .unwrap_or_default();
+
+        let output = crate::pipeline::write_prefab(
             &opened_prefab.uncooked_prefab,
-        );
-        prefab_format::serialize(
-            &mut ron_ser,
-            &prefab_ser,
-            opened_prefab.uncooked_prefab.prefab_id(),
+            &opened_prefab.instances,
+            format,
+            &prefab_serde_context,
         )
-        .expect("failed to round-trip prefab");
-        let output = ron_ser.into_output_string();
-        log::trace!("Exporting prefab:");
-        log::trace!("{}", output);
+        .map_err(|e| format!("Failed to save prefab {}: {}", opened_prefab.uuid, e))?;
+        log::trace!("Exporting prefab as {:?}:", format);
+        if format == PrefabFileFormat::Ron {
+            log::trace!("{}", String::from_utf8_lossy(&output));
+        }
+
+        std::fs::write(&path, output)
+            .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+        log::info!("Saved prefab {} to {}", opened_prefab.uuid, path.display());
+        Ok(())
+    }
 
-        std::fs::write("assets/demo_level.prefab", output).unwrap();
+    // Writes the opened prefab as JSON. This goes outside the assets directory, otherwise the
+    // daemon would import it as a second copy of the same prefab
+    fn export_json(&self) {
+        let opened_prefab = match &self.opened_prefab {
+            Some(opened_prefab) => opened_prefab,
+            None => return,
+        };
+
+        let registered_components = crate::create_component_registry_by_uuid();
+        let prefab_serde_context = legion_prefab::PrefabSerdeContext {
+            registered_components,
+        };
+
+        let output = match crate::pipeline::write_prefab(
+            &opened_prefab.uncooked_prefab,
+            &opened_prefab.instances,
+            PrefabFileFormat::Json,
+            &prefab_serde_context,
+        ) {
+            Ok(output) => output,
+            Err(e) => {
+                log::error!("Failed to export prefab {} as JSON: {}", opened_prefab.uuid, e);
+                return;
+            }
+        };
+
+        let path = format!("exported/{}.prefab.json", opened_prefab.uuid);
+        let result =
+            std::fs::create_dir_all("exported").and_then(|_| std::fs::write(&path, output));
+        match result {
+            Ok(_) => log::info!("Exported prefab {} to {}", opened_prefab.uuid, path),
+            Err(e) => log::error!("Failed to write {}: {}", path, e),
+        }
     }
 
     pub fn create_empty_transaction(
@@ -3,6 +3,12 @@ pub use fps_text::FpsTextResource;
 
 mod asset;
 pub use asset::AssetResource;
+pub use asset::{AssetEvent, AssetEventKind, ReplacedVersionRef};
+pub use asset::AssetSource;
+pub(crate) use asset::AssetLoader;
+
+mod cooked_prefab;
+pub use cooked_prefab::CookedPrefabResource;
 
 mod canvas_draw;
 pub use canvas_draw::CanvasDrawResource;
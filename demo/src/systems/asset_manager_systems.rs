@@ -1,5 +1,5 @@
 use legion::prelude::*;
-use crate::resources::AssetResource;
+use crate::resources::{AssetResource, CookedPrefabResource};
 
 pub fn update_asset_manager() -> Box<dyn Schedulable> {
     SystemBuilder::new("update asset manager")
@@ -8,3 +8,10 @@ pub fn update_asset_manager() -> Box<dyn Schedulable> {
             asset_manager.update();
         })
 }
+
+pub fn spawn_loaded_cooked_prefabs(
+    world: &mut World,
+    resources: &mut Resources,
+) {
+    CookedPrefabResource::spawn_loaded_prefabs(world, resources);
+}
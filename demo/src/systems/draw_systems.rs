@@ -8,9 +8,11 @@ use crate::components::{
 };
 use crate::components::DrawSkiaBoxComponent;
 use crate::components::DrawSkiaCircleComponent;
+use crate::components::DrawSkiaSpriteComponent;
 
 use crate::resources::{
     CanvasDrawResource, CameraResource, InputResource, ViewportResource, DebugDrawResource,
+    AssetResource,
 };
 use crate::resources::ImguiResource;
 use crate::resources::FpsTextResource;
@@ -27,6 +29,7 @@ pub fn draw() -> Box<dyn Schedulable> {
         .write_resource::<ViewportResource>()
         .read_resource::<InputResource>()
         .write_resource::<DebugDrawResource>()
+        .read_resource::<AssetResource>()
         .with_query(<(
             Read<Position2DComponent>,
             Read<DrawSkiaBoxComponent>,
@@ -40,11 +43,26 @@ pub fn draw() -> Box<dyn Schedulable> {
             TryRead<UniformScale2DComponent>,
             TryRead<Rotation2DComponent>,
         )>::query())
+        .with_query(<(
+            Read<Position2DComponent>,
+            Read<DrawSkiaSpriteComponent>,
+            TryRead<UniformScale2DComponent>,
+            TryRead<NonUniformScale2DComponent>,
+            TryRead<Rotation2DComponent>,
+        )>::query())
         .build(
             |_,
              world,
-             (draw_context, fps_text, camera_state, viewport_state, input_resource, debug_draw),
-             (draw_boxes_query, draw_circles_query)| {
+             (
+                draw_context,
+                fps_text,
+                camera_state,
+                viewport_state,
+                input_resource,
+                debug_draw,
+                asset_resource,
+            ),
+             (draw_boxes_query, draw_circles_query, draw_sprites_query)| {
                 draw_context.with_canvas(|canvas, coordinate_system_helper| {
                     let window_size = input_resource.window_size();
                     let camera_position = camera_state.position;
@@ -129,6 +147,62 @@ pub fn draw() -> Box<dyn Schedulable> {
                         );
                     }
 
+                    // Draw all the sprites
+                    for (pos, skia_sprite, uniform_scale, non_uniform_scale, rotation) in
+                        draw_sprites_query.iter(world)
+                    {
+                        // Sprites whose image hasn't loaded yet are skipped
+                        let (image, src_rect) = match skia_sprite.skia_image(&*asset_resource) {
+                            Some(image) => image,
+                            None => continue,
+                        };
+
+                        let mut half_extents = *skia_sprite.half_extents;
+                        if let Some(uniform_scale) = uniform_scale {
+                            half_extents *= uniform_scale.uniform_scale;
+                        }
+
+                        if let Some(non_uniform_scale) = non_uniform_scale {
+                            half_extents *= *non_uniform_scale.non_uniform_scale;
+                        }
+
+                        let rotation_in_degrees = if let Some(rotation) = rotation {
+                            rotation.rotation * 180.0 / std::f32::consts::PI
+                        } else {
+                            0.0
+                        };
+
+                        canvas.save();
+                        canvas.translate(skia_safe::Vector::new(
+                            pos.position.x(),
+                            pos.position.y(),
+                        ));
+                        canvas.rotate(rotation_in_degrees, None);
+
+                        // The visible range puts +y at the top of the screen, so flip the image to
+                        // keep it upright
+                        canvas.scale((1.0, -1.0));
+
+                        let mut paint = skia_safe::Paint::default();
+                        paint.set_filter_quality(skia_safe::FilterQuality::Low);
+
+                        canvas.draw_image_rect(
+                            &image,
+                            src_rect.as_ref().map(|src_rect| {
+                                (src_rect, skia_safe::canvas::SrcRectConstraint::Strict)
+                            }),
+                            skia_safe::Rect {
+                                left: -half_extents.x(),
+                                right: half_extents.x(),
+                                top: -half_extents.y(),
+                                bottom: half_extents.y(),
+                            },
+                            &paint,
+                        );
+
+                        canvas.restore();
+                    }
+
                     // Debug draw
                     for line_list in debug_draw.take_line_lists() {
                         if line_list.points.len() < 2 {
@@ -187,6 +187,51 @@ pub fn editor_inspector_window(
                                 );
                             }
                         }
+
+                        //
+                        // Show which prefabs the selected entities and their components came from
+                        //
+                        if ui.collapsing_header(im_str!("Provenance")).build() {
+                            let cook_provenance = opened_prefab.cook_provenance();
+                            for selected_entity in selection_world.selected_entities() {
+                                let entity_uuid = opened_prefab
+                                    .world_to_prefab_mappings()
+                                    .get(selected_entity)
+                                    .and_then(|x| prefab_entity_to_uuid.get(x));
+
+                                let entity_uuid = match entity_uuid {
+                                    Some(entity_uuid) => entity_uuid,
+                                    None => continue,
+                                };
+
+                                ui.text(im_str!(
+                                    "Entity {}",
+                                    uuid::Uuid::from_bytes(*entity_uuid)
+                                ));
+
+                                if let Some(source) = cook_provenance.entity_source(entity_uuid) {
+                                    ui.text(im_str!("  Defined in {}", source));
+                                }
+
+                                for component_type in cook_provenance.component_types(entity_uuid)
+                                {
+                                    let type_name = editor_ui_state
+                                        .component_registry_by_uuid()
+                                        .get(component_type)
+                                        .map(|x| x.type_name())
+                                        .unwrap_or("unknown component");
+
+                                    let writers = cook_provenance
+                                        .component_writers(entity_uuid, component_type)
+                                        .iter()
+                                        .map(|x| x.to_string())
+                                        .collect::<Vec<_>>()
+                                        .join(" -> ");
+
+                                    ui.text(im_str!("  {}: {}", type_name, writers));
+                                }
+                            }
+                        }
                     });
             }
         });
@@ -52,6 +52,24 @@ fn imgui_menu_tool_button(
     }
 }
 
+// Shows an error message in its own window. Returns true if the user dismissed it
+fn imgui_error_window(
+    ui: &imgui::Ui,
+    title: &imgui::ImStr,
+    message: &str,
+) -> bool {
+    let mut dismissed = false;
+    imgui::Window::new(title)
+        .position([350.0, 50.0], imgui::Condition::Once)
+        .size([450.0, 150.0], imgui::Condition::Once)
+        .build(ui, || {
+            ui.text_wrapped(&im_str!("{}", message));
+            dismissed = ui.button(im_str!("Dismiss"), [80.0, 0.0]);
+        });
+
+    dismissed
+}
+
 pub fn editor_imgui_menu() -> Box<dyn Schedulable> {
     SystemBuilder::new("editor_imgui_menu")
         .write_resource::<ImguiResource>()
@@ -76,6 +94,19 @@ pub fn editor_imgui_menu() -> Box<dyn Schedulable> {
                     }
                 }
 
+                // If opening or saving a prefab failed, show why until the user dismisses it
+                if let Some(open_prefab_error) = editor_state.open_prefab_error() {
+                    if imgui_error_window(ui, im_str!("Error"), open_prefab_error) {
+                        editor_state.clear_open_prefab_error();
+                    }
+                }
+
+                if let Some(save_prefab_error) = editor_state.save_prefab_error() {
+                    if imgui_error_window(ui, im_str!("Save Error"), save_prefab_error) {
+                        editor_state.clear_save_prefab_error();
+                    }
+                }
+
                 ui.main_menu_bar(|| {
                     //axis-arrow
                     imgui_menu_tool_button(
@@ -99,6 +130,10 @@ pub fn editor_imgui_menu() -> Box<dyn Schedulable> {
                         if imgui::MenuItem::new(im_str!("Save")).build(ui) {
                             editor_state.enqueue_save_prefab();
                         }
+
+                        if imgui::MenuItem::new(im_str!("Export as JSON")).build(ui) {
+                            editor_state.enqueue_export_prefab_json();
+                        }
                     });
 
                     ui.menu(imgui::im_str!("Edit"), true, || {
@@ -157,6 +192,11 @@ pub fn editor_imgui_menu() -> Box<dyn Schedulable> {
                     if time_state.is_simulation_paused() {
                         ui.text(im_str!("SIMULATION PAUSED"));
                     }
+
+                    if let Some((loaded, requested)) = editor_state.pending_prefab_open_progress()
+                    {
+                        ui.text(im_str!("LOADING PREFABS {}/{}", loaded, requested));
+                    }
                 });
             });
         })
@@ -79,6 +79,13 @@ pub fn reload_editor_state_if_file_changed(
     EditorStateResource::hot_reload_if_asset_changed(world, resources);
 }
 
+pub fn editor_update_pending_prefab_open(
+    world: &mut World,
+    resources: &mut Resources,
+) {
+    EditorStateResource::update_pending_prefab_open(world, resources);
+}
+
 pub fn editor_process_edit_diffs(
     world: &mut World,
     resources: &mut Resources,
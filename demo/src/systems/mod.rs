@@ -7,6 +7,7 @@ pub use physics_systems::read_from_physics;
 
 mod asset_manager_systems;
 pub use asset_manager_systems::update_asset_manager;
+pub use asset_manager_systems::spawn_loaded_cooked_prefabs;
 
 mod app_control_systems;
 pub use app_control_systems::quit_if_escape_pressed;
@@ -34,6 +35,7 @@ pub use editor_systems::editor_entity_list_window;
 pub use editor_systems::editor_process_selection_ops;
 pub use editor_systems::editor_inspector_window;
 pub use editor_systems::reload_editor_state_if_file_changed;
+pub use editor_systems::editor_update_pending_prefab_open;
 pub use editor_systems::editor_process_edit_diffs;
 
 use legion::prelude::*;
@@ -136,6 +138,7 @@ pub fn create_update_schedule(criteria: &ScheduleCriteria) -> Schedule {
         .always(advance_time)
         .always(quit_if_escape_pressed)
         .always(update_asset_manager)
+        .always_thread_local(spawn_loaded_cooked_prefabs)
         .always(update_fps_text)
         .always(update_physics)
         .simulation_unpaused_only(read_from_physics)
@@ -144,6 +147,7 @@ pub fn create_update_schedule(criteria: &ScheduleCriteria) -> Schedule {
         .always_thread_local(editor_refresh_selection_world)
         // Editor input
         .always_thread_local(reload_editor_state_if_file_changed)
+        .always_thread_local(editor_update_pending_prefab_open)
         .always(editor_keybinds)
         .always(editor_mouse_input)
         .always(editor_update_editor_draw)
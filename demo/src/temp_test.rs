@@ -1,8 +1,7 @@
-use crate::resources::AssetResource;
+use crate::resources::{AssetLoader, AssetResource};
 use atelier_loader::{
     asset_uuid,
     handle::{AssetHandle, Handle},
-    rpc_loader::RpcLoader,
     LoadStatus, Loader,
 };
 use std::collections::HashMap;
@@ -76,7 +75,8 @@ pub fn temp_force_load_asset(asset_manager: &mut AssetResource) {
         let handle = Handle::<Position2DComponentDef>::new(asset_manager.tx().clone(), handle);
         loop {
             asset_manager.update();
-            if let LoadStatus::Loaded = handle.load_status::<RpcLoader>(asset_manager.loader()) {
+            if let LoadStatus::Loaded = handle.load_status::<AssetLoader>(asset_manager.loader())
+            {
                 let custom_asset: &Position2DComponentDef = handle
                     .asset(asset_manager.storage())
                     .expect("failed to get asset");
@@ -108,7 +108,8 @@ pub fn temp_force_load_asset(asset_manager: &mut AssetResource) {
         let handle = Handle::<PrefabAsset>::new(asset_manager.tx().clone(), handle);
         loop {
             asset_manager.update();
-            if let LoadStatus::Loaded = handle.load_status::<RpcLoader>(asset_manager.loader()) {
+            if let LoadStatus::Loaded = handle.load_status::<AssetLoader>(asset_manager.loader())
+            {
                 break;
             }
         }
@@ -140,7 +141,7 @@ pub fn temp_force_load_asset(asset_manager: &mut AssetResource) {
         println!("--- CLONE MERGE 1 ---");
         println!("This test just clones Position2DComponentDef");
         let resources = Resources::default();
-        let clone_merge_impl = SpawnCloneImpl::new(registered_components.clone(), &resources);
+        let clone_merge_impl = SpawnCloneImpl::new(&registered_components, &resources);
         world.clone_from(&prefab_asset.prefab.world, &clone_merge_impl, None, None);
 
         println!("MERGED: iterate positions");
@@ -156,7 +157,7 @@ pub fn temp_force_load_asset(asset_manager: &mut AssetResource) {
 
         println!("--- CLONE MERGE 2 ---");
         println!("This test transforms Position2DComponentDef into Position2DComponent");
-        let mut clone_merge_impl = SpawnCloneImpl::new(registered_components.clone(), &resources);
+        let mut clone_merge_impl = SpawnCloneImpl::new(&registered_components, &resources);
         clone_merge_impl.add_mapping_into::<Position2DComponentDef, Position2DComponent>();
 
         clone_merge_impl.add_mapping_closure::<Position2DComponentDef, Position2DComponent, _>(
@@ -191,7 +192,7 @@ pub fn temp_force_load_asset(asset_manager: &mut AssetResource) {
 
         println!("--- CLONE MERGE 3 ---");
         println!("This test demonstrates replacing existing entities rather than making new ones");
-        let mut clone_merge_impl = SpawnCloneImpl::new(registered_components.clone(), &resources);
+        let mut clone_merge_impl = SpawnCloneImpl::new(&registered_components, &resources);
         clone_merge_impl.add_mapping_into::<Position2DComponentDef, Position2DComponent>();
 
         // Get a list of entities in the prefab
@@ -271,11 +272,14 @@ pub fn temp_force_prefab_cook(asset_manager: &mut AssetResource) {
     let prefab_asset_id = asset_uuid!("5fd8256d-db36-4fe2-8211-c7b3446e1927");
 
     let universe = Universe::new();
-    crate::prefab_cooking::cook_prefab(
+    if let Err(e) = crate::prefab_cooking::cook_prefab(
         &universe,
         asset_manager,
         &registered_components,
         &registered_components_by_uuid,
         prefab_asset_id,
-    );
+        None,
+    ) {
+        log::error!("Failed to cook prefab {}: {}", prefab_asset_id, e);
+    }
 }
@@ -4,8 +4,10 @@ use prefab_format::{EntityUuid, ComponentTypeUuid};
 use std::collections::HashMap;
 use std::collections::HashSet;
 use legion_prefab::{ComponentRegistration, DiffSingleResult};
+use serde::{Deserialize, Serialize};
 use crate::component_diffs::{
-    DiffSingleSerializerAcceptor, ComponentDiff, EntityDiff, EntityDiffOp, WorldDiff,
+    ComponentDataFormat, DiffSingleSerializerAcceptor, ComponentDiff, EntityDiff, EntityDiffOp,
+    WorldDiff,
 };
 
 struct TransactionBuilderEntityInfo {
@@ -106,7 +108,7 @@ pub struct Transaction {
     uuid_to_entities: HashMap<EntityUuid, TransactionEntityInfo>,
 }
 
-#[derive(Clone)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct TransactionDiffs {
     apply_diff: WorldDiff,
     revert_diff: WorldDiff,
@@ -212,6 +214,9 @@ impl Transaction {
         let mut apply_component_diffs = vec![];
         let mut revert_component_diffs = vec![];
 
+        // The diffs record the format their data is written in, so it can be read back later
+        let format = ComponentDataFormat::default();
+
         // Iterate the entities in the selection world and prefab world and genereate diffs for
         // each component type.
         for (entity_uuid, entity_info) in &self.uuid_to_entities {
@@ -227,7 +232,7 @@ impl Transaction {
                     result: &mut apply_result,
                 };
                 let mut apply_data = vec![];
-                bincode::with_serializer(&mut apply_data, apply_acceptor);
+                format.with_serializer(&mut apply_data, apply_acceptor);
 
                 if apply_result != DiffSingleResult::NoChange {
                     let mut revert_result = DiffSingleResult::NoChange;
@@ -240,7 +245,7 @@ impl Transaction {
                         result: &mut revert_result,
                     };
                     let mut revert_data = vec![];
-                    bincode::with_serializer(&mut revert_data, revert_acceptor);
+                    format.with_serializer(&mut revert_data, revert_acceptor);
 
                     apply_component_diffs.push(
                         ComponentDiff::new_from_diff_single_result(
@@ -248,6 +253,7 @@ impl Transaction {
                             *component_type,
                             apply_result,
                             apply_data,
+                            format,
                         )
                         .unwrap(),
                     );
@@ -258,6 +264,7 @@ impl Transaction {
                             *component_type,
                             revert_result,
                             revert_data,
+                            format,
                         )
                         .unwrap(),
                     );